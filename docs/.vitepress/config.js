@@ -169,6 +169,8 @@ export default defineConfig({
             { text: 'Connectors', link: '/api/core/connectors' },
             { text: 'Param', link: '/api/core/param' },
             { text: 'Selection', link: '/api/core/selection' },
+            { text: 'History', link: '/api/core/history' },
+            { text: 'State', link: '/api/core/state' },
             { text: 'Multi-Database Support', link: '/api/core/multi-database-support' }
           ]
         },
@@ -190,6 +192,7 @@ export default defineConfig({
           text: 'Mosaic Inputs',
           collapsed: true,
           items: [
+            { text: 'KPI', link: '/api/inputs/kpi' },
             { text: 'Menu', link: '/api/inputs/menu' },
             { text: 'Search', link: '/api/inputs/search' },
             { text: 'Slider', link: '/api/inputs/slider' },
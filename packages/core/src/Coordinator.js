@@ -1,7 +1,9 @@
+import { Query, isQuery } from '@uwdata/mosaic-sql';
 import { socketConnector } from './connectors/socket.js';
 import { DataCubeIndexer } from './DataCubeIndexer.js';
 import { QueryManager, Priority } from './QueryManager.js';
 import { queryFieldInfo } from './util/field-info.js';
+import { mutatedTables, queryTables, tableName } from './util/query-tables.js';
 import { voidLogger } from './util/void-logger.js';
 
 /**
@@ -32,9 +34,19 @@ export function coordinator(instance) {
  * @param {object} [options] Coordinator options.
  * @param {*} [options.logger=console] The logger to use, defaults to `console`.
  * @param {*} [options.manager] The query manager to use.
- * @param {boolean} [options.cache=true] Boolean flag to enable/disable query caching.
+ * @param {boolean | object} [options.cache=true] Boolean flag to
+ *  enable/disable query caching, or an options object for the query cache.
  * @param {boolean} [options.consolidate=true] Boolean flag to enable/disable query consolidation.
+ * @param {number | number[]} [options.concurrency] The maximum number of
+ *  concurrently submitted queries, either a single limit or an array of
+ *  per-priority limits. Defaults to the query manager setting.
+ * @param {boolean | object} [options.progressive=false] Progressive query
+ *  mode options. If enabled, clients that support approximate results are
+ *  first updated with the result of a sampled query.
  * @param {object} [options.indexes] Data cube indexer options.
+ * @param {boolean | object} [options.prebuild=false] Data cube index
+ *  prebuilding options. If enabled, data cube index tables for registered
+ *  selection clauses are speculatively built once the coordinator is idle.
  */
 export class Coordinator {
   constructor(db = socketConnector(), {
@@ -42,15 +54,28 @@ export class Coordinator {
     manager = new QueryManager(),
     cache = true,
     consolidate = true,
-    indexes = {}
+    concurrency,
+    progressive = false,
+    indexes = {},
+    prebuild = false
   } = {}) {
     this.manager = manager;
-    this.manager.cache(cache);
+    // scope persisted cache entries by the queried database
+    const persist = cache?.persist && typeof cache.get !== 'function';
+    this.manager.cache(persist ? { database: db.id, ...cache } : cache);
     this.manager.consolidate(consolidate);
+    this.manager.concurrency(concurrency);
+    this.progressive(progressive);
     this.dataCubeIndexer = new DataCubeIndexer(this, indexes);
+    this.manager.idle(() => schedulePrebuild(this));
+    this.prebuild(prebuild);
+    this._connectionListener = state => updateConnection(this, state);
+    this._notifyListener = message => updateTable(this, message);
+    /** @type {Map<string, { incremental: boolean }>} */
+    this._live = new Map;
     this.logger(logger);
     this.databaseConnector(db);
-    this.clear();
+    this.clear({ cache: false });
   }
 
   /**
@@ -66,19 +91,164 @@ export class Coordinator {
       this.filterGroups = new Map;
       this.clients?.forEach(client => this.disconnect(client));
       this.clients = new Set;
+      this.clientRequests = new Map;
     }
     if (cache) this.manager.cache().clear();
   }
 
   /**
-   * Get or set the database connector.
+   * Return statistics for the client-side query cache.
+   * @returns {{
+   *  entries: number, bytes: number,
+   *  hits: number, misses: number, evictions: number
+   * }} The number of cached entries, their approximate total size in bytes,
+   *  and the counts of cache hits, misses, and evictions.
+   */
+  cacheStats() {
+    return this.manager.cache().stats();
+  }
+
+  /**
+   * Invalidate cached query results and data cube index tables that depend
+   * on the given tables. Use this method after tables are modified outside
+   * of this coordinator, for example by an external ingestion process.
+   * Modifications issued through the `exec` method are tracked automatically.
+   * Cached results of SQL string queries, whose tables are not known, are
+   * always invalidated.
+   * @param {string | string[]} [tables] The modified table name(s). If
+   *  unspecified, all cached results and index tables are invalidated.
+   */
+  invalidate(tables) {
+    const names = tables == null ? null : [tables].flat().map(tableName);
+    this.manager.cache().invalidate?.(names);
+    this.dataCubeIndexer.invalidate(names);
+  }
+
+  /**
+   * Subscribe to modifications of the given (live) tables. When a table
+   * is modified, cached query results over the table are invalidated and
+   * connected clients whose queries read the table are updated. If the
+   * database connector supports notifications (such as the socket connector
+   * with a Mosaic data server), modifications by any database user are
+   * reported. Otherwise, only modifications issued via `exec` are tracked.
+   * @param {string | string[]} tables The table name(s).
+   * @param {object} [options] Subscription options.
+   * @param {boolean} [options.incremental=true] If true, data cube index
+   *  tables are updated incrementally when rows are appended to a table,
+   *  rather than dropped and rebuilt.
+   */
+  subscribe(tables, { incremental = true } = {}) {
+    const names = [tables].flat().map(tableName);
+    names.forEach(name => this._live.set(name, { incremental }));
+    this.manager.connector()?.subscribe?.(names);
+  }
+
+  /**
+   * Unsubscribe from modifications of the given tables.
+   * @param {string | string[]} tables The table name(s).
+   */
+  unsubscribe(tables) {
+    const names = [tables].flat().map(tableName);
+    names.forEach(name => this._live.delete(name));
+    this.manager.connector()?.unsubscribe?.(names);
+  }
+
+  /**
+   * Get or set the database connector. If the connector emits connection
+   * `state` events, state changes are passed on to all connected clients.
+   * If the connector emits table modification `notify` events, subscribed
+   * live tables are updated.
    * @param {*} [db] The database connector to use.
    * @returns The current database connector.
    */
   databaseConnector(db) {
+    if (db) {
+      const listener = this._connectionListener;
+      const notify = this._notifyListener;
+      const tables = Array.from(this._live.keys());
+      const prev = this.manager.connector();
+      prev?.removeEventListener?.('state', listener);
+      prev?.removeEventListener?.('notify', notify);
+      if (tables.length) prev?.unsubscribe?.(tables);
+      db.addEventListener?.('state', listener);
+      db.addEventListener?.('notify', notify);
+      if (tables.length) db.subscribe?.(tables);
+    }
     return this.manager.connector(db);
   }
 
+  /**
+   * Get the database connector serving a table. For a routing connector,
+   * this is the connector the table is routed to. Otherwise, this is the
+   * current database connector.
+   * @param {string} table The (possibly schema-qualified) table name.
+   * @returns {object} The database connector.
+   */
+  locate(table) {
+    const db = this.manager.connector();
+    return db?.locate?.(table) ?? db;
+  }
+
+  /**
+   * The current database connection state, if reported by the connector.
+   * For example, the socket connector reports one of `'connecting'`,
+   * `'open'`, `'reconnecting'`, or `'closed'`.
+   * @returns {string | undefined} The connection state.
+   */
+  get connectionState() {
+    return this.manager.connector()?.state;
+  }
+
+  /**
+   * Get or set the progressive query mode. If enabled, client updates first
+   * issue a sampled query and pass its approximate result to the client, then
+   * refine the client with the result of the full query. Only clients that
+   * support approximate results (see `MosaicClient.progressive`) are updated
+   * progressively. If a new update for a client arrives, its outstanding
+   * queries are canceled.
+   * @param {boolean | { size?: number, method?: string }} [value] A Boolean
+   *  flag or sampling options. The `size` option is either a fraction of
+   *  rows (between 0 and 1) or a number of rows to sample (default `0.01`).
+   *  The `method` option is the sampling method (default `'reservoir'`).
+   * @returns {{ size: number, method: string } | null} The current sampling
+   *  options, or null if progressive mode is disabled.
+   */
+  progressive(value) {
+    if (value !== undefined) {
+      this._progressive = value
+        ? { size: 0.01, method: 'reservoir', ...(value === true ? {} : value) }
+        : null;
+    }
+    return this._progressive;
+  }
+
+  /**
+   * Get or set the data cube index prebuilding mode. If enabled, once the
+   * coordinator has been idle (with no queued or in-flight queries) for a
+   * given delay, data cube index tables are built at low priority for
+   * connected clients and the selection clauses registered by interactors
+   * (see `Selection.register`). An interactor's first activation can then
+   * use a prebuilt index table rather than wait for one to be created.
+   * @param {boolean | {
+   *  delay?: number, maxTables?: number, maxRows?: number
+   * }} [value] A Boolean flag or prebuild options. The `delay` option is
+   *  the idle time in milliseconds to wait before prebuilding (default
+   *  `1000`). The `maxTables` (default `10`) and `maxRows` (default `1e7`)
+   *  options set a budget for the number of prebuilt tables and their total
+   *  row count.
+   * @returns {{ delay: number, maxTables: number, maxRows: number } | null}
+   *  The current prebuild options, or null if prebuilding is disabled.
+   */
+  prebuild(value) {
+    if (value !== undefined) {
+      this._prebuild = value
+        ? { delay: 1000, maxTables: 10, maxRows: 1e7, ...(value === true ? {} : value) }
+        : null;
+      schedulePrebuild(this);
+    }
+    return this._prebuild;
+  }
+
   /**
    * Get or set the logger.
    * @param {*} logger  The logger to use.
@@ -95,8 +265,10 @@ export class Coordinator {
   // -- Query Management ----
 
   /**
-   * Cancel previosuly submitted query requests. These queries will be
-   * canceled if they are queued but have not yet been submitted.
+   * Cancel previosuly submitted query requests. Queued queries are dropped.
+   * Queries that have already been submitted to the database are interrupted
+   * if the database connector supports cancellation, in which case the
+   * request is rejected with the error reported by the connector.
    * @param {import('./util/query-result.js').QueryResult[]} requests An array
    *  of query result objects, such as those returned by the `query` method.
    */
@@ -105,17 +277,36 @@ export class Coordinator {
   }
 
   /**
-   * Issue a query for which no result (return value) is needed.
+   * Issue a query for which no result (return value) is needed. If the
+   * query modifies tables, cached query results and data cube index tables
+   * that depend on those tables are invalidated.
    * @param {import('@uwdata/mosaic-sql').Query | string} query The query.
    * @param {object} [options] An options object.
    * @param {number} [options.priority] The query priority, defaults to
    *  `Priority.Normal`.
+   * @param {string} [options.route] The name of a table whose database
+   *  connector should run the query, for use with routing connectors.
    * @returns {import('./util/query-result.js').QueryResult} A query result
    *  promise.
    */
-  exec(query, { priority = Priority.Normal } = {}) {
+  exec(query, { priority = Priority.Normal, ...options } = {}) {
     query = Array.isArray(query) ? query.join(';\n') : query;
-    return this.manager.request({ type: 'exec', query }, priority);
+    const result = this.manager.request({ type: 'exec', query, options }, priority);
+    // drop index tables over modified tables once the modification is done
+    // cached query results are invalidated by the query manager
+    const tables = mutatedTables(`${query}`);
+    if (tables.length) {
+      this.dataCubeIndexer.invalidate(tables, { priority });
+      // without connector notifications, update live tables locally
+      const live = tables.filter(table => this._live.has(table));
+      if (live.length && !this.manager.connector()?.subscribe) {
+        result.then(
+          () => live.forEach(table => updateTable(this, { table })),
+          () => {} // errors are handled by the caller
+        );
+      }
+    }
+    return result;
   }
 
   /**
@@ -127,6 +318,16 @@ export class Coordinator {
    * @param {boolean} [options.cache=true] If true, cache the query result.
    * @param {number} [options.priority] The query priority, defaults to
    *  `Priority.Normal`.
+   * @param {(batch: import('apache-arrow').Table) => void} [options.onBatch]
+   *  A callback invoked with each Arrow record batch as it arrives. If
+   *  specified, an Arrow query result is streamed from the database in
+   *  batches, provided the database connector supports streaming. The
+   *  returned promise still resolves to the complete result table.
+   * @param {string} [options.route] The name of a table whose database
+   *  connector should run the query, for use with routing connectors.
+   * @param {object | false} [options.trace] Query telemetry metadata, such
+   *  as the `client` and `selection` that triggered the query. If false,
+   *  the query is not traced.
    * @returns {import('./util/query-result.js').QueryResult} A query result
    *  promise.
    */
@@ -134,9 +335,71 @@ export class Coordinator {
     type = 'arrow',
     cache = true,
     priority = Priority.Normal,
+    onBatch,
+    trace,
     ...options
   } = {}) {
-    return this.manager.request({ type, query, cache, options }, priority);
+    return this.manager.request(
+      { type, query, cache, onBatch, trace, options },
+      priority
+    );
+  }
+
+  /**
+   * Request the query plan of a query, as reported by the database's
+   * `EXPLAIN` statement.
+   * @param {import('@uwdata/mosaic-sql').Query | string} query The query.
+   * @param {object} [options] An options object.
+   * @param {boolean} [options.analyze=true] If true, the query is run and
+   *  the plan is annotated with profiling information (`EXPLAIN ANALYZE`).
+   * @returns {Promise<string>} A promise for the query plan text.
+   */
+  async explain(query, { analyze = true } = {}) {
+    const rows = await this.query(
+      `EXPLAIN ${analyze ? 'ANALYZE ' : ''}${query}`,
+      { type: 'json', cache: false, priority: Priority.Low, trace: false }
+    );
+    return Array.from(rows, row => row.explain_value).join('\n');
+  }
+
+  /**
+   * Add an event listener. Listeners for `'query'` events receive query
+   * telemetry objects once query requests settle. Each telemetry object has
+   * the following properties:
+   * - `type`, `sql`, `priority`: The query type, SQL text, and priority.
+   * - `client`, `selection`: The client and selection that triggered the
+   *   query, or null if unknown.
+   * - `cube`: True if the query is answered by a data cube index table.
+   * - `sample`: True if the query is a sampled progressive query.
+   * - `cached`: True if the result was retrieved from the client cache.
+   * - `consolidated`: True if the query was consolidated with others.
+   * - `status`: One of `'done'`, `'error'`, or `'canceled'`.
+   * - `error`: The query error, or null.
+   * - `queued`: The request timestamp, via `performance.now()`.
+   * - `wait`: The time in milliseconds spent queued, or null if the query
+   *   was never submitted.
+   * - `time`: The time in milliseconds from submission to completion.
+   * - `bytes`: The size of the query result in bytes. This is the byte
+   *   length reported by the database connector, such as the size of the
+   *   received Arrow IPC data, if available, and otherwise approximated.
+   *
+   * Queries are only traced while query event listeners are registered.
+   * Requests with a `trace` option of `false`, such as those issued by
+   * `explain()`, are not traced.
+   * @param {'query'} type The event type.
+   * @param {(trace: object) => void} callback The listener callback.
+   */
+  addEventListener(type, callback) {
+    this.manager.addEventListener(type, callback);
+  }
+
+  /**
+   * Remove an event listener.
+   * @param {'query'} type The event type.
+   * @param {(trace: object) => void} callback The listener callback.
+   */
+  removeEventListener(type, callback) {
+    this.manager.removeEventListener(type, callback);
   }
 
   /**
@@ -152,6 +415,30 @@ export class Coordinator {
     return this.query(query, { ...options, cache: true, priority: Priority.Low });
   }
 
+  /**
+   * Pull the result of a query on a remote database into a local table,
+   * for database connectors that support pulls such as the hybrid
+   * connector. Tables are only pulled upon request, the coordinator does
+   * not pull tables automatically. The pull is issued as a query request,
+   * and so can be canceled and is included in query telemetry.
+   * @param {string} table The local table name.
+   * @param {import('@uwdata/mosaic-sql').Query | string} query The query to
+   *  run on the remote database.
+   * @param {object} [options] An options object.
+   * @param {number} [options.priority] The query priority, defaults to
+   *  `Priority.Normal`.
+   * @returns {import('./util/query-result.js').QueryResult} A query result
+   *  promise for the pulled Arrow table.
+   */
+  pull(table, query, options = {}) {
+    if (typeof this.databaseConnector().pull !== 'function') {
+      throw new Error('Database connector does not support pulls.');
+    }
+    return this.query(query, {
+      ...options, type: 'arrow', cache: false, pull: table
+    });
+  }
+
   createBundle(name, queries, priority = Priority.Low) {
     const options = { name, queries };
     return this.manager.request({ type: 'create-bundle', options }, priority);
@@ -170,14 +457,66 @@ export class Coordinator {
    * @param {import('./MosaicClient.js').MosaicClient} client A Mosaic client.
    * @param {import('@uwdata/mosaic-sql').Query | string} query The data query.
    * @param {number} [priority] The query priority.
+   * @param {object} [options] Update options.
+   * @param {boolean} [options.progressive=true] If false, do not issue a
+   *  sampled query even if progressive mode is enabled.
+   * @param {object} [options.trace] Additional query telemetry metadata,
+   *  such as the selection that triggered the update.
    * @returns {Promise} A Promise that resolves upon completion of the update.
    */
-  updateClient(client, query, priority = Priority.Normal) {
+  updateClient(client, query, priority = Priority.Normal, {
+    progressive = true,
+    trace
+  } = {}) {
+    const { clientRequests } = this;
+
+    // cancel any outstanding requests that this update supersedes
+    const prev = clientRequests.get(client);
+    if (prev) this.cancel(prev);
+
+    // ignore results of superseded requests
+    const current = () => clientRequests.get(client) === requests;
+    const done = () => current() && clientRequests.delete(client);
+
+    // stream record batches to clients that render incrementally
+    const onBatch = typeof client.queryResultBatch === 'function'
+      ? batch => current() && client.queryResultBatch(batch)
+      : undefined;
+
     client.queryPending();
-    return this.query(query, { priority })
+
+    // in progressive mode, first issue a sampled query
+    // sampled results are random and short-lived, so do not cache them
+    const sample = progressive ? sampleQuery(this, client, query) : null;
+    const approx = sample
+      ? this.query(sample, {
+          priority, cache: false, trace: { ...trace, client, sample: true }
+        })
+      : null;
+    const request = this.query(query, {
+      priority, onBatch, trace: { ...trace, client }
+    });
+    const requests = approx ? [approx, request] : [request];
+    clientRequests.set(client, requests);
+
+    approx
+      ?.then(
+        data => current() && client.queryResult(data, { approximate: true }).update(),
+        () => {} // errors are reported by the full query
+      )
+      .catch(err => this._logger.error(err));
+
+    return request
       .then(
-        data => client.queryResult(data).update(),
-        err => { this._logger.error(err); client.queryError(err); }
+        data => {
+          if (done()) {
+            if (approx) this.cancel([approx]);
+            client.queryResult(data).update();
+          }
+        },
+        err => {
+          if (done()) { this._logger.error(err); client.queryError(err); }
+        }
       )
       .catch(err => this._logger.error(err));
   }
@@ -185,14 +524,16 @@ export class Coordinator {
   /**
    * Issue a query request for a client. If the query is null or undefined,
    * the client is simply updated. Otherwise `updateClient` is called. As a
-   * side effect, this method clears the current data cube indexer state.
+   * side effect, the cached data cube index entry of the client is removed,
+   * as the client query may have changed. Other index state, including
+   * pending index table creation, is kept.
    * @param {import('./MosaicClient.js').MosaicClient} client The client
    *  to update.
    * @param {import('@uwdata/mosaic-sql').Query | string | null} [query]
    *  The query to issue.
    */
   requestQuery(client, query) {
-    this.dataCubeIndexer.clear();
+    this.dataCubeIndexer.remove(client);
     return query
       ? this.updateClient(client, query)
       : client.update();
@@ -234,6 +575,7 @@ export class Coordinator {
     if (!clients.has(client)) return;
     clients.delete(client);
     client.coordinator = null;
+    this.clientRequests.delete(client);
 
     const group = filterGroups.get(client.filterBy);
     if (group) {
@@ -242,6 +584,50 @@ export class Coordinator {
   }
 }
 
+/**
+ * Process a database connection state change, informing all clients.
+ * @param {Coordinator} mc The Mosaic coordinator.
+ * @param {string} state The new connection state.
+ */
+function updateConnection(mc, state) {
+  mc.logger().info(`Database connection ${state}`);
+  for (const client of mc.clients) {
+    client.connectionState(state);
+  }
+}
+
+/**
+ * Respond to a modification of a table. Cached query results over the
+ * table are invalidated. For live (subscribed) tables, data cube index
+ * tables over the table are either updated with appended rows or dropped,
+ * and connected clients whose queries read the table, or whose tables are
+ * unknown, are then updated. For other tables, such as tables pulled by a
+ * hybrid connector, data cube index tables over the table are dropped.
+ * @param {Coordinator} mc The Mosaic coordinator.
+ * @param {object} message The table modification message.
+ * @param {string} message.table The modified table name.
+ * @param {number | null} [message.rowid] If non-null, the table was only
+ *  appended to and the appended rows have row ids greater than this value.
+ */
+function updateTable(mc, { table, rowid = null }) {
+  const name = tableName(table);
+  const live = mc._live.get(name);
+  if (!live) return mc.invalidate([name]);
+
+  mc.manager.cache().invalidate?.([name]);
+  if (live.incremental && rowid != null) {
+    mc.dataCubeIndexer.append(name, rowid);
+  } else {
+    mc.dataCubeIndexer.invalidate([name]);
+  }
+
+  for (const client of mc.clients) {
+    const query = client.query();
+    const tables = query && queryTables(query);
+    if (query && (!tables || tables.includes(name))) client.requestQuery();
+  }
+}
+
 /**
  * Connect a selection-client pair to the coordinator to process updates.
  * @param {Coordinator} mc The Mosaic coordinator.
@@ -309,6 +695,66 @@ function updateSelection(mc, selection) {
 
     // @ts-ignore
     const query = info?.query(active.predicate) ?? client.query(filter);
-    return mc.updateClient(client, query);
+    // data cube index queries are fast, so skip progressive sampling
+    return mc.updateClient(client, query, Priority.Normal, {
+      progressive: !info,
+      trace: { selection, cube: !!info }
+    });
   }));
 }
+
+/**
+ * Schedule prebuilding of data cube index tables after an idle delay. Any
+ * previously scheduled prebuild is canceled. When the delay elapses, index
+ * tables are prebuilt only if the coordinator is still idle.
+ * @param {Coordinator} mc The Mosaic coordinator.
+ */
+function schedulePrebuild(mc) {
+  clearTimeout(mc._prebuildTimer);
+  const options = mc.prebuild();
+  if (!options || !mc.manager.isIdle()) return;
+  mc._prebuildTimer = setTimeout(
+    () => mc.manager.isIdle() && prebuildIndexes(mc, options),
+    options.delay
+  );
+}
+
+/**
+ * Prebuild data cube index tables for all filter groups and the selection
+ * clauses registered with their selections, until the budget is exhausted.
+ * @param {Coordinator} mc The Mosaic coordinator.
+ * @param {{ maxTables: number, maxRows: number }} budget The prebuild budget.
+ */
+function prebuildIndexes(mc, budget) {
+  const { dataCubeIndexer, filterGroups } = mc;
+  for (const { selection, clients } of filterGroups.values()) {
+    for (const clause of selection.registered ?? []) {
+      for (const client of clients) {
+        if (!dataCubeIndexer.prebuild(client, selection, clause, budget)) {
+          return;
+        }
+      }
+    }
+  }
+}
+
+/**
+ * Create a sampled version of a client query for a progressive update.
+ * @param {Coordinator} mc The Mosaic coordinator.
+ * @param {import('./MosaicClient.js').MosaicClient} client A Mosaic client.
+ * @param {import('@uwdata/mosaic-sql').Query | string} query The data query.
+ * @returns {Query | null} The sampled query, or null if the query should
+ *  not be sampled.
+ */
+function sampleQuery(mc, client, query) {
+  const options = mc.progressive();
+  if (!options || !client.progressive || !(query instanceof Query)) {
+    return null;
+  }
+  // only sample queries over base tables that are not already sampled
+  const from = query.from();
+  if (query.sample() || !from.length || from.some(f => isQuery(f.from))) {
+    return null;
+  }
+  return query.clone().sample(options.size, options.method);
+}
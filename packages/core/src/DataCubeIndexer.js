@@ -1,11 +1,21 @@
 import {
-  Query, and, asColumn, create, isBetween, scaleTransform, sql
+  Query, agg, and, asColumn, create, isBetween, isNotTrue, literal,
+  scaleTransform, sql
 } from '@uwdata/mosaic-sql';
-import { indexColumns } from './util/index-columns.js';
+import { Priority } from './QueryManager.js';
+import { hasCenteredColumns, indexColumns } from './util/index-columns.js';
+import { jsType } from './util/js-type.js';
+import { tableName } from './util/query-tables.js';
 import { fnv_hash } from './util/hash.js';
 
 const Skip = { skip: true, result: null };
 
+// name of the table tracking persistent data cube index table usage
+const USAGE_TABLE = 'cube_index_usage';
+
+// name of the column identifying set operation branches
+const BRANCH = '__branch__';
+
 /**
  * Build and query optimized indices ("data cubes") for fast computation of
  * groupby aggregate queries over compatible client queries and selections.
@@ -14,7 +24,9 @@ const Skip = { skip: true, result: null };
  * realized as as database tables that can be queried for rapid updates.
  * Compatible client queries must consist of only groupby dimensions and
  * supported aggregate functions. Compatible selections must contain an active
- * clause that exposes metadata for an interval or point value predicate.
+ * clause that exposes metadata for an interval, point value, or text match
+ * predicate. Text match clauses are indexed by grouping on the distinct values
+ * of the searched column, and so are only indexed for low cardinality columns.
  */
 export class DataCubeIndexer {
   /**
@@ -23,18 +35,56 @@ export class DataCubeIndexer {
    * @param {object} [options] Indexer options.
    * @param {boolean} [options.enabled=true] Flag to enable/disable indexer.
    * @param {boolean} [options.temp=true] Flag to indicate if generated data
-   *  cube index tables should be temporary tables.
+   *  cube index tables should be temporary tables. If false, index tables
+   *  persist in the database and are reused by later sessions.
+   * @param {string} [options.schema='mosaic'] The database schema for
+   *  persistent (non-temporary) data cube index tables.
+   * @param {number} [options.maxTables=Infinity] The maximum number of
+   *  persistent data cube index tables to retain.
+   * @param {number} [options.maxRows=Infinity] The maximum total number of
+   *  rows across retained persistent data cube index tables.
+   * @param {number} [options.maxCardinality=10000] The maximum number of
+   *  distinct values of a text match clause column for which to generate
+   *  data cube index tables.
+   * @param {boolean} [options.approximate=false] Flag to index exact
+   *  distinct counts using approximate sketches. If false, clients with
+   *  exact distinct counts are not indexed.
    */
   constructor(coordinator, {
     enabled = true,
-    temp = true
+    temp = true,
+    schema = 'mosaic',
+    maxTables = Infinity,
+    maxRows = Infinity,
+    maxCardinality = 10000,
+    approximate = false
   } = {}) {
     /** @type {Map<import('./MosaicClient.js').MosaicClient, DataCubeInfo | Skip | null>} */
     this.indexes = new Map();
     this.active = null;
     this.temp = temp;
+    this.schema = schema;
+    this.maxTables = maxTables;
+    this.maxRows = maxRows;
+    this.maxCardinality = maxCardinality;
+    this.approximate = approximate;
+    /** @type {Map<string, number>} Prebuilt index tables and row counts. */
+    this.prebuilt = new Map();
+    /**
+     * Index tables, their normalized base table names, an optional generator
+     * of queries that index appended rows, and a creation completion flag.
+     * @type {Map<string, { from: string[], append: ((rowid: number) => string) | null, ready: boolean }>}
+     */
+    this.sources = new Map();
+    /**
+     * Persistent index table state per database connector: the creation
+     * of the usage table and the names of existing index tables.
+     * @type {Map<object, { usage?: Promise, existing?: Promise<Set<string>> }>}
+     */
+    this.databases = new Map();
     this.mc = coordinator;
     this._enabled = enabled;
+    this._schemaReady = false;
   }
 
   /**
@@ -53,9 +103,9 @@ export class DataCubeIndexer {
 
   /**
    * Clear the cache of data cube index table entries for the current active
-   * selection clause. This method will also cancel any queued data cube table
-   * creation queries that have not yet been submitted to the database. This
-   * method does _not_ drop any existing data cube tables.
+   * selection clause. This method will also cancel any pending data cube
+   * table creation queries, interrupting them if already submitted to the
+   * database. This method does _not_ drop any existing data cube tables.
    */
   clear() {
     this.mc.cancel(Array.from(this.indexes.values(), info => info?.result));
@@ -63,6 +113,17 @@ export class DataCubeIndexer {
     this.active = null;
   }
 
+  /**
+   * Remove the cached data cube index entry for a client, such that the
+   * entry is determined anew for the next selection update. Unlike `clear`,
+   * pending data cube table creation queries are not canceled, and a table
+   * with the same creation query is reused once created.
+   * @param {import('./MosaicClient.js').MosaicClient} client A Mosaic client.
+   */
+  remove(client) {
+    this.indexes.delete(client);
+  }
+
   /**
    * Return data cube index table information for the active state of a
    * client-selection pair, or null if the client is not indexable. This
@@ -81,7 +142,7 @@ export class DataCubeIndexer {
     // if not enabled, do nothing
     if (!this._enabled) return null;
 
-    const { indexes, mc, temp } = this;
+    const { indexes } = this;
     const { source } = activeClause;
 
     // if there is no clause source to track, do nothing
@@ -92,7 +153,9 @@ export class DataCubeIndexer {
       // if the active clause source has changed, clear indexer state
       // this cancels outstanding requests and clears the index cache
       // a clear also sets this.active to null
-      if (this.active.source !== source) this.clear();
+      // negation also changes the active predicate, so clear as well
+      if (this.active.source !== source
+        || this.active.negate !== !!activeClause.meta?.negate) this.clear();
       // if we've seen this source and it's not indexable, do nothing
       if (this.active?.source === null) return null;
     }
@@ -102,6 +165,9 @@ export class DataCubeIndexer {
 
     // if cached active columns are unset, analyze the active clause
     if (!active) {
+      // a clause without a predicate (such as a cleared selection) does
+      // not indicate the columns to index, so wait for a later clause
+      if (!activeClause.predicate) return null;
       // generate active data cube dimension columns to select over
       // will return an object with null source if not indexable
       this.active = active = activeColumns(activeClause);
@@ -115,7 +181,7 @@ export class DataCubeIndexer {
     }
 
     // get non-active data cube index table columns
-    const indexCols = indexColumns(client);
+    const indexCols = indexColumns(client, { approximate: this.approximate });
 
     let info;
     if (!indexCols) {
@@ -124,17 +190,439 @@ export class DataCubeIndexer {
     } else if (selection.skip(client, activeClause)) {
       // skip client if untouched by cross-filtering
       info = Skip;
+    } else if (selection.related(client, activeClause)) {
+      // clauses over a related table filter the client via a semi-join
+      info = null;
+    } else if (active.cardinality || indexCols.numeric.length) {
+      // generate data cube index table only for low cardinality columns
+      // and numeric quantile inputs, the client is not indexed until
+      // the checks complete
+      info = null;
+      this.checkIndexable(client, selection, active, indexCols);
     } else {
-      // generate data cube index table
-      const filter = selection.remove(source).predicate(client);
-      info = dataCubeInfo(client.query(filter), active, indexCols);
-      info.result = mc.exec(create(info.table, info.create, { temp }));
-      info.result.catch(e => mc.logger().error(e));
+      info = this.createIndex(client, selection, active, indexCols);
     }
 
     indexes.set(client, info);
     return info;
   }
+
+  /**
+   * Generate a data cube index table for a client-selection pair.
+   * Persistent tables are reused if the creation query is unchanged.
+   * @param {import('./MosaicClient.js').MosaicClient} client A Mosaic client.
+   * @param {import('./Selection.js').Selection} selection A Mosaic selection
+   *  to filter the client by.
+   * @param {*} active Active (selected) column definitions.
+   * @param {*} indexCols Data cube index column definitions.
+   * @returns {DataCubeInfo} Data cube index table information.
+   */
+  createIndex(client, selection, active, indexCols) {
+    const { indexes, mc, temp } = this;
+    const filter = selection.remove(active.source).predicate(client);
+    const schema = temp ? null : this.createSchema();
+    const info = dataCubeInfo(client.query(filter), active, indexCols, schema);
+    const source = this.addSource(info, indexCols);
+    const build = () => mc.exec(create(info.table, info.create, { temp }));
+    info.result = temp ? build() : this.reuse(info.table, indexCols.from[0])
+      .then(found => found || build());
+    info.result.then(
+      () => {
+        source.ready = true;
+        return temp || this.track(info.table);
+      },
+      // ignore errors for canceled requests, which are no longer indexed
+      e => indexes.get(client) === info && mc.logger().error(e)
+    );
+    return info;
+  }
+
+  /**
+   * Check if a client can be indexed, and if so generate a data cube index
+   * table for the client. Active columns of text match clauses must have
+   * a low cardinality, and quantile sketch inputs must have a numeric type.
+   * Subsequent selection updates then use the index table.
+   * @param {import('./MosaicClient.js').MosaicClient} client A Mosaic client.
+   * @param {import('./Selection.js').Selection} selection A Mosaic selection
+   *  to filter the client by.
+   * @param {*} active Active (selected) column definitions.
+   * @param {*} indexCols Data cube index column definitions.
+   * @returns {Promise} A Promise that resolves when the check completes.
+   */
+  async checkIndexable(client, selection, active, indexCols) {
+    const { indexes, mc } = this;
+    const priority = Priority.Low;
+    try {
+      const ok = await this.checkTypes(indexCols, priority)
+        && (!active.cardinality
+          || await this.checkCardinality(active, indexCols, priority));
+      // skip if the indexer state changed while the check was pending
+      if (ok && this.active === active && indexes.get(client) === null) {
+        indexes.set(client, this.createIndex(client, selection, active, indexCols));
+      }
+    } catch (err) {
+      mc.logger().error(err);
+    }
+  }
+
+  /**
+   * Query the number of distinct active column values over a client's base
+   * table, and test if the count does not exceed the maximum cardinality.
+   * @param {*} active Active (selected) column definitions.
+   * @param {*} indexCols Data cube index column definitions.
+   * @param {number} priority The query priority.
+   * @returns {Promise<boolean>} True if the active columns have a low
+   *  cardinality, false otherwise.
+   */
+  async checkCardinality(active, { from }, priority) {
+    if (from.length !== 1) return false; // base table is ambiguous
+    const cols = Object.values(active.columns);
+    const [counts] = await this.mc.query(
+      Query.from(from[0]).select(cols.map(
+        (col, i) => ({ [`n${i}`]: agg`approx_count_distinct(${col})` })
+      )),
+      { type: 'json', priority }
+    );
+    const n = Object.values(counts).reduce((n, v) => n * Number(v), 1);
+    return n <= this.maxCardinality;
+  }
+
+  /**
+   * Query the types of quantile sketch input columns, and test if all
+   * inputs have a numeric type. Sketches map values to numeric buckets,
+   * and so quantiles of other types, such as dates, are not indexed.
+   * @param {*} indexCols Data cube index column definitions.
+   * @param {number} priority The query priority.
+   * @returns {Promise<boolean>} True if all sketch input columns have a
+   *  numeric type, false otherwise.
+   */
+  async checkTypes({ from, numeric }, priority) {
+    if (!numeric.length) return true;
+    const q = Query.from(...from)
+      .select(numeric.map((col, i) => ({ [`x${i}`]: col })));
+    const desc = await this.mc.query(Query.describe(q), { type: 'json', priority });
+    return Array.from(desc).every(d => jsType(d.column_type) === 'number');
+  }
+
+  /**
+   * Speculatively build a data cube index table for a client-selection pair
+   * and a clause representative of potential activations, in advance of any
+   * actual activation. Tables are created at low priority and named by a
+   * hash of their creation query, so a later activation with the same
+   * selection state reuses the prebuilt table. Text match clauses, which
+   * require a cardinality check, and clients with quantile aggregates,
+   * which require a column type check, are not prebuilt.
+   * @param {import('./MosaicClient.js').MosaicClient} client A Mosaic client.
+   * @param {import('./Selection.js').Selection} selection A Mosaic selection
+   *  to filter the client by.
+   * @param {import('./util/selection-types.js').SelectionClause} clause
+   *  A representative selection clause.
+   * @param {object} [budget] The prebuild budget.
+   * @param {number} [budget.maxTables=Infinity] The maximum number of
+   *  prebuilt index tables.
+   * @param {number} [budget.maxRows=Infinity] The maximum total number of
+   *  rows across prebuilt index tables.
+   * @returns {boolean} False if the prebuild budget is exhausted (or the
+   *  indexer is disabled), true otherwise.
+   */
+  prebuild(client, selection, clause, {
+    maxTables = Infinity,
+    maxRows = Infinity
+  } = {}) {
+    const { indexes, mc, prebuilt, temp } = this;
+    let rows = 0;
+    prebuilt.forEach(n => rows += n);
+    if (!this._enabled || prebuilt.size >= maxTables || rows >= maxRows) {
+      return false;
+    }
+
+    // skip clauses and clients that can not be indexed
+    const { source, predicate } = clause;
+    if (!source || !predicate) return true;
+    const active = activeColumns(clause);
+    if (active.source === null || active.cardinality) return true;
+    const indexCols = indexColumns(client, { approximate: this.approximate });
+    if (!indexCols || indexCols.numeric.length) return true;
+    if (selection.skip(client, clause)) return true;
+    if (selection.related(client, clause)) return true;
+
+    // skip tables that are already built or in use
+    const filter = selection.remove(source).predicate(client);
+    const schema = temp ? null : this.createSchema();
+    const info = dataCubeInfo(client.query(filter), active, indexCols, schema);
+    const { table } = info;
+    if (prebuilt.has(table)) return true;
+    for (const entry of indexes.values()) {
+      if (entry?.table === table) return true;
+    }
+
+    const priority = Priority.Low;
+    prebuilt.set(table, 0);
+    const entry = this.addSource(info, indexCols);
+    mc.exec(create(table, info.create, { temp }), { priority })
+      .then(() => (entry.ready = true, mc.query(
+        `SELECT COUNT(*)::INTEGER AS rows FROM ${table}`,
+        { type: 'json', cache: false, priority }
+      )))
+      .then(([{ rows }]) => {
+        prebuilt.set(table, rows);
+        if (!temp) this.track(table);
+      })
+      .catch(err => mc.logger().error(err));
+    return true;
+  }
+
+  /**
+   * Record the base tables of a data cube index table.
+   * @param {DataCubeInfo} info The data cube index table information.
+   * @param {*} indexCols Data cube index column definitions.
+   * @returns {{ from: string[], append: Function | null, ready: boolean }}
+   *  The index table source entry.
+   */
+  addSource(info, indexCols) {
+    const { table, append } = info;
+    const source = { from: indexCols.from.map(tableName), append, ready: false };
+    this.sources.set(table, source);
+    return source;
+  }
+
+  /**
+   * Update data cube index tables built over a base table with rows that
+   * were appended to the base table, rather than drop and rebuild them.
+   * Appended rows (those with a row id greater than the given row id) are
+   * aggregated and inserted into the index tables. As index tables are
+   * re-aggregated when queried, the inserted partial aggregates combine
+   * with the existing ones. Index tables that can not be updated this way,
+   * such as those over joins or subqueries, are dropped. Index tables whose
+   * creation has not yet completed are left as-is, as they are created
+   * after the append and so already include the appended rows. If an
+   * incremental update fails, for example as the base table is a view
+   * without row ids, the index table is dropped.
+   * @param {string} table The normalized base table name.
+   * @param {number} rowid The largest row id prior to the append.
+   * @param {object} [options] Update options.
+   * @param {number} [options.priority] The priority of the update queries,
+   *  defaults to `Priority.Normal`.
+   * @returns {string[]} The names of the dropped index tables.
+   */
+  append(table, rowid, { priority = Priority.Normal } = {}) {
+    const { mc, sources } = this;
+    const drop = new Set;
+    for (const [name, source] of sources) {
+      const { from, append, ready } = source;
+      if (!from.includes(table) || !ready) continue;
+      if (append) {
+        mc.exec(append(rowid), { priority }).catch(err => {
+          mc.logger().error(err);
+          // the index table misses appended rows, drop it to be rebuilt
+          if (sources.get(name) === source) {
+            this.drop(new Set([name]), { priority });
+          }
+        });
+      } else {
+        drop.add(name);
+      }
+    }
+    return this.drop(drop, { priority });
+  }
+
+  /**
+   * Drop data cube index tables built over any of the given base tables,
+   * such as after those tables are modified, so that later selection updates
+   * rebuild the index tables over the modified data. Drop queries are issued
+   * with the given priority, which should match that of the modifying query
+   * so that the drops do not precede the modification.
+   * @param {string[] | null} tables The normalized base table names, or
+   *  null to drop all data cube index tables.
+   * @param {object} [options] Invalidation options.
+   * @param {number} [options.priority] The priority of the drop queries,
+   *  defaults to `Priority.Normal`.
+   * @returns {string[]} The names of the dropped index tables.
+   */
+  invalidate(tables, { priority = Priority.Normal } = {}) {
+    const names = tables && new Set(tables);
+    const drop = new Set;
+    for (const [table, { from }] of this.sources) {
+      if (!names || from.some(t => names.has(t))) drop.add(table);
+    }
+    return this.drop(drop, { priority });
+  }
+
+  /**
+   * Drop the given data cube index tables. Affected clients are no longer
+   * indexed, so that later selection updates rebuild their index tables.
+   * @param {Set<string>} drop The names of the index tables to drop.
+   * @param {object} [options] Drop options.
+   * @param {number} [options.priority] The priority of the drop queries,
+   *  defaults to `Priority.Normal`.
+   * @returns {string[]} The names of the dropped index tables.
+   */
+  drop(drop, { priority = Priority.Normal } = {}) {
+    const { indexes, mc, prebuilt, sources } = this;
+    if (!drop.size) return [];
+
+    // remove affected index entries, canceling any pending creation
+    const pending = [];
+    for (const [client, info] of indexes) {
+      if (drop.has(info?.table)) {
+        indexes.delete(client);
+        pending.push(info.result);
+      }
+    }
+    mc.cancel(pending);
+
+    drop.forEach(table => {
+      sources.delete(table);
+      prebuilt.delete(table);
+      this.database(table).existing?.then(names => names.delete(table));
+      mc.exec(`DROP TABLE IF EXISTS ${table}`, { priority });
+    });
+    return Array.from(drop);
+  }
+
+  /**
+   * Create the schema for persistent data cube index tables, if it does not
+   * yet exist.
+   * @returns {string} The schema name.
+   */
+  createSchema() {
+    const { mc, schema } = this;
+    if (!this._schemaReady) {
+      this._schemaReady = true;
+      mc.exec(`CREATE SCHEMA IF NOT EXISTS ${schema}`);
+    }
+    return schema;
+  }
+
+  /**
+   * Get the persistent index table state of the database serving a table.
+   * With a routing connector, each routed database has its own usage table
+   * and index tables.
+   * @param {string} table A table name.
+   * @returns {{ usage?: Promise, existing?: Promise<Set<string>> }} The
+   *  database state.
+   */
+  database(table) {
+    const db = this.mc.locate(table);
+    if (!this.databases.has(db)) this.databases.set(db, {});
+    return this.databases.get(db);
+  }
+
+  /**
+   * Query the names of persistent data cube index tables that already exist
+   * in the database serving a table, such as tables created by earlier
+   * sessions. The names are queried once, and then kept up to date as index
+   * tables are created and dropped.
+   * @param {string} table A table name, such as an index base table.
+   * @returns {Promise<Set<string>>} The qualified table names.
+   */
+  existingTables(table) {
+    const { mc, schema } = this;
+    const state = this.database(table);
+    return state.existing ??= mc.query(
+      'SELECT table_name FROM duckdb_tables() '
+        + `WHERE schema_name = '${schema}'`,
+      { type: 'json', cache: false, priority: Priority.High, route: table }
+    )
+      .then(rows => new Set(Array.from(rows, r => `${schema}.${r.table_name}`)))
+      .catch(err => (mc.logger().error(err), new Set));
+  }
+
+  /**
+   * Test if a persistent data cube index table already exists in the
+   * database serving its base table. If so, the index table is routed to
+   * that database, as it was created by an earlier session.
+   * @param {string} table The data cube index table name.
+   * @param {string} base The index base table name.
+   * @returns {Promise<boolean>} True if the index table exists.
+   */
+  async reuse(table, base) {
+    const { mc } = this;
+    const found = (await this.existingTables(base)).has(table);
+    if (found) mc.databaseConnector().register?.(table, mc.locate(base));
+    return found;
+  }
+
+  /**
+   * Record usage of a persistent data cube index table, then drop least
+   * recently used tables that exceed the configured budget.
+   * @param {string} table The data cube index table name.
+   * @returns {Promise} A Promise that resolves when tracking completes.
+   */
+  async track(table) {
+    const { maxRows, maxTables, mc, schema } = this;
+    const state = this.database(table);
+    const priority = Priority.Low;
+    state.existing?.then(names => names.add(table));
+    try {
+      // usage statements are routed to the database of the index table
+      await (state.usage ??= mc.exec(
+        `CREATE TABLE IF NOT EXISTS ${schema}.${USAGE_TABLE} `
+          + '(name VARCHAR PRIMARY KEY, accessed TIMESTAMP, rows BIGINT)',
+        { priority, route: table }
+      ));
+      await mc.exec(
+        `INSERT OR REPLACE INTO ${schema}.${USAGE_TABLE} `
+          + `SELECT '${table}', now(), COUNT(*) FROM ${table}`,
+        { priority, route: table }
+      );
+      if (maxTables < Infinity || maxRows < Infinity) {
+        await this.collect(table);
+      }
+    } catch (err) {
+      mc.logger().error(err);
+    }
+  }
+
+  /**
+   * Drop the least recently used persistent data cube index tables that
+   * exceed the table count (`maxTables`) or total row (`maxRows`) budget.
+   * Tables in use for the current active selection clause are not dropped.
+   * The budget applies per database, using the usage table of the database
+   * that serves the given index table.
+   * @param {string} table A persistent index table in the database.
+   * @returns {Promise<string[]>} The names of the dropped tables.
+   */
+  async collect(table) {
+    const { indexes, maxRows, maxTables, mc } = this;
+    const usage = `${this.createSchema()}.${USAGE_TABLE}`;
+    const inUse = new Set(Array.from(indexes.values(), info => info?.table));
+    const route = table;
+    const entries = await mc.query(
+      `SELECT name, rows FROM ${usage} ORDER BY accessed DESC`,
+      { type: 'json', cache: false, priority: Priority.Low, route }
+    );
+
+    // retain most recently used tables until the budget is exhausted
+    const drop = [];
+    let tables = 0;
+    let rows = 0;
+    for (const { name, rows: n } of entries) {
+      const full = drop.length > 0
+        || tables >= maxTables || rows + Number(n) > maxRows;
+      if (inUse.has(name) || !full) {
+        tables += 1;
+        rows += Number(n);
+      } else {
+        drop.push(name);
+      }
+    }
+
+    if (drop.length) {
+      const priority = Priority.Low;
+      const { existing } = this.database(table);
+      drop.forEach(name => {
+        existing?.then(names => names.delete(name));
+        mc.exec(`DROP TABLE IF EXISTS ${name}`, { priority, route });
+      });
+      const names = drop.map(name => `'${name}'`).join(', ');
+      await mc.exec(
+        `DELETE FROM ${usage} WHERE name IN (${names})`,
+        { priority, route }
+      );
+    }
+    return drop;
+  }
 }
 
 /**
@@ -154,13 +642,14 @@ function activeColumns(clause) {
   let columns;
 
   if (!meta || !clauseCols) {
-    return { source: null, columns, predicate };
+    return { source: null, columns, predicate, negate: !!meta?.negate };
   }
 
   // @ts-ignore
   const { type, scales, bin, pixelSize = 1 } = meta;
 
-  if (type === 'point') {
+  if (type === 'point' || type === 'match') {
+    // group by the selected columns and apply the clause predicate as-is
     predicate = x => x;
     columns = Object.fromEntries(
       clauseCols.map(col => [`${col}`, asColumn(col)])
@@ -190,7 +679,22 @@ function activeColumns(clause) {
     }
   }
 
-  return { source: columns ? source : null, columns, predicate };
+  // negated clauses select records outside the active predicate,
+  // including records for which the predicate is null
+  const negate = !!meta.negate;
+  if (negate && predicate) {
+    const pred = predicate;
+    predicate = p => p ? isNotTrue(pred(p)) : [];
+  }
+
+  return {
+    source: columns ? source : null,
+    columns,
+    predicate,
+    negate,
+    // text match columns are indexed only if they have low cardinality
+    cardinality: type === 'match'
+  };
 }
 
 const BIN = { ceil: 'CEIL', round: 'ROUND' };
@@ -220,44 +724,73 @@ function binInterval(scale, pixelSize, bin) {
 
 /**
  * Generate data cube table query information.
- * @param {Query} clientQuery The original client query.
+ * @param {*} clientQuery The original client query, either a select
+ *  query or a UNION ALL set operation over select queries.
  * @param {*} active Active (selected) column definitions.
  * @param {*} indexCols Data cube index column definitions.
+ * @param {string | null} [schema] The schema for persistent index tables,
+ *  or null for temporary tables.
  * @returns {DataCubeInfo}
  */
-function dataCubeInfo(clientQuery, active, indexCols) {
-  const { dims, aggr, aux } = indexCols;
+function dataCubeInfo(clientQuery, active, indexCols, schema) {
+  const { dims, aggr, aux, branches } = indexCols;
   const { columns } = active;
 
-  // build index table construction query
-  const query = clientQuery
-    .select({ ...columns, ...aux })
-    .groupby(Object.keys(columns));
-
-  // ensure active clause columns are selected by subqueries
-  const [subq] = query.subqueries;
-  if (subq) {
-    const cols = Object.values(columns).flatMap(c => c.columns);
-    subqueryPushdown(subq, cols);
-  }
-
   // push orderby criteria to later cube queries
-  const order = query.orderby();
-  query.query.orderby = [];
+  const order = clientQuery.orderby();
+
+  // build index table construction query
+  // set operation branches are tagged to keep their groups distinct
+  const query = branches
+    ? Query.unionAll(clientQuery.queries.map((q, i) => cubeQuery(
+        q, columns, { ...branches[i], [BRANCH]: literal(i) }
+      )))
+    : cubeQuery(clientQuery, columns, aux);
 
   // generate creation query string and hash id
   const create = query.toString();
   const id = (fnv_hash(create) >>> 0).toString(16);
-  const table = `cube_index_${id}`;
+  const table = `${schema ? `${schema}.` : ''}cube_index_${id}`;
+
+  // rows appended to a single base table can be indexed incrementally,
+  // unless auxiliary columns are centered on a global average that
+  // changes as rows are appended
+  const [base, ...rest] = branches ? [] : query.from();
+  const append = base && !rest.length && !query.subqueries.length
+    && !hasCenteredColumns(aux)
+    ? rowid => `INSERT INTO ${table} ${query.clone().where(sql`rowid > ${rowid}`)}`
+    : null;
 
   // generate data cube select query
   const select = Query
     .select(dims, aggr)
     .from(table)
-    .groupby(dims)
+    .groupby(dims, branches ? BRANCH : [])
     .orderby(order);
 
-  return new DataCubeInfo({ table, create, active, select });
+  return new DataCubeInfo({ table, create, active, select, append });
+}
+
+/**
+ * Extend a client select query to build a data cube index table.
+ * @param {Query} query The client select query. This query is modified.
+ * @param {object} columns Active (selected) column definitions.
+ * @param {object} aux Auxiliary column definitions.
+ * @returns {Query} The data cube index construction query.
+ */
+function cubeQuery(query, columns, aux) {
+  query
+    .select({ ...columns, ...aux })
+    .groupby(Object.keys(columns));
+  query.query.orderby = [];
+
+  // ensure active clause columns are selected by subqueries
+  const [subq] = query.subqueries;
+  if (subq) {
+    const cols = Object.values(columns).flatMap(c => c.columns);
+    subqueryPushdown(subq, cols);
+  }
+  return query;
 }
 
 /**
@@ -287,7 +820,7 @@ export class DataCubeInfo {
    * Create a new DataCubeInfo instance.
    * @param {object} options
    */
-  constructor({ table, create, active, select } = {}) {
+  constructor({ table, create, active, select, append = null } = {}) {
     /** The name of the data cube index table. */
     this.table = table;
     /** The SQL query used to generate the data cube index table. */
@@ -301,6 +834,12 @@ export class DataCubeInfo {
     this.active = active;
     /** Select query (sans where clause) for data cube tables. */
     this.select = select;
+    /**
+     * Generator of a query that inserts rows appended to the base table,
+     * those with a row id greater than the input row id, into the data cube
+     * index table. Null if the index table can not be updated incrementally.
+     */
+    this.append = append;
     /**
      * Boolean flag indicating a client that should be skipped.
      * This value is always false for completed data cube info.
@@ -0,0 +1,281 @@
+import { AsyncDispatch } from './util/AsyncDispatch.js';
+import { distinct } from './util/distinct.js';
+import { isSelection } from './Selection.js';
+import { applyState, settle } from './ParamState.js';
+
+/**
+ * Records the state of a collection of named Params and Selections,
+ * supporting undo and redo, named bookmarks, and replay of state sequences.
+ * History recording is opt-in: only explicitly added Params and Selections
+ * are tracked. The history emits `'change'` events whenever the recorded
+ * history or current position changes.
+ */
+export class History extends AsyncDispatch {
+
+  /**
+   * Create a new History instance.
+   * @param {object} [options] The history options.
+   * @param {Record<string, import('./Param.js').Param>} [options.params]
+   *  Named Params and Selections to track.
+   * @param {number} [options.max=100] The maximum number of recorded states.
+   * @param {number} [options.merge=250] The time window in milliseconds for
+   *  merging consecutive updates, such as those generated while brushing,
+   *  into a single recorded state.
+   */
+  constructor({ params = {}, max = 100, merge = 250 } = {}) {
+    super();
+    this.max = max;
+    this.merge = merge;
+    /** @type {Map<string, import('./Param.js').Param>} */
+    this.params = new Map;
+    /** @type {Map<string, object>} */
+    this.bookmarks = new Map;
+    this._listeners = new Map;
+    this._states = [];
+    this._index = -1;
+    this._time = -Infinity;
+    this._restoring = 0;
+    for (const name in params) this.add(name, params[name]);
+    this.clear();
+  }
+
+  /**
+   * Track a Param or Selection. A new Param state is recorded whenever the
+   * tracked Param emits a value event.
+   * @param {string} name The name of the Param.
+   * @param {import('./Param.js').Param} param The Param or Selection.
+   * @returns {this} This History instance.
+   */
+  add(name, param) {
+    this.remove(name);
+    const listener = () => {
+      if (!this._restoring) this.record();
+    };
+    param.addEventListener('value', listener);
+    this.params.set(name, param);
+    this._listeners.set(name, listener);
+    // include the current value in the current state
+    const state = this._states[this._index];
+    if (state) state[name] = snapshot(param);
+    return this;
+  }
+
+  /**
+   * Stop tracking a Param or Selection.
+   * @param {string} name The name of the Param.
+   * @returns {this} This History instance.
+   */
+  remove(name) {
+    const param = this.params.get(name);
+    if (param) {
+      param.removeEventListener('value', this._listeners.get(name));
+      this.params.delete(name);
+      this._listeners.delete(name);
+    }
+    return this;
+  }
+
+  /**
+   * Clear the recorded history, keeping only the current state.
+   * Bookmarks are retained.
+   * @returns {this} This History instance.
+   */
+  clear() {
+    this._states = [this.state];
+    this._index = 0;
+    this._time = -Infinity;
+    this.emit('change', this);
+    return this;
+  }
+
+  /**
+   * The current state of all tracked Params, as an object keyed by Param
+   * name. Param entries are values, Selection entries are clause arrays.
+   */
+  get state() {
+    const state = {};
+    for (const [name, param] of this.params) {
+      state[name] = snapshot(param);
+    }
+    return state;
+  }
+
+  /**
+   * The recorded states, from oldest to newest.
+   */
+  get states() {
+    return this._states.slice();
+  }
+
+  /**
+   * The index of the current state among the recorded states.
+   */
+  get index() {
+    return this._index;
+  }
+
+  /**
+   * Indicates if there is an earlier state to undo to.
+   */
+  get canUndo() {
+    return this._index > 0;
+  }
+
+  /**
+   * Indicates if there is a later state to redo to.
+   */
+  get canRedo() {
+    return this._index < this._states.length - 1;
+  }
+
+  /**
+   * Record the current state as a new history entry. Later (undone) entries
+   * are discarded. Updates within the merge time window of the previous
+   * recorded update replace that entry. This method is invoked
+   * automatically upon updates to tracked Params.
+   * @returns {this} This History instance.
+   */
+  record() {
+    const state = this.state;
+    const curr = this._states[this._index];
+    if (curr && !changed(curr, state)) return this;
+
+    const now = performance.now();
+    const replace = this._index > 0
+      && this._index === this._states.length - 1
+      && now - this._time < this.merge;
+    this._states.splice(this._index + (replace ? 0 : 1), Infinity, state);
+    this._time = now;
+    if (this._states.length > this.max) this._states.shift();
+    this._index = this._states.length - 1;
+    this.emit('change', this);
+    return this;
+  }
+
+  /**
+   * Restore the previous recorded state.
+   * @returns {Promise<boolean>} A Promise resolving to true if a state was
+   *  restored, or false if there is no earlier state.
+   */
+  async undo() {
+    return this.canUndo ? this.go(this._index - 1) : false;
+  }
+
+  /**
+   * Restore the next recorded state, following an undo.
+   * @returns {Promise<boolean>} A Promise resolving to true if a state was
+   *  restored, or false if there is no later state.
+   */
+  async redo() {
+    return this.canRedo ? this.go(this._index + 1) : false;
+  }
+
+  /**
+   * Restore the recorded state at the given index.
+   * @param {number} index The index of the recorded state.
+   * @returns {Promise<boolean>} A Promise resolving to true once the state
+   *  is restored, or false if the index is invalid.
+   */
+  async go(index) {
+    const state = this._states[index];
+    if (!state) return false;
+    this._index = index;
+    this._time = -Infinity;
+    await this.apply(state);
+    this.emit('change', this);
+    return true;
+  }
+
+  /**
+   * Save the current state as a named bookmark.
+   * @param {string} name The bookmark name.
+   * @returns {this} This History instance.
+   */
+  bookmark(name) {
+    this.bookmarks.set(name, this.state);
+    this.emit('change', this);
+    return this;
+  }
+
+  /**
+   * Restore a state, either given directly or by bookmark name, and record
+   * it as a new history entry that may be undone.
+   * @param {string | object} state The state object or bookmark name.
+   * @returns {Promise<this>} A Promise resolving to this History instance
+   *  once the state is restored.
+   */
+  async restore(state) {
+    const target = typeof state === 'string' ? this.bookmarks.get(state) : state;
+    if (!target) throw new Error(`Unrecognized bookmark: ${state}`);
+    await this.apply(target);
+    this._time = -Infinity;
+    return this.record();
+  }
+
+  /**
+   * Replay a sequence of states in order, without modifying the recorded
+   * history. The tracked Params retain the final state.
+   * @param {(string | object)[]} [states] The state objects or bookmark
+   *  names to replay. Defaults to the recorded history states.
+   * @param {object} [options] The replay options.
+   * @param {number} [options.delay=500] The delay in milliseconds between
+   *  consecutive states.
+   * @returns {Promise<this>} A Promise resolving to this History instance
+   *  once the replay completes.
+   */
+  async replay(states = this.states, { delay = 500 } = {}) {
+    for (let i = 0; i < states.length; ++i) {
+      if (i > 0) await new Promise(resolve => setTimeout(resolve, delay));
+      const state = states[i];
+      await this.apply(typeof state === 'string' ? this.bookmarks.get(state) : state);
+    }
+    return this;
+  }
+
+  /**
+   * Update tracked Params to match a state, without recording history.
+   * Params not included in the state are left unchanged.
+   * @param {object} state The state object.
+   * @returns {Promise<void>} A Promise that resolves once all resulting
+   *  value events have been dispatched.
+   */
+  async apply(state) {
+    ++this._restoring;
+    try {
+      for (const [name, param] of this.params) {
+        if (name in state) applyState(param, state[name]);
+      }
+      await settle(Array.from(this.params.values()));
+    } finally {
+      --this._restoring;
+    }
+  }
+}
+
+/**
+ * Take a snapshot of the state of a Param or Selection.
+ * @param {import('./Param.js').Param} param The Param or Selection.
+ * @returns {*} The Param value or an array of Selection clauses.
+ */
+function snapshot(param) {
+  return isSelection(param) ? param.clauses.slice() : param.value;
+}
+
+/**
+ * Test if two states differ.
+ * @param {object} a A state object.
+ * @param {object} b Another state object.
+ * @returns {boolean} True if the states differ, false otherwise.
+ */
+function changed(a, b) {
+  for (const name in b) {
+    const u = a[name];
+    const v = b[name];
+    if (Array.isArray(u) && Array.isArray(v)
+      ? u.length !== v.length || u.some((c, i) => c !== v[i])
+      : distinct(u, v)) {
+      return true;
+    }
+  }
+  return false;
+}
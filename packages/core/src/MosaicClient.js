@@ -46,6 +46,16 @@ export class MosaicClient {
     return true;
   }
 
+  /**
+   * Return a boolean indicating if the client can display approximate
+   * results. If true and the coordinator is in progressive mode, the client
+   * is first updated with the result of a sampled query and then refined
+   * with the result of the full query.
+   */
+  get progressive() {
+    return false;
+  }
+
   /**
    * Return an array of fields queried by this client.
    * @returns {object[]|null} The fields to retrieve info for.
@@ -82,10 +92,20 @@ export class MosaicClient {
 
   /**
    * Called by the coordinator to return a query result.
+   *
+   * Clients that can render incrementally may also implement an optional
+   * `queryResultBatch(batch)` method. If present, query results are streamed
+   * and the method is called with each Arrow record batch (as a table) as it
+   * arrives. This method is still called with the complete result once all
+   * batches have arrived.
    * @param {*} data The query result.
+   * @param {object} [info] Query result metadata.
+   * @param {boolean} [info.approximate] True if the result is an
+   *  approximation computed over a data sample, in which case a refined
+   *  result will follow.
    * @returns {this}
    */
-  queryResult(data) { // eslint-disable-line no-unused-vars
+  queryResult(data, info) { // eslint-disable-line no-unused-vars
     return this;
   }
 
@@ -99,6 +119,18 @@ export class MosaicClient {
     return this;
   }
 
+  /**
+   * Called by the coordinator to report a change in the database connection
+   * state, such as `'reconnecting'` or `'open'`. Clients may override this
+   * method to indicate a lost connection. Queued data queries are resent
+   * upon reconnection, so clients need not issue new queries.
+   * @param {string} state The connection state.
+   * @returns {this}
+   */
+  connectionState(state) { // eslint-disable-line no-unused-vars
+    return this;
+  }
+
   /**
    * Request the coordinator to execute a query for this client.
    * If an explicit query is not provided, the client query method will
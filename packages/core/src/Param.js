@@ -1,3 +1,4 @@
+import { isParamLike } from '@uwdata/mosaic-sql';
 import { AsyncDispatch } from './util/AsyncDispatch.js';
 import { distinct } from './util/distinct.js';
 
@@ -53,6 +54,30 @@ export class Param extends AsyncDispatch {
     return new Param(values);
   }
 
+  /**
+   * Create a new Param instance whose value is computed from the values of
+   * other Params. The value is recomputed whenever a dependency updates.
+   * The derived Param has a `dispose` method that removes its listeners
+   * from the dependencies, after which the value is no longer updated.
+   * @param {(...values: any[]) => any} fn A function that takes the current
+   *  dependency values, in order, and returns the derived value.
+   * @param {...*} deps The dependencies, either param-like values (such as
+   *  Params, Selections, or query params) or constant values.
+   * @returns {Param & { dispose: () => void }} The new Param instance.
+   */
+  static derive(fn, ...deps) {
+    const get = () => fn(...deps.map(d => isParamLike(d) ? d.value : d));
+    const p = new Param(get());
+    const update = () => p.update(get());
+    const params = deps.filter(d => isParamLike(d));
+    params.forEach(d => d.addEventListener('value', update));
+    return Object.assign(p, {
+      dispose() {
+        params.forEach(d => d.removeEventListener('value', update));
+      }
+    });
+  }
+
   /**
    * The current value of the Param.
    */
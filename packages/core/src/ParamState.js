@@ -0,0 +1,284 @@
+import { SQLExpression, column, isSQLExpression } from '@uwdata/mosaic-sql';
+import { isSelection } from './Selection.js';
+import {
+  clauseInterval, clauseIntervals, clauseMatch, clauseNegate, clausePoint,
+  clausePoints
+} from './SelectionClause.js';
+
+/**
+ * @typedef {Record<string, import('./Param.js').Param>
+ *  | Map<string, import('./Param.js').Param>
+ *  | { params: Map<string, import('./Param.js').Param> }} ParamCollection
+ *  Named Params and Selections, either as an object, a Map, or a
+ *  History instance.
+ */
+
+const VERSION = 1;
+
+/**
+ * Serialize the state of named Params and Selections into a
+ * JSON-compatible object. Param values are serialized as-is, with dates
+ * encoded as timestamps. Selection clauses are serialized by clause type,
+ * selected field(s), value, and metadata. Live clause properties, such as
+ * the clause source, associated clients, and predicate, are not included.
+ * Instead, each clause records the `id` property of its source, if any.
+ * @param {ParamCollection} params The named Params and Selections.
+ * @returns {object} The serialized state.
+ */
+export function serializeState(params) {
+  const state = {};
+  for (const [name, param] of entries(params)) {
+    state[name] = isSelection(param)
+      ? { clauses: param.clauses.map(serializeClause).filter(c => c) }
+      : { value: encodeValue(param.value) };
+  }
+  return { version: VERSION, params: state };
+}
+
+/**
+ * Deserialize a state object, producing a state compatible with the
+ * `History` restore method. Restored selection clauses are re-associated
+ * with their source components. A clause source is resolved by id using
+ * the *sources* option, or otherwise by matching the type and field(s) of
+ * a clause registered with the selection, such as by an interactor.
+ * Clauses without a matching source are given a new placeholder source.
+ * @param {ParamCollection} params The named Params and Selections.
+ * @param {object} data The serialized state, as produced by serializeState.
+ * @param {object} [options] Deserialization options.
+ * @param {Record<string, any> | Map<string, any> | any[]} [options.sources]
+ *  Clause source components keyed by id, or an array of source components
+ *  with `id` properties, such as inputs and interactors.
+ * @returns {object} The state object, keyed by Param name. Param entries
+ *  are values, Selection entries are clause arrays.
+ */
+export function deserializeState(params, data, { sources = {} } = {}) {
+  if (data?.version !== VERSION) {
+    throw new Error(`Unsupported state version: ${data?.version}`);
+  }
+  const lookup = Array.isArray(sources)
+    ? id => sources.find(source => source?.id === id)
+    : sources instanceof Map
+      ? id => sources.get(id)
+      : id => sources[id];
+  const state = {};
+  for (const [name, param] of entries(params)) {
+    const entry = data.params[name];
+    if (!entry) continue;
+    state[name] = isSelection(param)
+      ? entry.clauses.map(c => deserializeClause(c, param, lookup))
+      : decodeValue(entry.value);
+  }
+  return state;
+}
+
+/**
+ * Restore the state of named Params and Selections from a serialized
+ * state object. Params not included in the state are left unchanged.
+ * @param {ParamCollection} params The named Params and Selections.
+ * @param {object} data The serialized state, as produced by serializeState.
+ * @param {object} [options] Restore options, as for deserializeState.
+ * @returns {Promise<void>} A Promise that resolves once all resulting
+ *  value events have been dispatched.
+ */
+export async function restoreState(params, data, options) {
+  const state = deserializeState(params, data, options);
+  const list = [];
+  for (const [name, param] of entries(params)) {
+    if (name in state) {
+      applyState(param, state[name]);
+      list.push(param);
+    }
+  }
+  await settle(list);
+}
+
+/**
+ * Encode a serialized state as a URL hash string.
+ * @param {object} data The serialized state.
+ * @param {string} [key='mosaic'] The hash parameter name.
+ * @returns {string} The URL hash string, including a leading '#'.
+ */
+export function encodeHash(data, key = 'mosaic') {
+  const bytes = new TextEncoder().encode(JSON.stringify(data));
+  const base64 = btoa(Array.from(bytes, b => String.fromCharCode(b)).join(''))
+    .replaceAll('+', '-')
+    .replaceAll('/', '_')
+    .replace(/=+$/, '');
+  return `#${key}=${base64}`;
+}
+
+/**
+ * Decode a serialized state from a URL hash string.
+ * @param {string} hash The URL hash string, such as `location.hash`.
+ * @param {string} [key='mosaic'] The hash parameter name.
+ * @returns {object | null} The serialized state, or null if the hash does
+ *  not contain a state parameter.
+ */
+export function decodeHash(hash, key = 'mosaic') {
+  const param = new URLSearchParams(`${hash}`.replace(/^#/, '')).get(key);
+  if (!param) return null;
+  const binary = atob(param.replaceAll('-', '+').replaceAll('_', '/'));
+  const bytes = Uint8Array.from(binary, c => c.charCodeAt(0));
+  return JSON.parse(new TextDecoder().decode(bytes));
+}
+
+/**
+ * Update a Param or Selection to match a state entry. For Selections, the
+ * source of each added or removed clause is notified by invoking its
+ * `restore` method, if any, with the clause, such that components such as
+ * inputs and interactors can update their display without publishing a
+ * new clause. Removed clauses are passed as clauses with a null value.
+ * @param {import('./Param.js').Param} param The Param or Selection.
+ * @param {*} value The Param value or array of Selection clauses.
+ */
+export function applyState(param, value) {
+  if (!isSelection(param)) {
+    param.update(value);
+    return;
+  }
+  const current = param._resolved;
+  // remove clauses from sources that are not part of the target state
+  for (const clause of current) {
+    if (!value.some(c => c.source === clause.source)) {
+      const { source, clients } = clause;
+      const cleared = { source, clients, value: null, predicate: null };
+      param.update(cleared);
+      source?.restore?.(cleared);
+    }
+  }
+  // add target clauses that differ from the current state
+  for (const clause of value) {
+    if (!current.includes(clause)) {
+      param.update(clause);
+      clause.source?.restore?.(clause);
+    }
+  }
+}
+
+/**
+ * Wait until all pending value events of the given Params are dispatched.
+ * @param {import('./Param.js').Param[]} params The Params.
+ * @returns {Promise<void>}
+ */
+export async function settle(params) {
+  for (let p = pending(params); p.length; p = pending(params)) {
+    await Promise.all(p);
+  }
+}
+
+function pending(params) {
+  return params
+    .map(param => param._callbacks.get('value')?.pending)
+    .filter(x => x);
+}
+
+function entries(params) {
+  return params instanceof Map ? params
+    : params.params instanceof Map ? params.params
+    : Object.entries(params);
+}
+
+function clauseType(clause) {
+  const { meta, field, fields } = clause;
+  const type = meta?.type;
+  if (type === 'match' && field !== undefined) return 'match';
+  if (type === 'point') {
+    return fields ? 'points' : field !== undefined ? 'point' : null;
+  }
+  if (type === 'interval') {
+    return fields ? 'intervals' : field !== undefined ? 'interval' : null;
+  }
+  return null;
+}
+
+function serializeClause(clause) {
+  const type = clauseType(clause);
+  if (!type) return null;
+  const { meta, field, fields, value, source } = clause;
+  const rest = { ...meta };
+  delete rest.type;
+  return {
+    type,
+    ...(fields ? { fields: fields.map(encodeField) } : { field: encodeField(field) }),
+    value: encodeValue(value),
+    meta: encodeValue(rest),
+    source: source?.id ?? null
+  };
+}
+
+function deserializeClause(data, selection, lookup) {
+  const { type, meta = {}, source: id } = data;
+  const key = JSON.stringify(data.fields ?? data.field);
+  const registered = selection.registered.find(c => {
+    const s = serializeClause(c);
+    return s?.type === type && JSON.stringify(s.fields ?? s.field) === key;
+  });
+  const source = (id != null ? lookup(id) : undefined)
+    ?? registered?.source
+    ?? { id };
+  const clients = registered?.source === source ? registered.clients : undefined;
+  const field = data.field && decodeField(data.field);
+  const fields = data.fields?.map(decodeField);
+  const value = decodeValue(data.value);
+  const { scales, bin, pixelSize, method, negate } = decodeValue(meta);
+  const opt = { source, ...(clients ? { clients } : {}) };
+  const clause = createClause(type, field, fields, value, {
+    opt, scales, bin, pixelSize, method
+  });
+  return negate ? clauseNegate(clause) : clause;
+}
+
+function createClause(type, field, fields, value, {
+  opt, scales, bin, pixelSize, method
+}) {
+  switch (type) {
+    case 'point':
+      return clausePoint(field, value, opt);
+    case 'points':
+      return clausePoints(fields, value, opt);
+    case 'interval':
+      return clauseInterval(field, value, {
+        ...opt, scale: scales?.[0], bin, pixelSize
+      });
+    case 'intervals':
+      return clauseIntervals(fields, value, { ...opt, scales, bin, pixelSize });
+    case 'match':
+      return clauseMatch(field, value, { ...opt, method });
+    default:
+      throw new Error(`Unrecognized clause type: ${type}`);
+  }
+}
+
+function encodeField(field) {
+  return typeof field === 'string' ? { column: field }
+    : isSQLExpression(field) ? { sql: `${field}`, columns: field.columns }
+    : { column: field.column, ...(field.table ? { table: field.table } : {}) };
+}
+
+function decodeField({ column: name, table, sql, columns }) {
+  return sql != null ? new SQLExpression(sql, columns)
+    : table ? column(table, name)
+    : column(name);
+}
+
+function encodeValue(value) {
+  return value instanceof Date ? { $date: +value }
+    : Array.isArray(value) ? value.map(encodeValue)
+    : value && typeof value === 'object' && value.constructor === Object
+      ? Object.fromEntries(
+          Object.entries(value)
+            .filter(([, v]) => v !== undefined)
+            .map(([k, v]) => [k, encodeValue(v)])
+        )
+      : value;
+}
+
+function decodeValue(value) {
+  return Array.isArray(value) ? value.map(decodeValue)
+    : value && typeof value === 'object'
+      ? ('$date' in value ? new Date(value.$date)
+        : Object.fromEntries(
+            Object.entries(value).map(([k, v]) => [k, decodeValue(v)])
+          ))
+      : value;
+}
@@ -1,4 +1,5 @@
 import { Query, Ref, isDescribeQuery } from '@uwdata/mosaic-sql';
+import { queryTables } from './util/query-tables.js';
 import { QueryResult } from './util/query-result.js';
 
 function wait(callback) {
@@ -9,6 +10,10 @@ function wait(callback) {
   return method(callback);
 }
 
+function isEmpty(options) {
+  return options == null || Object.keys(options).length === 0;
+}
+
 /**
  * Create a consolidator to combine structurally compatible queries.
  * @param {*} enqueue Query manager enqueue method
@@ -35,9 +40,12 @@ export function consolidator(enqueue, cache, record) {
 
   return {
     add(entry, priority) {
-      if (entry.request.type === 'arrow') {
+      const { type, onBatch, options } = entry.request;
+      if (type === 'arrow' && !onBatch && isEmpty(options)) {
         // wait one frame, gather an ordered list of queries
         // only Apache Arrow is supported, so we can project efficiently
+        // streamed queries are not consolidated, as batches are not projected
+        // queries with connector options (such as a route) are kept as-is
         id = id || wait(() => run());
         pending.push({ entry, priority, index: pending.length });
       } else {
@@ -81,7 +89,7 @@ function entryGroups(entries, cache) {
  */
 function consolidationKey(query, cache) {
   const sql = `${query}`;
-  if (query instanceof Query && !cache.get(sql)) {
+  if (query instanceof Query && !cache.has(sql)) {
     if (
       // @ts-ignore
       query.orderby().length || query.where().length ||
@@ -131,7 +139,8 @@ function consolidate(group, enqueue, record) {
         type: 'arrow',
         cache: false,
         record: false,
-        query: (group.query = consolidatedQuery(group, record))
+        query: (group.query = consolidatedQuery(group, record)),
+        consolidates: group.map(({ entry }) => entry.result)
       },
       result: (group.result = new QueryResult())
     });
@@ -236,7 +245,7 @@ async function processResults(group, cache) {
       : map ? projectResult(data, map)
       : data;
     if (request.cache) {
-      cache.set(String(request.query), extract);
+      cache.set(String(request.query), extract, queryTables(request.query));
     }
     result.fulfill(extract);
   });
@@ -1,31 +1,77 @@
 import { consolidator } from './QueryConsolidator.js';
-import { lruCache, voidCache } from './util/cache.js';
+import { approxBytes, lruCache, voidCache } from './util/cache.js';
 import { priorityQueue } from './util/priority-queue.js';
+import { isIndexTable, mutatedTables, queryTables } from './util/query-tables.js';
 import { QueryResult } from './util/query-result.js';
+import { requestId } from './util/request-id.js';
 
 export const Priority = { High: 0, Normal: 1, Low: 2 };
 
+const RANKS = 3;
+
 export class QueryManager {
-  constructor() {
-    this.queue = priorityQueue(3);
+  /**
+   * Create a new query manager.
+   * @param {number | number[]} [concurrency=4] The maximum number of
+   *  concurrently submitted requests. Either a single number that applies
+   *  to every priority level, or an array of per-priority limits indexed
+   *  by priority rank.
+   */
+  constructor(concurrency = 4) {
+    this.queue = priorityQueue(RANKS);
     this.db = null;
     this.clientCache = null;
     this._logger = null;
     this._logQueries = false;
     this.recorders = [];
-    this.pending = null;
+    this.running = Array(RANKS).fill(0);
+    this.inflight = 0;
+    this.blocked = null;
+    this.exclusive = false;
+    this.submitted = new Map;
     this._consolidate = null;
+    this._idle = null;
+    this.listeners = new Set;
+    this.traces = new Map;
+    this.concurrency(concurrency);
   }
 
   next() {
-    if (this.pending || this.queue.isEmpty()) return;
-    const { request, result } = this.queue.next();
-    this.pending = this.submit(request, result);
-    this.pending.finally(() => { this.pending = null; this.next(); });
+    const { queue, running, limits } = this;
+    while (!this.exclusive) {
+      let entry = this.blocked;
+      if (entry) {
+        // an exclusive request waits for in-flight requests to complete
+        if (this.inflight) return;
+        this.blocked = null;
+      } else {
+        entry = queue.next(rank => running[rank] < limits[rank]);
+        if (!entry) return;
+        if (isExclusive(entry.request) && this.inflight) {
+          this.blocked = entry;
+          return;
+        }
+      }
+      this.run(entry);
+    }
+  }
+
+  run({ request, result, priority }) {
+    const exclusive = isExclusive(request);
+    this.exclusive = exclusive;
+    this.running[priority] += 1;
+    this.inflight += 1;
+    this.submit(request, result).finally(() => {
+      this.running[priority] -= 1;
+      this.inflight -= 1;
+      if (exclusive) this.exclusive = false;
+      this.next();
+      if (this.isIdle()) this._idle?.();
+    });
   }
 
   enqueue(entry, priority = Priority.Normal) {
-    this.queue.insert(entry, priority);
+    this.queue.insert({ ...entry, priority }, priority);
     this.next();
   }
 
@@ -37,19 +83,32 @@ export class QueryManager {
 
   async submit(request, result) {
     try {
-      const { query, type, cache = false, record = true, options } = request;
+      const { query, type, cache = false, record = true, onBatch, options } = request;
       const sql = query ? `${query}` : null;
+      this.traceStart(result, request);
 
       // update recorders
       if (record) {
         this.recordQuery(sql);
       }
 
+      // invalidate cached results that read tables modified by an exec
+      // exec requests run in isolation, so no other request is in flight
+      // results with unknown tables are kept if only index tables change
+      if (type === 'exec' && sql) {
+        const tables = mutatedTables(sql);
+        if (tables.length) {
+          const unknown = !tables.every(isIndexTable);
+          this.clientCache.invalidate?.(tables, { unknown });
+        }
+      }
+
       // check query cache
       if (cache) {
         const cached = this.clientCache.get(sql);
         if (cached) {
           this._logger.debug('Cache');
+          if (this.traces.has(result)) this.traces.get(result).cached = true;
           result.fulfill(cached);
           return;
         }
@@ -60,21 +119,74 @@ export class QueryManager {
       if (this._logQueries) {
         this._logger.debug('Query', { type, sql, ...options });
       }
-      const data = await this.db.query({ type, sql, ...options });
-      if (cache) this.clientCache.set(sql, data);
+      const id = requestId();
+      this.submitted.set(result, id);
+      const onBytes = this.traceBytes(result);
+      const data = onBatch && type === 'arrow'
+        ? await this.db.query({ type, sql, id, stream: true, ...options }, { onBatch, onBytes })
+        : await this.db.query({ type, sql, id, ...options }, { onBytes });
+      if (cache) this.clientCache.set(sql, data, queryTables(query));
       this._logger.debug(`Request: ${(performance.now() - t0).toFixed(1)}`);
       result.fulfill(data);
     } catch (err) {
       result.reject(err);
+    } finally {
+      this.submitted.delete(result);
     }
   }
 
+  /**
+   * Get or set the client-side query cache.
+   * @param {boolean | object} [value] A Boolean flag to enable or disable
+   *  caching, a cache instance, or an options object for a new LRU cache.
+   * @returns The current query cache.
+   */
   cache(value) {
     return value !== undefined
-      ? (this.clientCache = value === true ? lruCache() : (value || voidCache()))
+      ? (this.clientCache = value === true ? lruCache()
+          : typeof value?.get === 'function' ? value
+          : value ? lruCache(value)
+          : voidCache())
       : this.clientCache;
   }
 
+  /**
+   * Get or set the maximum number of concurrently submitted requests.
+   * @param {number | number[]} [value] A single limit that applies to all
+   *  priority levels, or an array of per-priority limits.
+   * @returns {number[]} The per-priority concurrency limits.
+   */
+  concurrency(value) {
+    if (value !== undefined) {
+      const limits = Array.isArray(value) ? value : Array(RANKS).fill(value);
+      this.limits = Array.from(
+        { length: RANKS },
+        (_, i) => Math.max(1, limits[i] ?? limits[limits.length - 1])
+      );
+      this.next();
+    }
+    return this.limits;
+  }
+
+  /**
+   * Get or set a callback function to invoke whenever the query manager
+   * becomes idle, with no queued or in-flight requests.
+   * @param {(() => void) | null} [callback] The idle callback.
+   * @returns {(() => void) | null} The current idle callback.
+   */
+  idle(callback) {
+    return callback !== undefined ? (this._idle = callback) : this._idle;
+  }
+
+  /**
+   * Indicate if the query manager is idle, with no queued or in-flight
+   * requests.
+   * @returns {boolean} True if idle, false otherwise.
+   */
+  isIdle() {
+    return !this.inflight && !this.blocked && this.queue.isEmpty();
+  }
+
   logger(value) {
     return value ? (this._logger = value) : this._logger;
   }
@@ -98,6 +210,9 @@ export class QueryManager {
   request(request, priority = Priority.Normal) {
     const result = new QueryResult();
     const entry = { request, result };
+    if (this.listeners.size && request.trace !== false) {
+      this.trace(request, result, priority);
+    }
     if (this._consolidate) {
       this._consolidate.add(entry, priority);
     } else {
@@ -106,10 +221,32 @@ export class QueryManager {
     return result;
   }
 
+  /**
+   * Cancel query requests. Queued requests are removed from the queue.
+   * For requests already submitted to the database, the database connector
+   * is asked to interrupt the running query, if supported. An interrupted
+   * request is rejected with the error reported by the connector.
+   * @param {QueryResult[]} requests The query results to cancel.
+   */
   cancel(requests) {
     const set = new Set(requests);
+    for (const result of set) {
+      const trace = this.traces.get(result);
+      if (trace) {
+        trace.status = 'canceled';
+        // requests that were never submitted will not settle
+        if (trace.wait == null) this.traceEnd(result);
+      }
+    }
     if (set.size) {
       this.queue.remove(({ result }) => set.has(result));
+      if (set.has(this.blocked?.result)) {
+        this.blocked = null;
+        this.next();
+      }
+      for (const [result, id] of this.submitted) {
+        if (set.has(result)) this.db.cancel?.(id);
+      }
     }
   }
 
@@ -118,6 +255,112 @@ export class QueryManager {
       result.reject('Cleared');
       return true;
     });
+    if (this.blocked) {
+      this.blocked.result.reject('Cleared');
+      this.blocked = null;
+    }
+  }
+
+  /**
+   * Add an event listener for query telemetry. Listeners for `'query'`
+   * events are invoked with a telemetry object once a query request
+   * settles. Requests are only traced while listeners are registered.
+   * @param {'query'} type The event type.
+   * @param {(trace: object) => void} callback The listener callback.
+   */
+  addEventListener(type, callback) {
+    if (type === 'query') this.listeners.add(callback);
+  }
+
+  /**
+   * Remove an event listener for query telemetry.
+   * @param {'query'} type The event type.
+   * @param {(trace: object) => void} callback The listener callback.
+   */
+  removeEventListener(type, callback) {
+    if (type === 'query') this.listeners.delete(callback);
+  }
+
+  /**
+   * Begin tracing a query request.
+   * @param {*} request The query request. The optional `trace` property
+   *  provides metadata, such as the triggering client and selection.
+   * @param {QueryResult} result The query result.
+   * @param {number} priority The query priority.
+   */
+  trace(request, result, priority) {
+    const { query, type, trace } = request;
+    this.traces.set(result, {
+      type,
+      sql: query ? `${query}` : null,
+      priority,
+      client: null,
+      selection: null,
+      cube: false,
+      sample: false,
+      ...trace,
+      cached: false,
+      consolidated: false,
+      status: 'pending',
+      error: null,
+      queued: performance.now(),
+      wait: null,
+      time: null,
+      bytes: null
+    });
+    result.then(
+      data => this.traceEnd(result, data),
+      error => this.traceEnd(result, undefined, error)
+    );
+  }
+
+  /**
+   * Record the submission of a query request, along with any requests
+   * consolidated into it.
+   * @param {QueryResult} result The query result.
+   * @param {*} request The query request.
+   */
+  traceStart(result, request) {
+    const now = performance.now();
+    for (const r of [result, ...(request.consolidates ?? [])]) {
+      const trace = this.traces.get(r);
+      if (trace) {
+        trace.wait = now - trace.queued;
+        trace.consolidated = r !== result;
+      }
+    }
+  }
+
+  /**
+   * Return a callback that records the number of result bytes received by
+   * the database connector, such as the Arrow IPC byte length, for a traced
+   * query request. Streamed results may report bytes for each batch.
+   * @param {QueryResult} result The query result.
+   * @returns {((bytes: number) => void) | undefined} The callback, or
+   *  undefined if the request is not traced.
+   */
+  traceBytes(result) {
+    const trace = this.traces.get(result);
+    return trace && (bytes => { trace.bytes = (trace.bytes ?? 0) + bytes; });
+  }
+
+  /**
+   * Complete tracing of a query request and notify listeners.
+   * @param {QueryResult} result The query result.
+   * @param {*} [data] The query result data.
+   * @param {*} [error] The query error, if any.
+   */
+  traceEnd(result, data, error) {
+    const trace = this.traces.get(result);
+    if (!trace) return;
+    this.traces.delete(result);
+    const now = performance.now();
+    if (trace.status === 'pending') trace.status = error ? 'error' : 'done';
+    if (trace.wait != null) trace.time = now - trace.queued - trace.wait;
+    trace.error = error ?? null;
+    // prefer the byte length reported by the connector, if any
+    trace.bytes ??= approxBytes(data);
+    this.listeners.forEach(callback => callback(trace));
   }
 
   record() {
@@ -141,3 +384,15 @@ export class QueryManager {
     return recorder;
   }
 }
+
+/**
+ * Test if a request must run in isolation. Requests that may modify database
+ * state (such as exec statements and bundle operations) wait for all
+ * in-flight requests to complete, and block later requests until done.
+ * @param {*} request The query request.
+ * @returns {boolean} True if the request is exclusive, false otherwise.
+ */
+function isExclusive(request) {
+  const { type } = request;
+  return type !== 'arrow' && type !== 'json';
+}
@@ -0,0 +1,142 @@
+import { coordinator as defaultCoordinator } from './Coordinator.js';
+import { MosaicClient } from './MosaicClient.js';
+import { Param } from './Param.js';
+
+/**
+ * A Mosaic client whose query result provides the value of a reactive
+ * parameter, such as the maximum date within the current selection.
+ * Query params are param-like: they provide a current value and dispatch
+ * `'value'` events, and so can be used in place of a Param in mark
+ * encodings, attributes, and SQL expressions. Query params are read-only
+ * and are updated only by query results.
+ */
+export class QueryParam extends MosaicClient {
+  /**
+   * Create a new query-backed param.
+   * @param {*} query The query providing the param value. Either a Query
+   *  instance, or a function that takes a filter predicate and returns a
+   *  query. Query instances are filtered by adding the filter predicate to
+   *  a cloned query.
+   * @param {object} [options] The param options.
+   * @param {import('./Selection.js').Selection} [options.filterBy] A
+   *  selection to filter the query by. The param is updated whenever the
+   *  selection changes.
+   * @param {*} [options.value] The initial param value, in effect until
+   *  the first query result arrives.
+   * @param {(data: any) => any} [options.extract] A function that maps a
+   *  query result to a param value. By default, the first column value of
+   *  the first result row is used.
+   */
+  constructor(query, { filterBy, value, extract = firstValue } = {}) {
+    super(filterBy);
+    this._query = query;
+    this._extract = extract;
+    this._param = new Param(value);
+  }
+
+  /**
+   * The current value of the param.
+   */
+  get value() {
+    return this._param.value;
+  }
+
+  /**
+   * Add an event listener callback for the given event type.
+   * Query params support `'value'` type events only.
+   * @param {string} type The event type.
+   * @param {(value: *) => void | Promise} callback The event handler
+   *  callback function to add.
+   */
+  addEventListener(type, callback) {
+    this._param.addEventListener(type, callback);
+  }
+
+  /**
+   * Remove an event listener callback for the given event type.
+   * @param {string} type The event type.
+   * @param {(value: *) => void | Promise} callback The event handler
+   *  callback function to remove.
+   */
+  removeEventListener(type, callback) {
+    this._param.removeEventListener(type, callback);
+  }
+
+  /**
+   * Returns a promise that resolves when any pending updates complete for
+   * the event of the given type currently being processed.
+   * @param {string} type The event type.
+   * @returns {Promise} A pending event promise.
+   */
+  pending(type) {
+    return this._param.pending(type);
+  }
+
+  /**
+   * Return the query providing the param value.
+   * @param {*} [filter] The filtering criteria to apply in the query.
+   * @returns {*} The client query.
+   */
+  query(filter = []) {
+    const q = this._query;
+    return typeof q === 'function' ? q(filter)
+      : typeof q?.clone === 'function' ? q.clone().where(filter)
+      : q;
+  }
+
+  /**
+   * Update the param value with a query result.
+   * @param {*} data The query result.
+   * @returns {this}
+   */
+  queryResult(data) {
+    this._param.update(this._extract(data));
+    return this;
+  }
+
+  /**
+   * Disconnect the param from its coordinator, after which the param value
+   * is no longer updated. Event listeners are retained.
+   */
+  dispose() {
+    this.coordinator?.disconnect(this);
+  }
+}
+
+/**
+ * Create a new query-backed param and connect it to a coordinator.
+ * @param {*} query The query providing the param value. Either a Query
+ *  instance, or a function that takes a filter predicate and returns a
+ *  query.
+ * @param {object} [options] The param options.
+ * @param {import('./Selection.js').Selection} [options.filterBy] A
+ *  selection to filter the query by.
+ * @param {*} [options.value] The initial param value.
+ * @param {(data: any) => any} [options.extract] A function that maps a
+ *  query result to a param value.
+ * @param {import('./Coordinator.js').Coordinator | null} [options.coordinator]
+ *  The coordinator to connect to. Defaults to the global coordinator. If
+ *  null, the param is not connected.
+ * @returns {QueryParam} The new query param.
+ */
+export function queryParam(query, {
+  coordinator = defaultCoordinator(),
+  ...options
+} = {}) {
+  const param = new QueryParam(query, options);
+  coordinator?.connect(param);
+  return param;
+}
+
+/**
+ * Extract the first column value of the first row of a query result.
+ * @param {*} data The query result, an Arrow table or array of row objects.
+ * @returns {*} The extracted value, or undefined if the result is empty.
+ */
+function firstValue(data) {
+  if (typeof data?.getChildAt === 'function') {
+    return data.numRows ? data.getChildAt(0)?.get(0) : undefined;
+  }
+  const row = data?.[0] ?? Array.from(data ?? [])[0];
+  return row == null ? undefined : Object.values(row)[0];
+}
@@ -1,5 +1,13 @@
-import { or } from '@uwdata/mosaic-sql';
+import { Query, column, isNotTrue, or, sql } from '@uwdata/mosaic-sql';
 import { Param } from './Param.js';
+import { clientTable, tableName } from './util/query-tables.js';
+
+/**
+ * @typedef {string | { table: string, column: string }} RelationKey
+ *  A join key column, either as a `'table.column'` string or an object.
+ * @typedef {[RelationKey, RelationKey] | { from: RelationKey, to: RelationKey }} Relation
+ *  A relationship between join key columns of two tables.
+ */
 
 /**
  * Test if a value is a Selection instance.
@@ -10,6 +18,26 @@ export function isSelection(x) {
   return x instanceof Selection;
 }
 
+/**
+ * Registry of named selection resolution strategies.
+ * @type {Map<string, (options: object) => SelectionResolver>}
+ */
+const resolvers = new Map;
+
+/**
+ * Register a named selection resolution strategy. Registered strategies
+ * can be instantiated using `Selection.create` and referenced by name in
+ * declarative specifications. A later registration for the same name
+ * replaces the prior strategy.
+ * @param {string} name The resolution strategy name.
+ * @param {(options: object) => SelectionResolver} factory A function that
+ *  takes selection options (such as *cross*, *empty*, and *relations*) and
+ *  returns a new selection resolver instance.
+ */
+export function registerResolver(name, factory) {
+  resolvers.set(name, factory);
+}
+
 /**
  * Represents a dynamic set of query filter predicates.
  */
@@ -25,10 +53,12 @@ export class Selection extends Param {
    * @param {boolean} [options.empty=false] Boolean flag indicating if a lack
    *  of clauses should correspond to an empty selection with no records. This
    *  setting determines the default selection state.
+   * @param {Relation[]} [options.relations] Relationships between tables,
+   *  used to filter clients of related tables. See `relate`.
    * @returns {Selection} The new Selection instance.
    */
-  static intersect({ cross = false, empty = false } = {}) {
-    return new Selection(new SelectionResolver({ cross, empty }));
+  static intersect({ cross = false, empty = false, relations } = {}) {
+    return new Selection(new SelectionResolver({ cross, empty, relations }));
   }
 
   /**
@@ -41,10 +71,14 @@ export class Selection extends Param {
    * @param {boolean} [options.empty=false] Boolean flag indicating if a lack
    *  of clauses should correspond to an empty selection with no records. This
    *  setting determines the default selection state.
+   * @param {Relation[]} [options.relations] Relationships between tables,
+   *  used to filter clients of related tables. See `relate`.
    * @returns {Selection} The new Selection instance.
    */
-  static union({ cross = false, empty = false } = {}) {
-    return new Selection(new SelectionResolver({ cross, empty, union: true }));
+  static union({ cross = false, empty = false, relations } = {}) {
+    return new Selection(
+      new SelectionResolver({ cross, empty, union: true, relations })
+    );
   }
 
   /**
@@ -57,10 +91,14 @@ export class Selection extends Param {
    * @param {boolean} [options.empty=false] Boolean flag indicating if a lack
    *  of clauses should correspond to an empty selection with no records. This
    *  setting determines the default selection state.
+   * @param {Relation[]} [options.relations] Relationships between tables,
+   *  used to filter clients of related tables. See `relate`.
    * @returns {Selection} The new Selection instance.
    */
-  static single({ cross = false, empty = false } = {}) {
-    return new Selection(new SelectionResolver({ cross, empty, single: true }));
+  static single({ cross = false, empty = false, relations } = {}) {
+    return new Selection(
+      new SelectionResolver({ cross, empty, single: true, relations })
+    );
   }
 
   /**
@@ -70,10 +108,58 @@ export class Selection extends Param {
    * @param {boolean} [options.empty=false] Boolean flag indicating if a lack
    *  of clauses should correspond to an empty selection with no records. This
    *  setting determines the default selection state.
+   * @param {Relation[]} [options.relations] Relationships between tables,
+   *  used to filter clients of related tables. See `relate`.
+   * @returns {Selection} The new Selection instance.
+   */
+  static crossfilter({ empty = false, relations } = {}) {
+    return new Selection(
+      new SelectionResolver({ cross: true, empty, relations })
+    );
+  }
+
+  /**
+   * Create a new Selection instance with a per-field resolution strategy.
+   * Clauses over the same field(s) are combined using a union (disjunction),
+   * while clauses over different fields are combined using an intersection
+   * (conjunction), as in a faceted search interface.
+   * @param {object} [options] The selection options.
+   * @param {boolean} [options.cross=false] Boolean flag indicating
+   *  cross-filtered resolution. If true, selection clauses will not
+   *  be applied to the clients they are associated with.
+   * @param {boolean} [options.empty=false] Boolean flag indicating if a lack
+   *  of clauses should correspond to an empty selection with no records. This
+   *  setting determines the default selection state.
+   * @param {Relation[]} [options.relations] Relationships between tables,
+   *  used to filter clients of related tables. See `relate`.
    * @returns {Selection} The new Selection instance.
    */
-  static crossfilter({ empty = false } = {}) {
-    return new Selection(new SelectionResolver({ cross: true, empty }));
+  static perField({ cross = false, empty = false, relations } = {}) {
+    return new Selection(new PerFieldResolver({ cross, empty, relations }));
+  }
+
+  /**
+   * Create a new Selection instance using a named resolution strategy,
+   * either built-in (`'intersect'`, `'union'`, `'single'`, `'crossfilter'`,
+   * or `'perField'`) or added using `registerResolver`.
+   * @param {string} name The resolution strategy name.
+   * @param {object} [options] The selection options, passed to the
+   *  resolution strategy.
+   * @returns {Selection} The new Selection instance.
+   */
+  static create(name, options) {
+    const factory = resolvers.get(name);
+    if (!factory) throw new Error(`Unrecognized selection resolver: ${name}`);
+    return new Selection(factory(options ?? {}));
+  }
+
+  /**
+   * Indicates if a named selection resolution strategy is available.
+   * @param {string} name The resolution strategy name.
+   * @returns {boolean} True if the strategy exists, false otherwise.
+   */
+  static has(name) {
+    return resolvers.has(name);
   }
 
   /**
@@ -85,6 +171,7 @@ export class Selection extends Param {
     super([]);
     this._resolved = this._value;
     this._resolver = resolver;
+    this._registered = new Map;
   }
 
   /**
@@ -155,6 +242,33 @@ export class Selection extends Param {
     return this.clauses.find(c => c.source === source)?.value;
   }
 
+  /**
+   * Declare a relationship between join key columns of two tables, such as
+   * a foreign key and the primary key it references. Clauses generated by
+   * a source over one table then filter clients of a related table using a
+   * semi-join predicate of the form `key IN (SELECT ...)`. Relationships
+   * apply in both directions and may be chained across multiple tables.
+   * @param {RelationKey} from A join key column, such as `'flights.carrier'`.
+   * @param {RelationKey} to The related join key column, such as
+   *  `'carriers.code'`.
+   * @returns {this} This Selection instance.
+   */
+  relate(from, to) {
+    this._resolver.relate(from, to);
+    return this;
+  }
+
+  /**
+   * Indicates if a selection clause applies to a client via a declared
+   * relationship, rather than directly filtering the client's table.
+   * @param {*} client The client to test.
+   * @param {*} clause The selection clause.
+   * @returns {boolean} True if the clause is related, false otherwise.
+   */
+  related(client, clause) {
+    return !!this._resolver.path(client, clause);
+  }
+
   /**
    * Emit an activate event with the given selection clause.
    * @param {*} clause The clause repesenting the potential activation.
@@ -163,6 +277,27 @@ export class Selection extends Param {
     this.emit('activate', clause);
   }
 
+  /**
+   * Register a clause representative of potential activations by a clause
+   * source, such as an interactor. Unlike `activate`, no event is emitted.
+   * Registered clauses enable precomputation in advance of any interaction,
+   * such as prebuilding of data cube indexes. A later registration for the
+   * same source replaces the prior clause.
+   * @param {*} clause The clause representing potential activations.
+   * @returns {this} This Selection instance.
+   */
+  register(clause) {
+    this._registered.set(clause.source, clause);
+    return this;
+  }
+
+  /**
+   * The registered clauses representative of potential activations.
+   */
+  get registered() {
+    return Array.from(this._registered.values());
+  }
+
   /**
    * Update the selection with a new selection clause.
    * @param {*} clause The selection clause to add.
@@ -248,12 +383,63 @@ export class SelectionResolver {
    * @param {boolean} [options.empty=false] Boolean flag indicating if a lack
    *  of clauses should correspond to an empty selection with no records. This
    *  setting determines the default selection state.
+   * @param {Relation[]} [options.relations] Relationships between tables.
    */
-  constructor({ union, cross, single, empty } = {}) {
+  constructor({ union, cross, single, empty, relations = [] } = {}) {
     this.union = !!union;
     this.cross = !!cross;
     this.single = !!single;
     this.empty = !!empty;
+    /** @type {{ from: object, to: object }[]} */
+    this.relations = [];
+    relations.forEach(r => Array.isArray(r)
+      ? this.relate(r[0], r[1])
+      : this.relate(r.from, r.to)
+    );
+  }
+
+  /**
+   * Declare a relationship between join key columns of two tables.
+   * @param {RelationKey} from A join key column.
+   * @param {RelationKey} to The related join key column.
+   */
+  relate(from, to) {
+    this.relations.push({ from: relationKey(from), to: relationKey(to) });
+  }
+
+  /**
+   * Determine the relationships that connect the table of a clause source
+   * to the table of a client, if the tables differ.
+   * @param {*} client The client whose data may be filtered.
+   * @param {*} clause The selection clause.
+   * @returns {{ from: object, to: object }[] | null} The sequence of join
+   *  steps from the clause table to the client table, or null if the
+   *  tables match, are unknown, or are not related.
+   */
+  path(client, clause) {
+    if (!this.relations.length || !clause) return null;
+    const target = clientTable(client);
+    const source = clientTable(clause.source)
+      ?? clientTable(clause.clients?.values().next().value);
+    if (!target || !source || target === source) return null;
+
+    // breadth-first search over relationships in both directions
+    const visited = new Map([[source, []]]);
+    const queue = [source];
+    while (queue.length) {
+      const table = queue.shift();
+      const steps = visited.get(table);
+      for (const { from, to } of this.relations) {
+        for (const [a, b] of [[from, to], [to, from]]) {
+          if (a.table !== table || visited.has(b.table)) continue;
+          const next = [...steps, { from: a, to: b }];
+          if (b.table === target) return next;
+          visited.set(b.table, next);
+          queue.push(b.table);
+        }
+      }
+    }
+    return null;
   }
 
   /**
@@ -291,7 +477,7 @@ export class SelectionResolver {
    *  based on the current state of this selection.
    */
   predicate(clauseList, active, client) {
-    const { empty, union } = this;
+    const { empty } = this;
 
     if (empty && !clauseList.length) {
       return ['FALSE'];
@@ -301,13 +487,28 @@ export class SelectionResolver {
     if (this.skip(client, active)) return undefined;
 
     // remove client-specific predicates if cross-filtering
-    const predicates = clauseList
-      .filter(clause => !this.skip(client, clause))
-      .map(clause => clause.predicate);
+    // map clauses over related tables to semi-join predicates
+    const clauses = clauseList.filter(clause => !this.skip(client, clause));
+    const predicates = clauses.map(clause => {
+      const pred = semijoin(clause.predicate, this.path(client, clause));
+      // negated predicates also select records with null predicate values
+      return clause.meta?.negate ? isNotTrue(pred) : pred;
+    });
+    return this.combine(predicates, clauses);
+  }
 
+  /**
+   * Combine clause predicates into a selection query predicate. Subclasses
+   * may override this method to implement custom resolution strategies.
+   * @param {*[]} predicates The clause predicates to combine.
+   * @param {*[]} clauses The selection clauses corresponding to each
+   *  predicate, in the same order.
+   * @returns {*} The combined query predicate.
+   */
+  combine(predicates, clauses) { // eslint-disable-line no-unused-vars
     // return appropriate conjunction or disjunction
     // an array of predicates is implicitly conjunctive
-    return union && predicates.length > 1 ? or(predicates) : predicates;
+    return this.union && predicates.length > 1 ? or(predicates) : predicates;
   }
 
   /**
@@ -324,3 +525,84 @@ export class SelectionResolver {
     return null;
   }
 }
+
+/**
+ * Implements a per-field selection resolution strategy. Clauses over the
+ * same field(s) are combined using a union (disjunction), while clauses
+ * over different fields are combined using an intersection (conjunction).
+ */
+export class PerFieldResolver extends SelectionResolver {
+  /**
+   * Combine clause predicates by field.
+   * @param {*[]} predicates The clause predicates to combine.
+   * @param {*[]} clauses The selection clauses corresponding to each
+   *  predicate, in the same order.
+   * @returns {*} The combined query predicate.
+   */
+  combine(predicates, clauses) {
+    const groups = new Map;
+    clauses.forEach((clause, i) => {
+      const key = fieldKey(clause);
+      if (groups.has(key)) groups.get(key).push(predicates[i]);
+      else groups.set(key, [predicates[i]]);
+    });
+    // an array of predicates is implicitly conjunctive
+    return Array.from(groups.values(), g => g.length > 1 ? or(g) : g[0]);
+  }
+}
+
+registerResolver('intersect', ({ cross, empty, relations }) =>
+  new SelectionResolver({ cross, empty, relations }));
+registerResolver('union', ({ cross, empty, relations }) =>
+  new SelectionResolver({ cross, empty, union: true, relations }));
+registerResolver('single', ({ cross, empty, relations }) =>
+  new SelectionResolver({ cross, empty, single: true, relations }));
+registerResolver('crossfilter', ({ empty, relations }) =>
+  new SelectionResolver({ cross: true, empty, relations }));
+registerResolver('perField', ({ cross, empty, relations }) =>
+  new PerFieldResolver({ cross, empty, relations }));
+
+/**
+ * Determine a grouping key for the field(s) selected by a clause.
+ * Clauses without field information are keyed by their source.
+ * @param {*} clause The selection clause.
+ * @returns {*} The grouping key.
+ */
+function fieldKey(clause) {
+  const { field, fields, source } = clause;
+  return fields ? fields.map(f => `${f}`).join(',')
+    : field !== undefined ? `${field}`
+    : source;
+}
+
+/**
+ * Parse a relationship join key.
+ * @param {RelationKey} key The join key, either as a `'table.column'`
+ *  string or an object with table and column properties.
+ * @returns {{ table: string, column: string, name: string }} The join key
+ *  object, with a normalized table name and the original table name.
+ */
+function relationKey(key) {
+  if (typeof key === 'string') {
+    const index = key.lastIndexOf('.');
+    if (index < 0) throw new Error(`Relation key must include a table: ${key}`);
+    key = { table: key.slice(0, index), column: key.slice(index + 1) };
+  }
+  return { table: tableName(key.table), column: key.column, name: key.table };
+}
+
+/**
+ * Map a predicate over one table to a semi-join predicate over a related
+ * table, by following a sequence of join steps.
+ * @param {*} predicate The clause predicate.
+ * @param {{ from: object, to: object }[] | null} steps The join steps.
+ * @returns {*} The semi-join predicate, or the input predicate if there
+ *  are no join steps.
+ */
+function semijoin(predicate, steps) {
+  if (!steps || predicate == null) return predicate;
+  return steps.reduce((pred, { from, to }) => {
+    const query = Query.from(from.name).select(from.column).where(pred);
+    return sql`(${column(to.column)} IN (${query}))`;
+  }, predicate);
+}
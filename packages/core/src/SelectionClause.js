@@ -1,6 +1,6 @@
 import {
-  SQLExpression, and, contains, isBetween, isNotDistinct, literal,
-  or, prefix, regexp_matches, suffix
+  SQLExpression, and, contains, isBetween, isIn, isNotDistinct, isNull,
+  literal, or, prefix, regexp_matches, suffix
 } from '@uwdata/mosaic-sql';
 import { MosaicClient } from './MosaicClient.js';
 
@@ -36,6 +36,7 @@ export function clausePoint(field, value, {
     meta: { type: 'point' },
     source,
     clients,
+    field,
     value,
     predicate
   };
@@ -59,7 +60,13 @@ export function clausePoints(fields, value, {
 }) {
   /** @type {SQLExpression | null} */
   let predicate = null;
-  if (value) {
+  if (value?.length && fields.length === 1) {
+    // test membership of a single field using an IN list
+    const [field] = fields;
+    const vals = value.map(v => v[0]);
+    const list = isIn(field, vals.filter(v => v != null));
+    predicate = vals.some(v => v == null) ? or(list, isNull(field)) : list;
+  } else if (value) {
     const clauses = value.map(vals => {
       const list = vals.map((v, i) => isNotDistinct(fields[i], literal(v)));
       return list.length > 1 ? and(list) : list[0];
@@ -70,6 +77,7 @@ export function clausePoints(fields, value, {
     meta: { type: 'point' },
     source,
     clients,
+    fields,
     value,
     predicate
   };
@@ -100,7 +108,7 @@ export function clauseInterval(field, value, {
   const predicate = value != null ? isBetween(field, value) : null;
   /** @type {import('./util/selection-types.js').IntervalMetadata} */
   const meta = { type: 'interval', scales: scale && [scale], bin, pixelSize };
-  return { meta, source, clients, value, predicate };
+  return { meta, source, clients, field, value, predicate };
 }
 
 /**
@@ -131,7 +139,7 @@ export function clauseIntervals(fields, value, {
     : null;
   /** @type {import('./util/selection-types.js').IntervalMetadata} */
   const meta = { type: 'interval', scales, bin, pixelSize };
-  return { meta, source, clients, value, predicate };
+  return { meta, source, clients, fields, value, predicate };
 }
 
 const MATCH_METHODS = { contains, prefix, suffix, regexp: regexp_matches };
@@ -157,5 +165,20 @@ export function clauseMatch(field, value, {
   const predicate = value ? fn(field, literal(value)) : null;
   /** @type {import('./util/selection-types.js').MatchMetadata} */
   const meta = { type: 'match', method };
-  return { meta, source, clients, value, predicate };
+  return { meta, source, clients, field, value, predicate };
+}
+
+/**
+ * Generate a negated copy of a selection clause, which selects all records
+ * that do not match the clause predicate, such as everything outside of a
+ * brushed interval. Negation is recorded in the clause metadata and is
+ * applied by the selection resolver, such that the clause retains its
+ * original predicate for optimizations such as data cube indexing.
+ * @param {SelectionClause} clause The selection clause to negate.
+ * @param {boolean} [negate=true] Boolean flag indicating negation. If
+ *  false, a non-negated copy of the clause is returned.
+ * @returns {SelectionClause} The negated selection clause.
+ */
+export function clauseNegate(clause, negate = true) {
+  return { ...clause, meta: { ...clause.meta, negate: !!negate } };
 }
@@ -0,0 +1,206 @@
+import { sqlTables, tableParts } from '../util/query-tables.js';
+import { requestId } from '../util/request-id.js';
+import { routingConnector } from './routing.js';
+import { wasmConnector } from './wasm.js';
+
+/**
+ * Create a new hybrid connector that combines a remote database, such as a
+ * DuckDB data server, with a local DuckDB-WASM instance in the browser.
+ * Query results fetched from the remote database can be pulled into local
+ * tables. Subsequent queries that only reference local tables are then
+ * answered in the browser without network round trips, while all other
+ * queries are sent to the remote database. Tables are only pulled upon
+ * explicit request, using the `pull` method or `coordinator.pull()`.
+ *
+ * Pulling a table replaces its local contents, so a pull waits for running
+ * local queries over the table to complete, and local queries over the
+ * table wait for a running pull to complete.
+ * @param {object} options Connector options.
+ * @param {object} options.remote The connector for the remote database.
+ * @param {object} [options.local] The DuckDB-WASM connector for local
+ *  queries. If unspecified, a new `wasmConnector` is created.
+ * @param {string[]} [options.tables] The names of tables that already
+ *  exist in the local database.
+ * @param {string[]} [options.schemas] The names of schemas that already
+ *  exist in the local database.
+ */
+export function hybridConnector({
+  remote,
+  local = wasmConnector(),
+  tables = [],
+  schemas = []
+}) {
+  const router = routingConnector({
+    routes: [{ connector: local, tables, schemas }],
+    fallback: remote
+  });
+  const { addEventListener, removeEventListener, cancel, query } = router;
+  const listeners = new Set;
+  const pulled = new Set;
+  const pulling = new Set; // ids of remote pull requests
+  const readers = new Map; // running local queries, by table
+  const writers = new Map; // running table replacements, by table
+  const notify = table => listeners.forEach(
+    callback => callback({ table, rowid: null })
+  );
+  const key = table => tableParts(table).join('.');
+
+  /**
+   * Run a local query over tables once running pulls of the tables are
+   * complete, and track it as a reader of the tables until it settles.
+   * @param {string[]} tables The normalized names of the queried tables.
+   * @param {() => Promise} run The query callback.
+   */
+  async function read(tables, run) {
+    // check again after waiting, as another pull may have started
+    while (tables.some(table => writers.has(table))) {
+      await Promise.all(tables.map(table => writers.get(table)));
+    }
+    const result = run();
+    const done = result.catch(() => {});
+    for (const table of tables) {
+      if (!readers.has(table)) readers.set(table, new Set);
+      readers.get(table).add(done);
+    }
+    done.then(() => tables.forEach(table => readers.get(table).delete(done)));
+    return result;
+  }
+
+  /**
+   * Replace the contents of a local table, once running local queries and
+   * replacements of the table are complete.
+   * @param {string} table The local table name.
+   * @param {() => Promise} run The replacement callback.
+   */
+  async function write(table, run) {
+    const name = key(table);
+    const prev = writers.get(name);
+    const result = Promise.resolve(prev)
+      .then(() => Promise.all(readers.get(name) ?? []))
+      .then(run);
+    const done = result.catch(() => {});
+    writers.set(name, done);
+    try {
+      return await result;
+    } finally {
+      if (writers.get(name) === done) writers.delete(name);
+    }
+  }
+
+  /**
+   * Fetch a query result from the remote database and replace the
+   * contents of a local table with it.
+   * @param {object} request The query request, with the local table
+   *  name as the `pull` property.
+   * @returns {Promise} A Promise for the pulled Arrow table.
+   */
+  async function pull({ pull: table, ...request }) {
+    if (request.id != null) pulling.add(request.id);
+    let data;
+    try {
+      data = await remote.query({ ...request, type: 'arrow' });
+    } finally {
+      pulling.delete(request.id);
+    }
+    await write(table, async () => {
+      await local.insertArrow(table, data);
+      router.register(table, local);
+      pulled.add(table);
+    });
+    notify(table);
+    return data;
+  }
+
+  return Object.assign(router, {
+    local,
+    remote,
+    /**
+     * Add an event listener. Listeners for `'notify'` events are also
+     * invoked when a table is pulled or released, such that coordinators
+     * invalidate cached results and data cube index tables over the table,
+     * and update any clients of a subscribed live table.
+     * @param {'state' | 'notify'} type The event type.
+     * @param {(value: any) => void} callback The listener callback.
+     */
+    addEventListener(type, callback) {
+      if (type === 'notify') listeners.add(callback);
+      addEventListener(type, callback);
+    },
+    /**
+     * Remove an event listener.
+     * @param {'state' | 'notify'} type The event type.
+     * @param {(value: any) => void} callback The listener callback.
+     */
+    removeEventListener(type, callback) {
+      if (type === 'notify') listeners.delete(callback);
+      removeEventListener(type, callback);
+    },
+    /**
+     * Cancel a query request, including the remote query of a pull.
+     * @param {string} id The id of the query request to cancel.
+     */
+    async cancel(id) {
+      await (pulling.has(id) ? remote.cancel?.(id) : cancel(id));
+    },
+    /**
+     * Query the remote or local database. A request with a `pull` property
+     * naming a local table is a pull request: its result is fetched from
+     * the remote database and replaces the contents of the local table.
+     * Local queries over a table are not run while the table is pulled.
+     * @param {object} request The query request, as for other connectors.
+     * @param {object} [options] Connector-specific query options.
+     * @returns the query result
+     */
+    async query(request, options) {
+      if (request.pull != null) return pull(request);
+      const tables = sqlTables(request.sql ?? '')
+        .map(parts => parts.join('.'))
+        .filter(table => router.locate(table) === local);
+      return tables.length
+        ? read(tables, () => query(request, options))
+        : query(request, options);
+    },
+    /**
+     * Fetch the result of a query from the remote database as an Arrow
+     * table, and create or replace a local table with its contents.
+     * Once pulled, queries that only reference the table are answered
+     * locally. Notification listeners are informed that the table changed,
+     * such that data cube index tables over the table are rebuilt locally.
+     * Use `coordinator.pull()` instead to issue the pull as a query request
+     * of the coordinator, with support for cancellation and telemetry.
+     * @param {string} table The local table name.
+     * @param {*} query The query to run remotely, either a Query instance
+     *  or a SQL string.
+     * @returns {Promise<number>} A Promise that resolves to the number of
+     *  pulled rows.
+     */
+    async pull(table, query) {
+      const request = { type: 'arrow', sql: `${query}`, id: requestId() };
+      const data = await pull({ ...request, pull: table });
+      return data.numRows;
+    },
+    /**
+     * Drop a pulled table from the local database. Subsequent queries that
+     * reference the table are again routed to the remote database, and
+     * notification listeners are informed that the table changed.
+     * @param {string} table The local table name.
+     * @returns {Promise<void>} A Promise that resolves once dropped.
+     */
+    async release(table) {
+      if (pulled.delete(table)) {
+        await write(table, async () => {
+          router.register(table, null);
+          await local.query({ type: 'exec', sql: `DROP TABLE IF EXISTS ${table}` });
+        });
+        notify(table);
+      }
+    },
+    /**
+     * The names of tables pulled into the local database.
+     * @returns {string[]} The pulled table names.
+     */
+    pulledTables() {
+      return Array.from(pulled);
+    }
+  });
+}
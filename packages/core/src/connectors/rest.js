@@ -1,26 +1,56 @@
-import { tableFromIPC } from 'apache-arrow';
+import { RecordBatchReader, tableFromIPC } from 'apache-arrow';
+import { readBatches } from '../util/read-batches.js';
 
 export function restConnector(uri = 'http://localhost:3000/') {
+  function post(body) {
+    return fetch(uri, {
+      method: 'POST',
+      mode: 'cors',
+      cache: 'no-cache',
+      credentials: 'omit',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify(body)
+    });
+  }
+
   return {
+    /**
+     * The server URI, identifying the database queried by this connector.
+     */
+    id: uri,
+    /**
+     * Ask the DuckDB server to interrupt a running query.
+     * @param {string} id The id of the query request to cancel.
+     */
+    async cancel(id) {
+      await post({ type: 'cancel', id });
+    },
     /**
      * Query the DuckDB server.
      * @param {object} query
      * @param {'exec' | 'arrow' | 'json'} [query.type] The query type: 'exec', 'arrow', or 'json'.
      * @param {string} query.sql A SQL query string.
+     * @param {string} [query.id] A request id, used for cancellation.
+     * @param {object} [options] Query options.
+     * @param {(batch: import('apache-arrow').Table) => void} [options.onBatch]
+     *  Callback invoked with each Arrow record batch as it is read from the
+     *  response. Only applies to 'arrow' queries.
+     * @param {(bytes: number) => void} [options.onBytes] Callback invoked
+     *  with the byte length of a non-streamed Arrow IPC response.
      * @returns the query result
      */
-    async query(query) {
-      const req = fetch(uri, {
-        method: 'POST',
-        mode: 'cors',
-        cache: 'no-cache',
-        credentials: 'omit',
-        headers: { 'Content-Type': 'application/json' },
-        body: JSON.stringify(query)
-      });
+    async query(query, { onBatch, onBytes } = {}) {
+      const req = post(query);
+
+      if (query.type === 'arrow' && !onBatch) {
+        const bytes = new Uint8Array(await (await req).arrayBuffer());
+        onBytes?.(bytes.byteLength);
+        return tableFromIPC(bytes);
+      }
 
       return query.type === 'exec' ? req
-        : query.type === 'arrow' ? tableFromIPC(req)
+        : query.type === 'arrow'
+          ? readBatches(await RecordBatchReader.from(req), onBatch)
         : (await req).json();
     }
   };
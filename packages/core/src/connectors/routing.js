@@ -0,0 +1,208 @@
+import { createdTables, sqlTables, tableParts } from '../util/query-tables.js';
+
+// setup statements without table references that apply to all databases
+const BROADCAST = /^\s*(?:CREATE\s+SCHEMA|INSTALL|LOAD|SET)\b/i;
+
+/**
+ * Create a new routing connector that dispatches queries to different
+ * database connectors based on the tables they reference. For example,
+ * small lookup tables may be queried in the browser using DuckDB-WASM,
+ * while a large fact table is queried using a DuckDB data server.
+ *
+ * Queries are routed to the connector serving the tables they reference.
+ * Tables that do not match a route are served by the fallback connector. A
+ * query that references tables of different connectors is rejected. Tables
+ * created by exec statements, such as data cube index tables, are routed to
+ * the connector that created them. A query request may also name a table
+ * to route by (`route`), such as for statements on tables that exist in
+ * each routed database.
+ * @param {object} options Connector options.
+ * @param {{
+ *   connector: object,
+ *   tables?: string[],
+ *   schemas?: string[]
+ * }[]} [options.routes] The routes to match. Each route specifies a
+ *  connector along with the (possibly schema-qualified) names of tables
+ *  and the names of schemas it serves.
+ * @param {object} options.fallback The connector for queries that do not
+ *  match any route.
+ */
+export function routingConnector({ routes = [], fallback }) {
+  const connectors = Array.from(
+    new Set([fallback, ...routes.map(route => route.connector)])
+  );
+  const matchers = routes.map(({ connector, tables = [], schemas = [] }) => ({
+    connector,
+    tables: new Set(tables.map(name => tableParts(name).join('.'))),
+    schemas: new Set(schemas.map(name => tableParts(name).join('.')))
+  }));
+  const located = new Map;
+  const submitted = new Map;
+  const listeners = { state: new Map, notify: new Map };
+
+  /**
+   * Look up the connector for a table.
+   * @param {string[]} parts The normalized table name parts.
+   * @returns {object | undefined} The matching connector, if any.
+   */
+  function match(parts) {
+    const name = parts.join('.');
+    const table = parts[parts.length - 1];
+    const schemas = parts.slice(0, -1);
+    return located.get(name)
+      ?? matchers.find(m => m.tables.has(name)
+        || (schemas.length === 0 && m.tables.has(table))
+        || schemas.some(schema => m.schemas.has(schema))
+      )?.connector;
+  }
+
+  /**
+   * Determine the connector for a SQL string.
+   * @param {string} sql The SQL string.
+   * @returns {object} The connector to query.
+   */
+  function route(sql) {
+    const created = new Set(createdTables(sql).map(parts => parts.join('.')));
+    const matched = new Set;
+    for (const parts of sqlTables(sql)) {
+      if (created.has(parts.join('.'))) continue;
+      matched.add(match(parts) ?? fallback);
+    }
+    if (matched.size > 1) {
+      throw new Error(`Query references tables of multiple connectors: ${sql}`);
+    }
+    return matched.size ? matched.values().next().value : fallback;
+  }
+
+  /**
+   * Group table names by connector.
+   * @param {string[]} tables The table names.
+   * @returns {Map<object, string[]>} Table names keyed by connector.
+   */
+  function groupTables(tables) {
+    const groups = new Map;
+    for (const table of tables) {
+      const db = match(tableParts(table)) ?? fallback;
+      if (!groups.has(db)) groups.set(db, []);
+      groups.get(db).push(table);
+    }
+    return groups;
+  }
+
+  /**
+   * The combined connection state of all routed connectors: `'open'` if
+   * all reporting connectors are open, otherwise the first other state.
+   */
+  function state() {
+    const states = connectors.map(db => db.state).filter(s => s != null);
+    return states.length
+      ? (states.find(s => s !== 'open') ?? 'open')
+      : undefined;
+  }
+
+  return {
+    connectors,
+    /**
+     * An identifier of the databases queried by this connector, combining
+     * the identifiers of its connectors. Undefined if any connector lacks
+     * an identifier.
+     */
+    get id() {
+      const ids = connectors.map(db => db.id);
+      return ids.every(id => id != null) ? ids.join(' ') : undefined;
+    },
+    get state() {
+      return state();
+    },
+    /**
+     * Get the connector for a table.
+     * @param {string} table The (possibly schema-qualified) table name.
+     * @returns {object} The connector serving the table.
+     */
+    locate(table) {
+      return match(tableParts(table)) ?? fallback;
+    },
+    /**
+     * Route a table to a connector, for example after the table has been
+     * created or loaded in that database.
+     * @param {string} table The (possibly schema-qualified) table name.
+     * @param {object | null} connector The connector serving the table.
+     *  If null, the table is again routed by the configured routes.
+     */
+    register(table, connector) {
+      const name = tableParts(table).join('.');
+      if (connector) located.set(name, connector);
+      else located.delete(name);
+    },
+    /**
+     * Cancel a query request, using the connector it was routed to.
+     * @param {string} id The id of the query request to cancel.
+     */
+    async cancel(id) {
+      await submitted.get(id)?.cancel?.(id);
+    },
+    /**
+     * Route a query to the connector of the tables it references, or to
+     * the connector of the table named by the `route` request property.
+     * @param {object} query The query request, as for other connectors.
+     * @param {object} [options] Connector-specific query options.
+     * @returns the query result
+     */
+    async query(query, options) {
+      const { type, sql, id, route: table } = query;
+      if (type === 'exec' && BROADCAST.test(sql) && !sqlTables(sql).length) {
+        await Promise.all(connectors.map(db => db.query(query, options)));
+        return;
+      }
+      const db = table != null
+        ? (match(tableParts(table)) ?? fallback)
+        : route(sql);
+      if (id != null) submitted.set(id, db);
+      try {
+        const result = await db.query(query, options);
+        if (type === 'exec' && table == null) {
+          createdTables(sql).forEach(parts => located.set(parts.join('.'), db));
+        }
+        return result;
+      } finally {
+        submitted.delete(id);
+      }
+    },
+    /**
+     * Subscribe to modification notifications for tables, using the
+     * connectors that serve the tables.
+     * @param {string[]} tables The table names.
+     */
+    subscribe(tables) {
+      for (const [db, names] of groupTables(tables)) db.subscribe?.(names);
+    },
+    /**
+     * Unsubscribe from modification notifications for tables.
+     * @param {string[]} tables The table names.
+     */
+    unsubscribe(tables) {
+      for (const [db, names] of groupTables(tables)) db.unsubscribe?.(names);
+    },
+    /**
+     * Add an event listener to all routed connectors. State listeners are
+     * invoked with the combined connection state.
+     * @param {'state' | 'notify'} type The event type.
+     * @param {(value: any) => void} callback The listener callback.
+     */
+    addEventListener(type, callback) {
+      const listener = type === 'state' ? () => callback(state()) : callback;
+      listeners[type]?.set(callback, listener);
+      connectors.forEach(db => db.addEventListener?.(type, listener));
+    },
+    /**
+     * Remove an event listener from all routed connectors.
+     * @param {'state' | 'notify'} type The event type.
+     * @param {(value: any) => void} callback The listener callback.
+     */
+    removeEventListener(type, callback) {
+      const listener = listeners[type]?.get(callback) ?? callback;
+      listeners[type]?.delete(callback);
+      connectors.forEach(db => db.removeEventListener?.(type, listener));
+    }
+  };
+}
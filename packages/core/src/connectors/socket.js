@@ -1,97 +1,308 @@
 import { tableFromIPC } from 'apache-arrow';
+import { requestId } from '../util/request-id.js';
 
-export function socketConnector(uri = 'ws://localhost:3000/') {
+/**
+ * Create a new Web Socket connector to a DuckDB data server.
+ * @param {string} [uri] The server URI.
+ * @param {object} [options] Connector options.
+ * @param {boolean} [options.reconnect=true] Flag indicating if the connector
+ *  should reconnect after the socket unexpectedly closes.
+ * @param {number} [options.retries=Infinity] The maximum number of
+ *  consecutive reconnection attempts.
+ * @param {number} [options.minDelay=250] The delay in milliseconds before
+ *  the first reconnection attempt. The delay doubles after each attempt.
+ * @param {number} [options.maxDelay=30000] The maximum delay in milliseconds
+ *  between reconnection attempts.
+ * @param {number} [options.replays=3] The maximum number of times an
+ *  idempotent request is re-sent after reconnection. Requests that exceed
+ *  this limit, for example because they repeatedly cause the connection
+ *  to close, are rejected.
+ */
+export function socketConnector(uri = 'ws://localhost:3000/', {
+  reconnect = true,
+  retries = Infinity,
+  minDelay = 250,
+  maxDelay = 30000,
+  replays = 3
+} = {}) {
   const queue = [];
-  let connected = false;
-  let request = null;
+  const pending = new Map;
+  const listeners = { state: new Set, notify: new Set };
+  const subscriptions = new Set;
+  let state = 'closed';
+  let attempts = 0;
+  let binary = null;
   let ws;
 
+  function setState(value) {
+    if (state !== value) {
+      state = value;
+      listeners.state.forEach(callback => callback(value));
+    }
+  }
+
+  function reject(requests, error) {
+    requests.forEach(request => request.reject(error));
+  }
+
+  // responses without a request id, as sent by servers that do not tag
+  // responses, are matched to the oldest pending request
+  function responseId(id) {
+    return id ?? pending.keys().next().value;
+  }
+
   const events = {
     open() {
-      connected = true;
+      attempts = 0;
+      // (re-)subscribe to table modification notifications
+      if (subscriptions.size) {
+        send({ type: 'subscribe', tables: Array.from(subscriptions) });
+      }
+      setState('open');
       next();
     },
 
     close() {
-      connected = false;
-      request = null;
-      ws = null;
-      while (queue.length) {
-        queue.shift().reject('Socket closed');
+      binary = null;
+
+      // requests without a response may be replayed if idempotent
+      // streamed requests that already received batches are not replayed
+      // sent requests are replayed a limited number of times
+      const sent = new Set(pending.values());
+      const requests = [...sent, ...queue.splice(0)];
+      pending.clear();
+
+      if (reconnect && attempts < retries) {
+        const replay = requests.filter(request => {
+          const { query, tables } = request;
+          if (!isIdempotent(query) || tables?.length) return false;
+          if (sent.has(request)) request.replays = (request.replays ?? 0) + 1;
+          return !(request.replays > replays);
+        });
+        reject(requests.filter(r => !replay.includes(r)), 'Socket closed');
+        queue.push(...replay);
+        setState('reconnecting');
+        const delay = Math.min(maxDelay, minDelay * 2 ** attempts++);
+        setTimeout(init, delay);
+      } else {
+        ws = null;
+        attempts = 0;
+        reject(requests, 'Socket closed');
+        setState('closed');
       }
     },
 
     error(event) {
-      if (request) {
-        const { reject } = request;
-        request = null;
-        next();
-        reject(event);
-      } else {
-        console.error('WebSocket error: ', event);
-      }
+      // errors can not be attributed to a specific request
+      // the socket will close, rejecting or replaying pending requests
+      console.error('WebSocket error: ', event);
     },
 
     message({ data }) {
-      if (request) {
-        const { query, resolve, reject } = request;
-
-        // clear state, start next request
-        request = null;
-        next();
-
-        // process result
-        if (typeof data === 'string') {
-          const json = JSON.parse(data);
-          json.error ? reject(json.error) : resolve(json);
-        } else if (query.type === 'exec') {
-          resolve();
-        } else if (query.type === 'arrow') {
-          resolve(tableFromIPC(data.arrayBuffer()));
+      if (typeof data === 'string') {
+        // text messages are JSON objects tagged with a request id
+        // or untagged notifications of table modifications
+        // untagged JSON results are sent as arrays
+        const message = JSON.parse(data);
+        const { id: tag, type, error, result, ...rest } =
+          Array.isArray(message) ? { result: message } : message;
+        const id = type === 'notify' ? tag : responseId(tag);
+        const request = pending.get(id);
+        if (id == null && type === 'notify') {
+          listeners.notify.forEach(callback => callback(rest));
+        } else if (!request) {
+          console.warn(`Unexpected WebSocket message for request ${id}`);
+        } else if (error) {
+          pending.delete(id);
+          request.reject(error);
+        } else if (type === 'arrow' || type === 'batch') {
+          // the next binary message holds the Arrow result or record batch
+          binary = { id, batch: type === 'batch' };
         } else {
-          throw new Error(`Unexpected socket data: ${data}`);
+          pending.delete(id);
+          request.resolve(
+            request.query.type === 'exec' ? undefined
+              : request.tables ? request.read.then(() => concat(request.tables))
+              : result
+          );
         }
       } else {
-        console.log('WebSocket message: ', data);
+        // binary messages follow an arrow or batch header message
+        // untagged binary messages have no header message
+        const id = binary ? binary.id : responseId();
+        const request = pending.get(id);
+        if (!request) {
+          console.warn('Unexpected WebSocket binary message');
+        } else if (binary?.batch && request.tables) {
+          // read batches in order of arrival
+          request.onBytes?.(data.size);
+          const table = tableFromIPC(data.arrayBuffer());
+          request.read = request.read.then(async () => {
+            const batch = await table;
+            request.tables.push(batch);
+            request.onBatch(batch);
+          });
+        } else {
+          pending.delete(id);
+          request.onBytes?.(data.size);
+          request.resolve(tableFromIPC(data.arrayBuffer()));
+        }
+        binary = null;
       }
     }
   }
 
   function init() {
+    if (state === 'closed') setState('connecting');
     ws = new WebSocket(uri);
     for (const type in events) {
       ws.addEventListener(type, events[type]);
     }
   }
 
-  function enqueue(query, resolve, reject) {
+  function enqueue(query, { onBatch, onBytes }, resolve, reject) {
     if (ws == null) init();
-    queue.push({ query, resolve, reject });
-    if (connected && !request) next();
+    if (query.id == null) query = { ...query, id: requestId() };
+    if (onBatch && query.type === 'arrow') {
+      // request record batches as they are produced
+      query = { ...query, stream: true };
+      const read = Promise.resolve();
+      queue.push({ query, resolve, reject, onBytes, onBatch, tables: [], read });
+    } else {
+      queue.push({ query, resolve, reject, onBytes });
+    }
+    if (state === 'open') next();
   }
 
   function next() {
-    if (queue.length) {
-      request = queue.shift();
-      ws.send(JSON.stringify(request.query));
+    // send all queued requests, multiple requests may be in flight
+    // responses are matched to requests by id and may arrive in any order
+    while (queue.length) {
+      const request = queue.shift();
+      pending.set(request.query.id, request);
+      send(request.query);
+    }
+  }
+
+  function send(message) {
+    ws.send(JSON.stringify(message));
+  }
+
+  function updateSubscriptions(type, tables) {
+    tables.forEach(table => type === 'subscribe'
+      ? subscriptions.add(table)
+      : subscriptions.delete(table));
+    if (ws == null) init();
+    if (state === 'open') send({ type, tables });
+  }
+
+  function cancel(id) {
+    const index = queue.findIndex(({ query }) => query.id === id);
+    if (index >= 0) {
+      // not yet sent, simply drop the request
+      queue.splice(index, 1)[0].reject('Canceled');
+    } else if (pending.has(id)) {
+      // ask the server to interrupt the query
+      // the server still responds to the canceled request
+      send({ type: 'cancel', id });
     }
   }
 
   return {
+    /**
+     * The server URI, identifying the database queried by this connector.
+     */
+    id: uri,
     get connected() {
-      return connected;
+      return state === 'open';
+    },
+    /**
+     * The connection state, one of `'connecting'`, `'open'`,
+     * `'reconnecting'`, or `'closed'`.
+     */
+    get state() {
+      return state;
+    },
+    /**
+     * Add a listener for connection state changes (`'state'` events) or
+     * table modification notifications (`'notify'` events). Notification
+     * listeners receive an object with the modified `table` name and a
+     * `rowid` value. If non-null, the table was only appended to and the
+     * appended rows have row ids greater than `rowid`.
+     * @param {'state' | 'notify'} type The event type.
+     * @param {(value: any) => void} callback The listener callback.
+     */
+    addEventListener(type, callback) {
+      listeners[type]?.add(callback);
+    },
+    /**
+     * Remove a listener for connection state changes or table
+     * modification notifications.
+     * @param {'state' | 'notify'} type The event type.
+     * @param {(value: any) => void} callback The listener callback.
+     */
+    removeEventListener(type, callback) {
+      listeners[type]?.delete(callback);
     },
+    /**
+     * Subscribe to notifications of modifications to the given tables.
+     * Subscriptions are renewed upon reconnection.
+     * @param {string[]} tables The table names.
+     */
+    subscribe(tables) {
+      updateSubscriptions('subscribe', tables);
+    },
+    /**
+     * Unsubscribe from notifications of modifications to the given tables.
+     * @param {string[]} tables The table names.
+     */
+    unsubscribe(tables) {
+      updateSubscriptions('unsubscribe', tables);
+    },
+    /**
+     * Cancel a query request. If the query has already been sent, the
+     * server is asked to interrupt it.
+     * @param {string} id The id of the query request to cancel.
+     */
+    cancel,
     /**
      * Query the DuckDB server.
      * @param {object} query
      * @param {'exec' | 'arrow' | 'json'} [query.type] The query type: 'exec', 'arrow', or 'json'.
      * @param {string} query.sql A SQL query string.
+     * @param {string} [query.id] A request id, used to match responses and
+     *  for cancellation. If unspecified, a new id is generated.
+     * @param {object} [options] Query options.
+     * @param {(batch: import('apache-arrow').Table) => void} [options.onBatch]
+     *  Callback invoked with each Arrow record batch as it arrives. If
+     *  specified, the server is asked to stream the result in batches.
+     *  Only applies to 'arrow' queries.
+     * @param {(bytes: number) => void} [options.onBytes] Callback invoked
+     *  with the byte length of each received Arrow IPC message.
      * @returns the query result
      */
-    query(query) {
+    query(query, options = {}) {
       return new Promise(
-        (resolve, reject) => enqueue(query, resolve, reject)
+        (resolve, reject) => enqueue(query, options, resolve, reject)
       );
     }
   };
 }
+
+/**
+ * Test if a query request can be safely re-sent after reconnection.
+ * @param {*} query The query request.
+ * @returns {boolean} True if the request is idempotent.
+ */
+function isIdempotent(query) {
+  return query.type === 'arrow' || query.type === 'json';
+}
+
+/**
+ * Concatenate streamed record batch tables into a single table.
+ * @param {import('apache-arrow').Table[]} tables The tables to concatenate.
+ * @returns {import('apache-arrow').Table} The concatenated table.
+ */
+function concat([table, ...rest]) {
+  return rest.length ? table.concat(...rest) : table;
+}
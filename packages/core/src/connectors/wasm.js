@@ -1,4 +1,10 @@
 import * as duckdb from '@duckdb/duckdb-wasm';
+import { tableFromIPC } from 'apache-arrow';
+import { createdTables, sqlTables } from '../util/query-tables.js';
+import { readBatches } from '../util/read-batches.js';
+
+// statements that create connection-local (temporary) tables or views
+const TEMP = /\bCREATE\s+(?:OR\s+REPLACE\s+)?(?:TEMP|TEMPORARY)\s/i;
 
 export function wasmConnector(options = {}) {
   const { duckdb, connection, ...opts } = options;
@@ -6,6 +12,18 @@ export function wasmConnector(options = {}) {
   let con = connection;
   let loadPromise;
 
+  // a connection runs one query at a time, as a new query discards the
+  // pending result of a previous one. exec statements and queries over
+  // temporary tables, which are only visible to the connection that created
+  // them, run one at a time on the primary connection. other queries run
+  // concurrently on pooled connections.
+  let queue = Promise.resolve();
+  const idle = [];
+  const temps = new Set;
+  // ids of queries that are queued but not yet sent, and of running queries
+  const queued = new Set;
+  const running = new Map;
+
   function load() {
     if (!loadPromise) {
       // use a loading promise to avoid race conditions
@@ -39,20 +57,134 @@ export function wasmConnector(options = {}) {
     return con;
   }
 
+  /**
+   * Run a task on the primary connection, after all previous tasks on the
+   * primary connection complete.
+   * @param {(con: duckdb.AsyncDuckDBConnection) => Promise<any>} task The
+   *  task to run.
+   * @returns {Promise<any>} The task result.
+   */
+  function serial(task) {
+    const request = queue.then(() => getConnection()).then(task);
+    queue = request.catch(() => {});
+    return request;
+  }
+
+  /**
+   * Run a task on an idle pooled connection, creating a new connection if
+   * none is idle. If the DuckDB-WASM instance is not known, as is the case
+   * if only a connection is provided, the primary connection is used.
+   * @param {(con: duckdb.AsyncDuckDBConnection) => Promise<any>} task The
+   *  task to run.
+   * @returns {Promise<any>} The task result.
+   */
+  async function pooled(task) {
+    if (connection && !duckdb) return serial(task);
+    const con = idle.pop() ?? await (await getDuckDB()).connect();
+    try {
+      return await task(con);
+    } finally {
+      idle.push(con);
+    }
+  }
+
+  /**
+   * Test if a query must run on the primary connection.
+   * @param {string} type The query type.
+   * @param {string} sql A SQL query string.
+   * @returns {boolean} True if the query is an exec statement or references
+   *  a temporary table, false otherwise.
+   */
+  function isPrimary(type, sql) {
+    return type === 'exec' || (temps.size > 0 && sqlTables(sql)
+      .some(parts => temps.has(parts[parts.length - 1])));
+  }
+
+  /**
+   * Send a query to DuckDB-WASM and return the result as an Arrow table.
+   * @param {duckdb.AsyncDuckDBConnection} con The connection to query.
+   * @param {string} sql A SQL query string.
+   * @param {string} [id] A request id, used for cancellation.
+   * @param {(batch: import('apache-arrow').Table) => void} [onBatch]
+   *  Callback invoked with each Arrow record batch as it is read.
+   * @returns {Promise<import('apache-arrow').Table>} The query result.
+   */
+  async function send(con, sql, id, onBatch) {
+    if (id != null) {
+      // queries canceled while queued are no longer queued
+      if (!queued.delete(id)) throw 'Canceled';
+      running.set(id, con);
+    }
+    try {
+      const reader = await con.send(sql);
+      return await (onBatch ? readBatches(reader, onBatch) : tableFromIPC(reader));
+    } finally {
+      running.delete(id);
+    }
+  }
+
   return {
     getDuckDB,
     getConnection,
+    /**
+     * Cancel a query request. If the query is running, DuckDB-WASM is asked
+     * to interrupt the connection running it. If the query is queued, it is
+     * dropped before it is sent. Other requests are ignored.
+     * @param {string} id The id of the query request to cancel.
+     */
+    cancel: async id => {
+      if (running.has(id)) {
+        await running.get(id).cancelSent();
+      } else {
+        queued.delete(id);
+      }
+    },
+    /**
+     * Create or replace a table in the DuckDB-WASM instance with the
+     * contents of an Arrow table.
+     * @param {string} name The table name.
+     * @param {import('apache-arrow').Table} table The Arrow table.
+     * @returns {Promise<void>} A Promise that resolves once the table is
+     *  created.
+     */
+    insertArrow: async (name, table) => {
+      await serial(async con => {
+        await con.query(`DROP TABLE IF EXISTS ${name}`);
+        await con.insertArrowTable(table, { name, create: true });
+      });
+      temps.delete(name);
+    },
     /**
      * Query the DuckDB-WASM instance.
      * @param {object} query
      * @param {'exec' | 'arrow' | 'json'} [query.type] The query type: 'exec', 'arrow', or 'json'.
      * @param {string} query.sql A SQL query string.
+     * @param {string} [query.id] A request id, used for cancellation.
+     * @param {object} [options] Query options.
+     * @param {(batch: import('apache-arrow').Table) => void} [options.onBatch]
+     *  Callback invoked with each Arrow record batch as it is read.
+     *  Only applies to 'arrow' queries.
      * @returns the query result
      */
-    query: async query => {
-      const { type, sql } = query;
-      const con = await getConnection();
-      const result = await con.query(sql);
+    query: async (query, { onBatch } = {}) => {
+      const { type, sql, id } = query;
+      const stream = type === 'arrow' ? onBatch : undefined;
+      const task = con => send(con, sql, id, stream);
+      if (id != null) queued.add(id);
+      let result;
+      try {
+        result = await (isPrimary(type, sql) ? serial(task) : pooled(task));
+      } finally {
+        queued.delete(id);
+      }
+      if (type === 'exec') {
+        // track temporary tables, which are queried on the primary connection
+        const temp = TEMP.test(sql);
+        createdTables(sql).forEach(parts => {
+          const name = parts[parts.length - 1];
+          if (temp) temps.add(name); else temps.delete(name);
+        });
+      }
       return type === 'exec' ? undefined
         : type === 'arrow' ? result
         : result.toArray();
@@ -1,10 +1,27 @@
 export { MosaicClient } from './MosaicClient.js';
 export { Coordinator, coordinator } from './Coordinator.js';
-export { Selection, isSelection } from './Selection.js';
+export {
+  Selection,
+  SelectionResolver,
+  PerFieldResolver,
+  isSelection,
+  registerResolver
+} from './Selection.js';
 export { Param, isParam } from './Param.js';
+export { QueryParam, queryParam } from './QueryParam.js';
+export { History } from './History.js';
+export {
+  serializeState,
+  deserializeState,
+  restoreState,
+  encodeHash,
+  decodeHash
+} from './ParamState.js';
 export { Priority } from './QueryManager.js';
 
+export { hybridConnector } from './connectors/hybrid.js';
 export { restConnector } from './connectors/rest.js';
+export { routingConnector } from './connectors/routing.js';
 export { socketConnector } from './connectors/socket.js';
 export { wasmConnector } from './connectors/wasm.js';
 
@@ -13,7 +30,8 @@ export {
   clauseIntervals,
   clausePoint,
   clausePoints,
-  clauseMatch
+  clauseMatch,
+  clauseNegate
 } from './SelectionClause.js';
 
 export {
@@ -23,6 +41,14 @@ export {
   convertArrowColumn
 } from './util/convert-arrow.js'
 
+export {
+  isIndexTable,
+  mutatedTables,
+  sqlTables,
+  tableMutations,
+  tableName
+} from './util/query-tables.js';
+
 export { distinct } from './util/distinct.js';
 export { synchronizer } from './util/synchronizer.js';
 export { throttle } from './util/throttle.js';
@@ -1,58 +1,255 @@
-const requestIdle = typeof requestIdleCallback !== 'undefined'
-  ? requestIdleCallback
-  : setTimeout;
+import { tableFromIPC, tableToIPC } from 'apache-arrow';
+import { isArrowTable } from './convert-arrow.js';
 
 export const voidCache = () => ({
   get: () => undefined,
+  has: () => false,
   set: (key, value) => value,
-  clear: () => {}
+  invalidate: () => {},
+  clear: () => {},
+  stats: () => ({ entries: 0, bytes: 0, hits: 0, misses: 0, evictions: 0 })
 });
 
+/**
+ * Create a least recently used (LRU) cache. The cache is bounded by both
+ * the number of entries and the approximate total size of cached values in
+ * bytes. Entries are kept in access order, such that eviction of the least
+ * recently used entry takes constant time. Each entry may record the tables
+ * read by its query, such that entries can be invalidated when those tables
+ * are modified.
+ * @param {object} [options] Cache options.
+ * @param {number} [options.max=1000] The maximum number of entries.
+ * @param {number} [options.maxBytes=268435456] The maximum approximate total
+ *  size of cached values in bytes, default 256 MB.
+ * @param {number} [options.ttl=10800000] The time-to-live of an entry since
+ *  its last access in milliseconds, default 3 hours.
+ * @param {boolean | string | object} [options.persist=false] A persistent
+ *  store for cache entries, such that cached results can be reused after a
+ *  page reload. If true or a string, an IndexedDB store is used with the
+ *  default or provided database name. Otherwise, a store object with
+ *  `load`, `set`, `delete`, and `clear` methods may be provided.
+ * @param {string} [options.database] An identifier of the queried database,
+ *  such as a data server URI. Persisted entries are scoped by database, as
+ *  the same query may return different results from different databases.
+ * @param {string} [options.version] A version of the queried data.
+ *  Persisted entries of other data versions are discarded upon load.
+ */
 export function lruCache({
   max = 1000, // max entries
-  ttl = 3 * 60 * 60 * 1000 // time-to-live, default 3 hours
+  maxBytes = 256 * 1024 * 1024, // max approximate bytes, default 256 MB
+  ttl = 3 * 60 * 60 * 1000, // time-to-live, default 3 hours
+  persist = false,
+  database = null,
+  version = null
 } = {}) {
-  let cache = new Map;
+  const cache = new Map;
+  const counts = { hits: 0, misses: 0, evictions: 0 };
+  let bytes = 0;
 
-  function evict() {
-    const expire = performance.now() - ttl;
-    let lruKey = null;
-    let lruLast = Infinity;
+  // persisted keys are prefixed by the database and data version
+  // entries of other databases are kept, other versions and unscoped
+  // entries persisted by earlier releases are discarded
+  const scope = JSON.stringify([database, version]) + '\n';
+  const scoped = {
+    async load(s) {
+      const entries = [];
+      for (const [key, ...rest] of await s.load()) {
+        if (key.startsWith(scope)) {
+          entries.push([key.slice(scope.length), ...rest]);
+        } else {
+          const db = scopeDatabase(key);
+          if (db === undefined || db === database) update(s => s.delete(key));
+        }
+      }
+      return entries;
+    },
+    set: (s, key, ...rest) => s.set(scope + key, ...rest),
+    delete: (s, key) => s.delete(scope + key)
+  };
 
-    for (const [key, value] of cache) {
-      const { last } = value;
+  // apply an operation to the persistent store, ignoring failures
+  const store = persist === true || typeof persist === 'string'
+    ? indexedDBStore(persist === true ? undefined : persist)
+    : (persist || null);
+  const update = op => store && Promise.resolve()
+    .then(() => op(store))
+    .catch(() => {});
 
-      // least recently used entry seen so far
-      if (last < lruLast) {
-        lruKey = key;
-        lruLast = last;
-      }
+  function remove(key) {
+    const entry = cache.get(key);
+    if (entry) {
+      cache.delete(key);
+      bytes -= entry.bytes;
+    }
+  }
 
-      // remove if time since last access exceeds ttl
-      if (expire > last) {
-        cache.delete(key);
-      }
+  function add(key, value, last, tables) {
+    remove(key);
+    const entry = { last, value, tables, bytes: approxBytes(value) };
+
+    // skip values that exceed the byte budget on their own, rather than
+    // evicting all other entries only to then evict the value itself
+    if (entry.bytes > maxBytes) {
+      update(s => scoped.delete(s, key));
+      return;
     }
 
-    // remove lru entry
-    if (lruKey) {
-      cache.delete(lruKey);
+    cache.set(key, entry);
+    bytes += entry.bytes;
+
+    // evict least recently used entries, which come first in map order
+    while (cache.size && (cache.size > max || bytes > maxBytes)) {
+      const lruKey = cache.keys().next().value;
+      remove(lruKey);
+      update(s => scoped.delete(s, lruKey));
+      counts.evictions += 1;
     }
   }
 
+  function lookup(key) {
+    const entry = cache.get(key);
+    // remove if time since last access exceeds ttl
+    if (entry && Date.now() - entry.last > ttl) {
+      remove(key);
+      update(s => scoped.delete(s, key));
+      return undefined;
+    }
+    return entry;
+  }
+
+  // load persisted entries, without overwriting newer in-memory entries
+  update(async s => {
+    const entries = (await scoped.load(s))
+      .filter(([key, , last]) => !cache.has(key) && Date.now() - last <= ttl)
+      .sort((a, b) => a[2] - b[2]);
+    for (const [key, value, last, tables = null] of entries) {
+      add(key, value, last, tables);
+    }
+  });
+
   return {
     get(key) {
-      const entry = cache.get(key);
+      const entry = lookup(key);
       if (entry) {
-        entry.last = performance.now();
+        // move to the end of the map order as most recently used
+        cache.delete(key);
+        cache.set(key, entry);
+        entry.last = Date.now();
+        counts.hits += 1;
         return entry.value;
       }
+      counts.misses += 1;
+    },
+    has(key) {
+      return !!lookup(key);
     },
-    set(key, value) {
-      cache.set(key, { last: performance.now(), value });
-      if (cache.size > max) requestIdle(evict);
+    set(key, value, tables = null) {
+      const last = Date.now();
+      add(key, value, last, tables);
+      if (cache.has(key)) update(s => scoped.set(s, key, value, last, tables));
       return value;
     },
-    clear() { cache = new Map; }
+    invalidate(tables, { unknown = true } = {}) {
+      if (tables == null) return this.clear();
+      const names = new Set(tables);
+      for (const [key, entry] of cache) {
+        // entries with unknown tables may depend on any table
+        if (entry.tables ? entry.tables.some(t => names.has(t)) : unknown) {
+          remove(key);
+          update(s => scoped.delete(s, key));
+        }
+      }
+    },
+    clear() {
+      cache.clear();
+      bytes = 0;
+      update(s => s.clear());
+    },
+    stats() {
+      return { entries: cache.size, bytes, ...counts };
+    }
+  };
+}
+
+/**
+ * Return the database identifier of a persisted cache key.
+ * @param {string} key The persisted cache key.
+ * @returns {string | null | undefined} The database identifier, or
+ *  undefined if the key is not scoped.
+ */
+function scopeDatabase(key) {
+  try {
+    const scope = JSON.parse(key.slice(0, key.indexOf('\n')));
+    return Array.isArray(scope) ? scope[0] : undefined;
+  } catch (err) {
+    return undefined;
+  }
+}
+
+/**
+ * Return the approximate size in bytes of a cached value. For Apache Arrow
+ * tables, the size of the underlying buffers is used. Other values are
+ * approximated by the size of their JSON representation.
+ * @param {*} value The value to measure.
+ * @returns {number} The approximate size in bytes.
+ */
+export function approxBytes(value) {
+  if (value == null) {
+    return 0;
+  } else if (isArrowTable(value)) {
+    return value.batches.reduce((sum, batch) => sum + batch.data.byteLength, 0);
+  } else if (ArrayBuffer.isView(value)) {
+    return value.byteLength;
+  } else if (typeof value === 'string') {
+    return 2 * value.length;
+  }
+  try {
+    return 2 * (JSON.stringify(value)?.length ?? 0);
+  } catch (err) {
+    return 0; // unserializable value, such as one containing bigints
+  }
+}
+
+/**
+ * Create a persistent cache store backed by IndexedDB. Apache Arrow tables
+ * are stored in the Arrow IPC format, other values are stored as-is.
+ * @param {string} [name='mosaic-cache'] The IndexedDB database name.
+ * @returns A cache store object with `load`, `set`, `delete`, and `clear`
+ *  methods. Each method returns a Promise.
+ */
+export function indexedDBStore(name = 'mosaic-cache') {
+  const STORE = 'entries';
+  const db = new Promise((resolve, reject) => {
+    const req = indexedDB.open(name, 1);
+    req.onupgradeneeded = () => req.result.createObjectStore(STORE);
+    req.onsuccess = () => resolve(req.result);
+    req.onerror = () => reject(req.error);
+  });
+
+  const request = (mode, op) => db.then(db => new Promise((resolve, reject) => {
+    const req = op(db.transaction(STORE, mode).objectStore(STORE));
+    req.onsuccess = () => resolve(req.result);
+    req.onerror = () => reject(req.error);
+  }));
+
+  return {
+    async load() {
+      const records = await request('readonly', s => s.getAll());
+      return records.map(({ key, arrow, value, last, tables }) => [
+        key, arrow ? tableFromIPC(value) : value, last, tables
+      ]);
+    },
+    set(key, value, last, tables = null) {
+      const arrow = isArrowTable(value);
+      const data = arrow ? tableToIPC(value) : value;
+      const record = { key, arrow, value: data, last, tables };
+      return request('readwrite', s => s.put(record, key));
+    },
+    delete(key) {
+      return request('readwrite', s => s.delete(key));
+    },
+    clear() {
+      return request('readwrite', s => s.clear());
+    }
   };
 }
@@ -1,29 +1,96 @@
-import { Query, agg, sql } from '@uwdata/mosaic-sql';
+import { Query, agg, isQuery, sql } from '@uwdata/mosaic-sql';
 import { MosaicClient } from '../MosaicClient.js';
+import { getBaseTables } from './query-tables.js';
+
+// relative accuracy of quantile sketches
+const QUANTILE_ACCURACY = 0.01;
+
+// number of minimum hash values retained by distinct count sketches
+const DISTINCT_SKETCH_SIZE = 1024;
 
 /**
  * Determine data cube index columns for a given Mosaic client.
+ * Indexable client queries include aggregate queries over a single base
+ * table, star-schema style joins of a fact table with dimension tables,
+ * and UNION ALL set operations whose aggregate branches share a base table.
  * @param {MosaicClient} client The Mosaic client.
+ * @param {object} [options] Index column options.
+ * @param {boolean} [options.approximate=false] Flag to index exact distinct
+ *  counts using approximate sketches. If false, exact distinct counts are
+ *  not indexed.
  * @returns An object with necessary column data to generate data
  *  cube index columns, or null if the client is not indexable or
  *  the client query contains an invalid or unsupported expression.
  */
-export function indexColumns(client) {
+export function indexColumns(client, options) {
   if (!client.filterIndexable) return null;
   const q = client.query();
-  const from = getBaseTable(q);
+  const from = getBaseTables(q);
 
-  // bail if no base table or the query is not analyzable
-  if (typeof from !== 'string' || !q.select) return null;
+  // bail if no base tables or the query is not analyzable
+  if (!from?.length || !isQuery(q)) return null;
+
+  if (!q.select) {
+    // set operation, branches must share a single base table
+    return from.length === 1 ? setOperationColumns(q, from, options) : null;
+  }
 
+  const cols = aggregateColumns(q, from, options);
+  return cols && { from, ...cols };
+}
+
+/**
+ * Determine data cube index columns for a set operation query. Only
+ * UNION ALL operations are supported. Each branch must be an aggregate
+ * query that produces the same data cube columns.
+ * @param {*} query The set operation query.
+ * @param {string[]} from The base table names.
+ * @param {object} [options] Index column options.
+ * @returns An object with data cube index column data, including
+ *  per-branch auxiliary columns, or null if not indexable.
+ */
+function setOperationColumns(query, from, options) {
+  if (query.op !== 'UNION ALL') return null;
+  const branches = query.queries.map(
+    q => q.select ? aggregateColumns(q, from, options) : null
+  );
+  if (branches.some(b => !b)) return null;
+
+  // bail if branches do not generate compatible data cube columns
+  const key = ({ dims, aggr, aux }) => JSON.stringify([
+    dims, aggr.map(a => Object.entries(a).map(([k, v]) => [k, `${v}`])), Object.keys(aux)
+  ]);
+  const [{ dims, aggr, aux }] = branches;
+  const k = key(branches[0]);
+  if (branches.some(b => key(b) !== k)) return null;
+
+  const numeric = branches.flatMap(b => b.numeric);
+  return { from, dims, aggr, aux, numeric, branches: branches.map(b => b.aux) };
+}
+
+/**
+ * Determine data cube dimension, aggregate, and auxiliary columns for an
+ * aggregate select query.
+ * @param {Query} q The select query.
+ * @param {string[]} from The base table names.
+ * @param {object} [options] Index column options.
+ * @param {boolean} [options.approximate=false] Flag to index exact distinct
+ *  counts using approximate sketches.
+ * @returns An object with `dims`, `aggr`, `aux`, and `numeric` properties,
+ *  or null if the query contains an unsupported aggregate or no aggregates.
+ *  The `numeric` array lists input columns of quantile sketches, which can
+ *  only be indexed if these columns have a numeric type.
+ */
+function aggregateColumns(q, from, { approximate = false } = {}) {
   const aggr = []; // list of output aggregate columns
   const dims = []; // list of grouping dimension columns
   const aux = {};  // auxiliary columns needed by aggregates
+  const numeric = []; // input columns that must have a numeric type
 
   for (const entry of q.select()) {
-    const { as, expr: { aggregate, args } } = entry;
+    const { as, expr: { aggregate, args, isDistinct } } = entry;
     const op = aggregate?.toUpperCase?.();
-    switch (op) {
+    switch (isDistinct ? `${op} DISTINCT` : op) {
       case 'COUNT':
       case 'SUM':
         // TODO: revisit this DOUBLE cast in the future
@@ -124,6 +191,46 @@ export function indexColumns(client) {
         aggr.push({ [as]: agg`${op}("${as}")` });
         break;
 
+      // sketch-based aggregates track mergeable auxiliary sketches
+      // and produce approximate results with bounded error
+      // exact distinct counts are only sketched if approximation is enabled
+      case 'COUNT DISTINCT':
+        if (!approximate) return null;
+        // fall through
+      case 'APPROX_COUNT_DISTINCT':
+        aux[as] = null;
+        aggr.push({ [as]: distinctExpr(aux, args[0]) });
+        break;
+      case 'MEDIAN':
+        aux[as] = null;
+        numeric.push(args[0]);
+        aggr.push({ [as]: quantileExpr(aux, args[0], 0.5) });
+        break;
+      case 'QUANTILE_CONT':
+      case 'APPROX_QUANTILE':
+        if (!isProbability(args[1])) return null;
+        aux[as] = null;
+        numeric.push(args[0]);
+        aggr.push({ [as]: quantileExpr(aux, args[0], args[1]) });
+        break;
+      case 'QUANTILE':
+      case 'QUANTILE_DISC':
+        if (!isProbability(args[1])) return null;
+        aux[as] = null;
+        numeric.push(args[0]);
+        aggr.push({ [as]: quantileExpr(aux, args[0], args[1], false) });
+        break;
+
+      // value count aggregates track per-partition value histograms
+      case 'MODE':
+        aux[as] = null;
+        aggr.push({ [as]: modeExpr(aux, args[0]) });
+        break;
+      case 'ENTROPY':
+        aux[as] = null;
+        aggr.push({ [as]: entropyExpr(aux, args[0]) });
+        break;
+
       // otherwise, check if dimension
       default:
         if (!aggregate) dims.push(as);
@@ -134,7 +241,7 @@ export function indexColumns(client) {
   // bail if the query has no aggregates
   if (!aggr.length) return null;
 
-  return { from, dims, aggr, aux };
+  return { dims, aggr, aux, numeric };
 }
 
 /**
@@ -159,35 +266,8 @@ function auxName(type, ...args) {
 function sanitize(col) {
   return `${col}`
     .replaceAll('"', '')
-    .replaceAll(' ', '_');
-}
-
-/**
- * Identify a single base (source) table of a query.
- * @param {Query} query The input query.
- * @returns {string | undefined | NaN} the base table name, or
- *  `undefined` if there is no source table, or `NaN` if the
- *  query operates over multiple source tables.
- */
-function getBaseTable(query) {
-  const subq = query.subqueries;
-
-  // select query
-  if (query.select) {
-    const from = query.from();
-    // @ts-ignore
-    if (!from.length) return undefined;
-    if (subq.length === 0) return from[0].from.table;
-  }
-
-  // handle set operations / subqueries
-  const base = getBaseTable(subq[0]);
-  for (let i = 1; i < subq.length; ++i) {
-    const from = getBaseTable(subq[i]);
-    if (from === undefined) continue;
-    if (from !== base) return NaN;
-  }
-  return base;
+    .replaceAll(' ', '_')
+    .replaceAll('.', '_');
 }
 
 /**
@@ -226,13 +306,41 @@ function avgExpr(aux, as, arg) {
 
 /**
  * Generate a scalar subquery for a global average.
- * This value can be used to mean-center data.
+ * This value can be used to mean-center data. Any constant value is a
+ * valid center, so for queries over multiple tables the average is taken
+ * from the table referenced by the column. If that table is unknown, the
+ * data is not centered.
  * @param {*} x Souce data table column.
- * @param {string} from The source data table name.
+ * @param {string[]} from The source data table names.
  * @returns A scalar aggregate query
  */
 function avg(x, from) {
-  return sql`(SELECT AVG(${x}) FROM "${from}")`;
+  const table = from.length === 1 ? from[0]
+    : from.includes(x?.table) ? x.table
+    : null;
+  return table ? sql`(SELECT AVG(${x}) FROM "${table}")` : sql`0`;
+}
+
+/**
+ * Annotate an auxiliary column expression as centered on a global average.
+ * The global average changes as rows are added to the source table, so
+ * partial aggregates of centered columns only combine with other partial
+ * aggregates computed at the same time.
+ * @param {*} expr The auxiliary column expression.
+ * @returns The annotated expression.
+ */
+function centered(expr) {
+  return expr.annotate({ centered: true });
+}
+
+/**
+ * Indicate if any auxiliary columns are centered on a global average, in
+ * which case index tables can not be updated incrementally.
+ * @param {object} aux Auxiliary column definitions.
+ * @returns {boolean} True if any auxiliary column is centered.
+ */
+export function hasCenteredColumns(aux) {
+  return Object.values(aux ?? {}).some(expr => expr?.centered);
 }
 
 /**
@@ -283,7 +391,7 @@ function argminExpr(aux, as, [, y]) {
  *  sufficient statistics) to include in the data cube aggregation.
  * @param {*} x The source data table column. This may be a string,
  *  column reference, SQL expression, or other string-coercible value.
- * @param {string} from The source data table name.
+ * @param {string[]} from The source data table names.
  * @param {boolean} [correction=true] A flag for whether a Bessel
  *  correction should be applied to compute the sample variance
  *  rather than the populatation variance.
@@ -295,8 +403,8 @@ function varianceExpr(aux, x, from, correction = true) {
   const ssq = auxName('rssq', x); // residual sum of squares
   const sum = auxName('rsum', x); // residual sum
   const delta = sql`${x} - ${avg(x, from)}`;
-  aux[ssq] = agg`SUM((${delta}) ** 2)`;
-  aux[sum] = agg`SUM(${delta})`;
+  aux[ssq] = centered(agg`SUM((${delta}) ** 2)`);
+  aux[sum] = centered(agg`SUM(${delta})`);
   const adj = correction ? ` - 1` : ''; // Bessel correction
   return agg`(SUM(${ssq}) - (SUM(${sum}) ** 2 / ${n})) / (${n}${adj})`;
 }
@@ -312,7 +420,7 @@ function varianceExpr(aux, x, from, correction = true) {
  *  sufficient statistics) to include in the data cube aggregation.
  * @param {any[]} args Source data table columns. The entries may be strings,
  *  column references, SQL expressions, or other string-coercible values.
- * @param {string} from The source data table name.
+ * @param {string[]} from The source data table names.
  * @param {boolean|null} [correction=true] A flag for whether a Bessel
  *  correction should be applied to compute the sample covariance rather
  *  than the populatation covariance. If null, an expression for the
@@ -343,7 +451,7 @@ function covarianceExpr(aux, args, from, correction = true) {
  *  sufficient statistics) to include in the data cube aggregation.
  * @param {any[]} args Source data table columns. The entries may be strings,
  *  column references, SQL expressions, or other string-coercible values.
- * @param {string} from The source data table name.
+ * @param {string[]} from The source data table names.
  * @returns An aggregate expression for calculating correlation over
  *  pre-aggregated data partitions.
  */
@@ -387,14 +495,14 @@ function regrCountExpr(aux, [y, x]) {
  * @param {number} i An index indicating which argument column to sum.
  * @param {any[]} args Source data table columns. The entries may be strings,
  *  column references, SQL expressions, or other string-coercible values.
- * @param {string} from The source data table name.
+ * @param {string[]} from The source data table names.
  * @returns An aggregate expression over pre-aggregated data partitions.
  */
 function regrSumExpr(aux, i, args, from) {
   const v = args[i];
   const o = args[1 - i];
   const sum = auxName('rs', v);
-  aux[sum] = agg`SUM(${v} - ${avg(v, from)}) FILTER (${o} IS NOT NULL)`;
+  aux[sum] = centered(agg`SUM(${v} - ${avg(v, from)}) FILTER (${o} IS NOT NULL)`);
   return agg`SUM(${sum})`
 }
 
@@ -409,14 +517,14 @@ function regrSumExpr(aux, i, args, from) {
  * @param {number} i An index indicating which argument column to sum.
  * @param {any[]} args Source data table columns. The entries may be strings,
  *  column references, SQL expressions, or other string-coercible values.
- * @param {string} from The source data table name.
+ * @param {string[]} from The source data table names.
  * @returns An aggregate expression over pre-aggregated data partitions.
  */
 function regrSumSqExpr(aux, i, args, from) {
   const v = args[i];
   const u = args[1 - i];
   const ssq = auxName('rss', v);
-  aux[ssq] = agg`SUM((${v} - ${avg(v, from)}) ** 2) FILTER (${u} IS NOT NULL)`;
+  aux[ssq] = centered(agg`SUM((${v} - ${avg(v, from)}) ** 2) FILTER (${u} IS NOT NULL)`);
   return agg`SUM(${ssq})`
 }
 
@@ -430,13 +538,13 @@ function regrSumSqExpr(aux, i, args, from) {
  *  sufficient statistics) to include in the data cube aggregation.
  * @param {any[]} args Source data table columns. The entries may be strings,
  *  column references, SQL expressions, or other string-coercible values.
- * @param {string} from The source data table name.
+ * @param {string[]} from The source data table names.
  * @returns An aggregate expression over pre-aggregated data partitions.
  */
 function regrSumXYExpr(aux, args, from) {
   const [y, x] = args;
   const sxy = auxName('sxy', y, x);
-  aux[sxy] = agg`SUM((${x} - ${avg(x, from)}) * (${y} - ${avg(y, from)}))`;
+  aux[sxy] = centered(agg`SUM((${x} - ${avg(x, from)}) * (${y} - ${avg(y, from)}))`);
   return agg`SUM(${sxy})`;
 }
 
@@ -489,7 +597,7 @@ function regrAvgYExpr(aux, args) {
  * @param {number} i The index of the argument to compute the variance for.
  * @param {any[]} args Source data table columns. The entries may be strings,
  *  column references, SQL expressions, or other string-coercible values.
- * @param {string} from The source data table name.
+ * @param {string[]} from The source data table names.
  * @returns An aggregate expression for calculating variance over
  *  pre-aggregated data partitions.
  */
@@ -509,7 +617,7 @@ function regrVarExpr(aux, i, args, from) {
  *  sufficient statistics) to include in the data cube aggregation.
  * @param {any[]} args Source data table columns. The entries may be strings,
  *  column references, SQL expressions, or other string-coercible values.
- * @param {string} from The source data table name.
+ * @param {string[]} from The source data table names.
  * @returns An aggregate expression for calculating regression slopes over
  *  pre-aggregated data partitions.
  */
@@ -528,7 +636,7 @@ function regrSlopeExpr(aux, args, from) {
  *  sufficient statistics) to include in the data cube aggregation.
  * @param {any[]} args Source data table columns. The entries may be strings,
  *  column references, SQL expressions, or other string-coercible values.
- * @param {string} from The source data table name.
+ * @param {string[]} from The source data table names.
  * @returns An aggregate expression for calculating regression intercepts over
  *  pre-aggregated data partitions.
  */
@@ -538,3 +646,188 @@ function regrInterceptExpr(aux, args, from) {
   const m = regrSlopeExpr(aux, args, from);
   return agg`${ay} - (${m}) * ${ax}`;
 }
+
+/**
+ * Test if a value is a valid constant probability for quantile aggregates.
+ * @param {*} p The value to test.
+ * @returns {boolean} True if the value is a number between 0 and 1.
+ */
+function isProbability(p) {
+  return typeof p === 'number' && p >= 0 && p <= 1;
+}
+
+/**
+ * Generate an expression for approximate distinct value counts over data
+ * partitions. This method uses a k-minimum values (KMV) sketch: each
+ * partition retains the smallest distinct hash values of the input column,
+ * and merged sketches again retain the smallest hash values. Counts are
+ * exact if fewer than k distinct values exist. Otherwise, the count is
+ * estimated from the k-th smallest hash value, with a relative standard
+ * error of roughly 1 / sqrt(k). As a side effect, this method adds a
+ * column for the partition-level sketch to the input *aux* object.
+ * @param {object} aux An object for auxiliary columns (such as
+ *  sufficient statistics) to include in the data cube aggregation.
+ * @param {*} x The source data table column. This may be a string,
+ *  column reference, SQL expression, or other string-coercible value.
+ * @returns An aggregate expression for calculating distinct counts over
+ *  pre-aggregated data partitions.
+ */
+function distinctExpr(aux, x) {
+  const k = DISTINCT_SKETCH_SIZE;
+  const kmv = auxName('kmv', x);
+  aux[kmv] = agg`MIN(DISTINCT HASH(${x}), ${k}) FILTER (${x} IS NOT NULL)`;
+  const s = agg`list_sort(list_distinct(flatten(LIST(${kmv}) FILTER (${kmv} IS NOT NULL))))[1:${k}]`;
+  const est = agg`(${k} - 1) / (${s}[${k}] / 2 ** 64)`;
+  return agg`COALESCE(CASE WHEN len(${s}) < ${k} THEN len(${s}) ELSE ROUND(${est}) END, 0)::DOUBLE`;
+}
+
+/**
+ * Generate an expression for approximate quantiles over data partitions.
+ * This method uses a log-scale histogram sketch (as in DDSketch): values
+ * are mapped to logarithmically sized buckets such that a bucket's
+ * representative value is within a fixed relative error of every value
+ * in the bucket. Partition-level histograms are merged by concatenating
+ * their entries, and the quantile is then found by a cumulative count over
+ * the sorted buckets. Continuous quantiles interpolate between the values
+ * at the nearest ranks. As a side effect, this method adds columns for the
+ * partition-level count and sketch to the input *aux* object.
+ * @param {object} aux An object for auxiliary columns (such as
+ *  sufficient statistics) to include in the data cube aggregation.
+ * @param {*} x The source data table column. This may be a string,
+ *  column reference, SQL expression, or other string-coercible value.
+ * @param {number} p The quantile probability, between 0 and 1.
+ * @param {boolean} [continuous=true] A flag for whether the quantile
+ *  value should be interpolated between the nearest ranks. If false,
+ *  the value at the lower rank is returned.
+ * @returns An aggregate expression for calculating quantiles over
+ *  pre-aggregated data partitions.
+ */
+function quantileExpr(aux, x, p, continuous = true) {
+  const n = countExpr(aux, x);
+  const h = auxName('qsketch', x);
+  aux[h] = agg`HISTOGRAM(${sketchBucket(x)}) FILTER (${x} IS NOT NULL)`;
+  const entries = agg`list_transform(${histogramEntries(h)}, e -> {'k': e.key, 'c': e.value::DOUBLE})`;
+  const rank = agg`${p} * (${n} - 1)`;
+  const lo = rankValue(entries, agg`FLOOR(${rank})`);
+  if (!continuous) return lo;
+  const hi = rankValue(entries, agg`CEIL(${rank})`);
+  return agg`${lo} + (${rank} - FLOOR(${rank})) * (${hi} - ${lo})`;
+}
+
+/**
+ * Generate an expression that maps values to quantile sketch buckets.
+ * Non-zero values map to a bucket with index i = ceil(log_g(|x|)) for
+ * g = (1 + a) / (1 - a), where a is the relative accuracy. The bucket
+ * representative 2 g^i / (g + 1) is within relative error a of all
+ * values in the bucket, and preserves the sort order of values.
+ * @param {*} x The source data table column, which must have a numeric
+ *  type. This may be a string, column reference, SQL expression, or other
+ *  string-coercible value.
+ * @returns A SQL expression for the bucket representative value.
+ */
+function sketchBucket(x) {
+  const a = QUANTILE_ACCURACY;
+  const g = (1 + a) / (1 - a);
+  const v = sql`${x}::DOUBLE`;
+  const i = sql`CEIL(LN(ABS(${v})) / ${Math.log(g)})`;
+  return sql`CASE WHEN ${v} = 0 THEN 0 ELSE SIGN(${v}) * ${2 / (g + 1)} * ${g} ** ${i} END`;
+}
+
+/**
+ * Generate an expression for the value at a given rank, given a sorted
+ * list of weighted entries. The entries are scanned in sorted order to
+ * find the first entry whose cumulative count exceeds the rank.
+ * @param {*} entries A sorted list expression of {k, c} structs,
+ *  containing values (k) and counts (c).
+ * @param {*} rank The zero-based rank expression.
+ * @returns An aggregate expression for the value at the given rank,
+ *  or null if the rank exceeds the total count.
+ */
+function rankValue(entries, rank) {
+  const start = agg`{'k': NULL::DOUBLE, 'c': -(${rank})}`;
+  const step = 'CASE WHEN a.c > 0 THEN a ELSE {\'k\': e.k, \'c\': a.c + e.c} END';
+  return agg`struct_extract(list_reduce(list_prepend(${start}, ${entries}), (a, e) -> ${step}), 'k')`;
+}
+
+/**
+ * Generate an expression for the most frequent value over data partitions.
+ * Partition-level value histograms are merged by concatenating their
+ * entries, sorted such that entries for the same value are adjacent, and
+ * then scanned to find the value with the largest total count. As a side
+ * effect, this method adds a column for the partition-level histogram to
+ * the input *aux* object.
+ * @param {object} aux An object for auxiliary columns (such as
+ *  sufficient statistics) to include in the data cube aggregation.
+ * @param {*} x The source data table column. This may be a string,
+ *  column reference, SQL expression, or other string-coercible value.
+ * @returns An aggregate expression for calculating the mode over
+ *  pre-aggregated data partitions.
+ */
+function modeExpr(aux, x) {
+  const h = valueHistogram(aux, x);
+  const entries = agg`list_transform(${histogramEntries(h)}, e -> {'k': e.key, 'c': e.value::DOUBLE, 'm': e.key, 'mc': e.value::DOUBLE})`;
+  const step = [
+    'CASE WHEN a.k = e.k',
+    'THEN {\'k\': a.k, \'c\': a.c + e.c, \'m\': CASE WHEN a.c + e.c > a.mc THEN a.k ELSE a.m END, \'mc\': GREATEST(a.c + e.c, a.mc)}',
+    'ELSE {\'k\': e.k, \'c\': e.c, \'m\': CASE WHEN e.c > a.mc THEN e.k ELSE a.m END, \'mc\': GREATEST(e.c, a.mc)}',
+    'END'
+  ].join(' ');
+  return agg`struct_extract(list_reduce(${entries}, (a, e) -> ${step}), 'm')`;
+}
+
+/**
+ * Generate an expression for the (log-2) entropy of values over data
+ * partitions. Partition-level value histograms are merged by concatenating
+ * their entries, sorted such that entries for the same value are adjacent.
+ * A scan over the entries then sums c * log2(c) over the total count c of
+ * each distinct value, from which the entropy log2(n) - sum / n follows.
+ * As a side effect, this method adds columns for the partition-level count
+ * and histogram to the input *aux* object.
+ * @param {object} aux An object for auxiliary columns (such as
+ *  sufficient statistics) to include in the data cube aggregation.
+ * @param {*} x The source data table column. This may be a string,
+ *  column reference, SQL expression, or other string-coercible value.
+ * @returns An aggregate expression for calculating entropy over
+ *  pre-aggregated data partitions.
+ */
+function entropyExpr(aux, x) {
+  const n = countExpr(aux, x);
+  const h = valueHistogram(aux, x);
+  const entries = agg`list_transform(${histogramEntries(h)}, e -> {'k': HASH(e.key), 'c': e.value::DOUBLE, 's': 0::DOUBLE})`;
+  // terminal entry to flush the count of the last distinct value
+  const end = `{'k': NULL::UBIGINT, 'c': 0::DOUBLE, 's': 0::DOUBLE}`;
+  const step = [
+    'CASE WHEN a.k = e.k',
+    'THEN {\'k\': a.k, \'c\': a.c + e.c, \'s\': a.s}',
+    'ELSE {\'k\': e.k, \'c\': e.c, \'s\': a.s + a.c * LOG2(a.c)}',
+    'END'
+  ].join(' ');
+  const s = agg`struct_extract(list_reduce(list_append(${entries}, ${end}), (a, e) -> ${step}), 's')`;
+  return agg`LOG2(NULLIF(${n}, 0)) - ${s} / ${n}`;
+}
+
+/**
+ * Add an auxiliary column for a partition-level histogram of values,
+ * mapping each distinct non-null value to its count.
+ * @param {object} aux An object for auxiliary columns (such as
+ *  sufficient statistics) to include in the data cube aggregation.
+ * @param {*} x The source data table column. This may be a string,
+ *  column reference, SQL expression, or other string-coercible value.
+ * @returns {string} The auxiliary histogram column name.
+ */
+function valueHistogram(aux, x) {
+  const h = auxName('hist', x);
+  aux[h] = agg`HISTOGRAM(${x}) FILTER (${x} IS NOT NULL)`;
+  return h;
+}
+
+/**
+ * Generate an expression that merges partition-level histograms into a
+ * single list of {key, value} entries, sorted by key. Entries for the
+ * same key from different partitions are adjacent, but not combined.
+ * @param {string} h The auxiliary histogram column name.
+ * @returns An aggregate expression for the sorted histogram entries.
+ */
+function histogramEntries(h) {
+  return agg`list_sort(flatten(LIST(map_entries(${h})) FILTER (${h} IS NOT NULL)))`;
+}
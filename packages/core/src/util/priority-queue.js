@@ -66,11 +66,16 @@ export function priorityQueue(ranks) {
 
 		/**
 		 * Remove and return the next highest priority item.
+		 * @param {(rank: number) => boolean} [test] An optional predicate
+		 *  function to test if items of a given priority rank may be dequeued.
+		 *  If unspecified, all priority ranks are eligible.
 		 * @returns {*} The next item in the queue,
 		 *  or undefined if this queue is empty.
 		 */
-		next() {
-			for (const list of queue) {
+		next(test) {
+			for (let rank = 0; rank < ranks; ++rank) {
+				if (test && !test(rank)) continue;
+				const list = queue[rank];
 				const { head } = list;
 				if (head !== null) {
 					list.head = head.next;
@@ -0,0 +1,76 @@
+import { isQuery, tableName } from '@uwdata/mosaic-sql';
+
+export {
+  createdTables,
+  isIndexTable,
+  mutatedTables,
+  sqlTables,
+  tableMutations,
+  tableName,
+  tableParts
+} from '@uwdata/mosaic-sql';
+
+/**
+ * Identify the base (source) tables of a query, including the base tables
+ * of any subqueries, set operation branches, or common table expressions.
+ * @param {import('@uwdata/mosaic-sql').Query} query The input query.
+ * @returns {string[] | null} The base table names, or null if the
+ *  query includes a relation (such as a SQL expression) that is not
+ *  a table reference.
+ */
+export function getBaseTables(query) {
+  const tables = new Set;
+  const visit = q => {
+    if (q.select) {
+      const ctes = (q.cteFor?.query || q.query).with ?? [];
+      const names = new Set(ctes.map(({ as }) => as));
+      for (const { from } of q.from()) {
+        if (isQuery(from) || names.has(from.table)) continue;
+        if (typeof from.table !== 'string') return false;
+        tables.add(from.table);
+      }
+    }
+    return q.subqueries.every(visit);
+  };
+  return visit(query) ? Array.from(tables) : null;
+}
+
+// tables of client queries, cached per client as selections look up
+// client tables upon every predicate evaluation
+const clientTables = new WeakMap;
+
+/**
+ * Determine the table queried by a client or other selection clause
+ * source component. Components with a string-valued `from` property, such
+ * as inputs, query that table. Otherwise the base table of the unfiltered
+ * client query is used, which is cached per client. Components with a
+ * `mark` property, such as plot interactors, query the table of their mark.
+ * @param {*} client The client or source component.
+ * @returns {string | null} The normalized table name, or null if a single
+ *  table can not be determined.
+ */
+export function clientTable(client) {
+  if (!client) return null;
+  if (typeof client.from === 'string') return tableName(client.from);
+  if (typeof client.query === 'function') {
+    if (!clientTables.has(client)) {
+      const query = client.query([]);
+      const tables = isQuery(query) ? getBaseTables(query) : null;
+      clientTables.set(client, tables?.length === 1 ? tableName(tables[0]) : null);
+    }
+    return clientTables.get(client);
+  }
+  return client.mark ? clientTable(client.mark) : null;
+}
+
+/**
+ * Determine the normalized names of the tables read by a query, used to
+ * track the dependencies of cached query results.
+ * @param {*} query The query, either a Query instance or a SQL string.
+ * @returns {string[] | null} The normalized table names, or null if the
+ *  tables can not be determined, as is the case for SQL strings.
+ */
+export function queryTables(query) {
+  const tables = isQuery(query) ? getBaseTables(query) : null;
+  return tables && tables.map(tableName);
+}
@@ -0,0 +1,20 @@
+import { Table } from 'apache-arrow';
+
+/**
+ * Read Apache Arrow record batches as they arrive, invoking a callback
+ * with each batch and returning the complete result table.
+ * @param {AsyncIterable<import('apache-arrow').RecordBatch> & {
+ *  schema: import('apache-arrow').Schema }} reader An Arrow record batch
+ *  reader, such as an `AsyncRecordBatchStreamReader`.
+ * @param {(batch: Table) => void} onBatch Callback invoked with each record
+ *  batch, wrapped in a table.
+ * @returns {Promise<Table>} The complete result table.
+ */
+export async function readBatches(reader, onBatch) {
+  const batches = [];
+  for await (const batch of reader) {
+    batches.push(batch);
+    onBatch(new Table(batch));
+  }
+  return new Table(reader.schema, batches);
+}
@@ -0,0 +1,12 @@
+// request ids are prefixed to distinguish requests from different pages
+// that may share a database server
+const PREFIX = Math.random().toString(36).slice(2, 10);
+let count = 0;
+
+/**
+ * Generate a new unique request id.
+ * @returns {string} The request id.
+ */
+export function requestId() {
+  return `${PREFIX}-${++count}`;
+}
@@ -11,6 +11,11 @@ export interface ClauseMetadata {
    * The selection type, such as `'point'`, `'interval'`, or `'match'`.
    */
   type: string;
+  /**
+   * A flag indicating a negated clause, which selects all records that do
+   * not match the clause predicate.
+   */
+  negate?: boolean;
 }
 
 /**
@@ -117,6 +122,16 @@ export interface SelectionClause {
    * be updated when this clause is applied in a cross-filtering context.
    */
   clients?: Set<MosaicClient>;
+  /**
+   * The table column or expression selected by this clause, for clauses
+   * over a single field. Used to serialize and restore selection state.
+   */
+  field?: SQLExpression | string;
+  /**
+   * The table columns or expressions selected by this clause, for clauses
+   * over multiple fields. Used to serialize and restore selection state.
+   */
+  fields?: (SQLExpression | string)[];
   /**
    * A selected value associated with this clause. For example, for a 1D
    * interval selection clause the value may be a [lo, hi] array.
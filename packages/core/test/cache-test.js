@@ -0,0 +1,138 @@
+import assert from 'node:assert';
+import { lruCache } from '../src/util/cache.js';
+
+describe('lruCache', () => {
+  it('evicts least recently used entries', () => {
+    const cache = lruCache({ max: 2 });
+    cache.set('a', 1);
+    cache.set('b', 2);
+    assert.strictEqual(cache.get('a'), 1);
+    cache.set('c', 3);
+    assert.strictEqual(cache.get('b'), undefined);
+    assert.strictEqual(cache.get('a'), 1);
+    assert.strictEqual(cache.get('c'), 3);
+  });
+
+  it('evicts entries to stay within a byte budget', () => {
+    const cache = lruCache({ maxBytes: 10 });
+    cache.set('a', 'aa');
+    cache.set('b', 'bb');
+    assert.strictEqual(cache.stats().bytes, 8);
+    cache.set('c', 'cc');
+    assert.strictEqual(cache.has('a'), false);
+    assert.deepStrictEqual(
+      cache.stats(),
+      { entries: 2, bytes: 8, hits: 0, misses: 0, evictions: 1 }
+    );
+  });
+
+  it('does not cache values larger than the byte budget', () => {
+    const cache = lruCache({ maxBytes: 10 });
+    cache.set('a', 'aa');
+    cache.set('b', 'bb');
+    assert.strictEqual(cache.set('c', 'cccccccccccc'), 'cccccccccccc');
+    assert.strictEqual(cache.has('c'), false);
+    assert.strictEqual(cache.get('a'), 'aa');
+    assert.strictEqual(cache.get('b'), 'bb');
+    assert.deepStrictEqual(
+      cache.stats(),
+      { entries: 2, bytes: 8, hits: 2, misses: 0, evictions: 0 }
+    );
+  });
+
+  it('tracks hits and misses', () => {
+    const cache = lruCache();
+    cache.set('a', 1);
+    cache.get('a');
+    cache.get('a');
+    cache.get('b');
+    assert.strictEqual(cache.has('b'), false);
+    const { hits, misses } = cache.stats();
+    assert.deepStrictEqual([hits, misses], [2, 1]);
+  });
+
+  it('invalidates entries that read modified tables', () => {
+    const cache = lruCache();
+    cache.set('a', 1, ['t']);
+    cache.set('b', 2, ['u', 'v']);
+    cache.set('c', 3, null);
+    cache.invalidate(['v'], { unknown: false });
+    assert.strictEqual(cache.has('b'), false);
+    assert.strictEqual(cache.has('c'), true);
+    cache.invalidate(['v']);
+    assert.strictEqual(cache.has('a'), true);
+    assert.strictEqual(cache.has('b'), false);
+    assert.strictEqual(cache.has('c'), false);
+    cache.invalidate(null);
+    assert.strictEqual(cache.stats().entries, 0);
+  });
+
+  it('expires entries after the time-to-live', async () => {
+    const cache = lruCache({ ttl: 5 });
+    cache.set('a', 1);
+    await new Promise(resolve => setTimeout(resolve, 10));
+    assert.strictEqual(cache.get('a'), undefined);
+    assert.strictEqual(cache.stats().entries, 0);
+  });
+
+  it('loads and updates persisted entries', async () => {
+    // persisted keys are prefixed by the (unspecified) database and version
+    const scope = '[null,null]\n';
+    const data = new Map([[scope + 'a', [scope + 'a', 1, Date.now()]]]);
+    const store = {
+      load: async () => Array.from(data.values()),
+      set: async (key, value, last) => data.set(key, [key, value, last]),
+      delete: async key => data.delete(key),
+      clear: async () => data.clear()
+    };
+    const cache = lruCache({ max: 2, persist: store });
+    await new Promise(resolve => setTimeout(resolve, 0));
+    assert.strictEqual(cache.get('a'), 1);
+
+    cache.set('b', 2);
+    cache.set('c', 3);
+    await new Promise(resolve => setTimeout(resolve, 0));
+    assert.deepStrictEqual(Array.from(data.keys()), [scope + 'b', scope + 'c']);
+
+    cache.clear();
+    await new Promise(resolve => setTimeout(resolve, 0));
+    assert.strictEqual(data.size, 0);
+  });
+
+  it('scopes persisted entries by database and version', async () => {
+    const last = Date.now();
+    const key = (database, version) =>
+      JSON.stringify([database, version]) + '\nSELECT 1';
+    const data = new Map([
+      [key('db1', 'v1'), 1],
+      [key('db1', 'v0'), 0],
+      [key('db2', 'v1'), 2],
+      ['SELECT 1', -1]
+    ]);
+    const store = {
+      load: async () => Array.from(data, ([key, value]) => [key, value, last]),
+      set: async (key, value) => data.set(key, value),
+      delete: async key => data.delete(key),
+      clear: async () => data.clear()
+    };
+    const tick = () => new Promise(resolve => setTimeout(resolve, 0));
+
+    // other versions and unscoped entries are discarded
+    const cache = lruCache({ persist: store, database: 'db1', version: 'v1' });
+    await tick();
+    assert.strictEqual(cache.get('SELECT 1'), 1);
+    assert.deepStrictEqual(
+      Array.from(data.keys()),
+      [key('db1', 'v1'), key('db2', 'v1')]
+    );
+
+    // entries of other databases are neither loaded nor discarded
+    const other = lruCache({ persist: store, database: 'db3', version: 'v1' });
+    await tick();
+    assert.strictEqual(other.get('SELECT 1'), undefined);
+    other.set('SELECT 1', 3);
+    await tick();
+    assert.strictEqual(data.get(key('db3', 'v1')), 3);
+    assert.strictEqual(data.size, 3);
+  });
+});
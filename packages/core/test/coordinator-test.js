@@ -1,5 +1,17 @@
 import assert from 'node:assert';
-import { Coordinator, coordinator } from '../src/index.js';
+import { Query, count, median, variance } from '@uwdata/mosaic-sql';
+import { Coordinator, Selection, clausePoint, coordinator } from '../src/index.js';
+import { TestClient } from './util/test-client.js';
+
+class ProgressiveClient extends TestClient {
+  get progressive() {
+    return true;
+  }
+}
+
+function sqlConnector() {
+  return { query: async ({ sql }) => sql };
+}
 
 describe('coordinator', () => {
   it('has accessible singleton', () => {
@@ -11,4 +23,217 @@ describe('coordinator', () => {
 
     assert.strictEqual(mc2, coordinator());
   });
+
+  it('supports progressive updates', async () => {
+    const mc = new Coordinator(sqlConnector(), {
+      logger: null, cache: false, consolidate: false, progressive: true
+    });
+    const results = [];
+    const client = new ProgressiveClient(Query.from('t').select({ n: count() }), null, {
+      queryResult(data, info) {
+        results.push([data, !!info?.approximate]);
+        return this;
+      }
+    });
+    await mc.updateClient(client, client.query());
+    assert.deepStrictEqual(results, [
+      ['SELECT COUNT(*)::INTEGER AS "n" FROM "t" USING SAMPLE 1 PERCENT (reservoir)', true],
+      ['SELECT COUNT(*)::INTEGER AS "n" FROM "t"', false]
+    ]);
+  });
+
+  it('does not cache sampled progressive queries', async () => {
+    const mc = new Coordinator(sqlConnector(), {
+      logger: null, consolidate: false, progressive: true
+    });
+    const client = new ProgressiveClient(Query.from('t').select({ n: count() }));
+    await mc.updateClient(client, client.query());
+    assert.strictEqual(mc.cacheStats().entries, 1);
+  });
+
+  it('skips progressive updates for unsupported clients', async () => {
+    const mc = new Coordinator(sqlConnector(), {
+      logger: null, cache: false, consolidate: false, progressive: true
+    });
+    const results = [];
+    const client = new TestClient(Query.from('t').select({ n: count() }), null, {
+      queryResult(data, info) {
+        results.push([data, !!info?.approximate]);
+        return this;
+      }
+    });
+    await mc.updateClient(client, client.query());
+    assert.deepStrictEqual(results, [['SELECT COUNT(*)::INTEGER AS "n" FROM "t"', false]]);
+  });
+
+  it('prebuilds data cube indexes when idle', async () => {
+    const queries = [];
+    const db = {
+      query: async ({ type, sql }) => {
+        queries.push(sql);
+        return type === 'json' ? [{ rows: 3 }] : sql;
+      }
+    };
+    const mc = new Coordinator(db, {
+      logger: null, cache: false, consolidate: false, prebuild: { delay: 0 }
+    });
+    const sel = Selection.crossfilter();
+    sel.register(clausePoint('y', 0, { source: {} }));
+    const query = Query.from('t').select({ x: 'x', n: count() }).groupby('x');
+    await mc.connect(new TestClient(query, sel));
+    await new Promise(resolve => setTimeout(resolve, 20));
+
+    const create = queries.filter(q => q.startsWith('CREATE TEMP TABLE'));
+    assert.strictEqual(create.length, 1);
+    assert.ok(create[0].endsWith('GROUP BY "x", "y"'));
+    assert.deepStrictEqual(Array.from(mc.dataCubeIndexer.prebuilt.values()), [3]);
+  });
+
+  it('drops data cube indexes over modified tables', async () => {
+    const queries = [];
+    const db = {
+      query: async ({ type, sql }) => {
+        queries.push(sql);
+        return type === 'json' ? [{ rows: 3 }] : sql;
+      }
+    };
+    const mc = new Coordinator(db, {
+      logger: null, cache: false, consolidate: false, prebuild: { delay: 0 }
+    });
+    const sel = Selection.crossfilter();
+    sel.register(clausePoint('y', 0, { source: {} }));
+    const query = Query.from('t').select({ x: 'x', n: count() }).groupby('x');
+    await mc.connect(new TestClient(query, sel));
+    await new Promise(resolve => setTimeout(resolve, 20));
+    const [table] = mc.dataCubeIndexer.prebuilt.keys();
+
+    await mc.exec('INSERT INTO u VALUES (1)');
+    assert.strictEqual(mc.dataCubeIndexer.prebuilt.size, 1);
+
+    await mc.exec('INSERT INTO t VALUES (1, 2)');
+    await new Promise(resolve => setTimeout(resolve, 0));
+    assert.ok(queries.includes(`DROP TABLE IF EXISTS ${table}`));
+    assert.strictEqual(mc.dataCubeIndexer.sources.has(table), false);
+  });
+
+  it('updates clients and indexes of live tables', async () => {
+    const queries = [];
+    const listeners = new Set;
+    const subscribed = [];
+    const db = {
+      query: async ({ type, sql }) => {
+        queries.push(sql);
+        return type === 'json' ? [{ rows: 3 }] : sql;
+      },
+      addEventListener: (type, f) => type === 'notify' && listeners.add(f),
+      removeEventListener: () => {},
+      subscribe: tables => subscribed.push(...tables)
+    };
+    const notify = message => listeners.forEach(f => f(message));
+    const mc = new Coordinator(db, {
+      logger: null, cache: false, consolidate: false, prebuild: { delay: 0 }
+    });
+    mc.subscribe('T');
+    assert.deepStrictEqual(subscribed, ['t']);
+
+    const sel = Selection.crossfilter();
+    sel.register(clausePoint('y', 0, { source: {} }));
+    const query = Query.from('t').select({ x: 'x', n: count() }).groupby('x');
+    const results = { t: 0, u: 0 };
+    await mc.connect(new TestClient(query, sel, {
+      queryResult() { results.t += 1; }
+    }));
+    await mc.connect(new TestClient(Query.from('u').select('x'), null, {
+      queryResult() { results.u += 1; }
+    }));
+    await new Promise(resolve => setTimeout(resolve, 20));
+    const [table] = mc.dataCubeIndexer.prebuilt.keys();
+    assert.deepStrictEqual(results, { t: 1, u: 1 });
+
+    notify({ table: 't', rowid: 5 });
+    await new Promise(resolve => setTimeout(resolve, 20));
+    assert.deepStrictEqual(results, { t: 2, u: 1 });
+    const insert = queries.find(q => q.startsWith(`INSERT INTO ${table}`));
+    assert.ok(insert.includes('WHERE rowid > 5'));
+    assert.ok(mc.dataCubeIndexer.sources.has(table));
+
+    notify({ table: 't', rowid: null });
+    await new Promise(resolve => setTimeout(resolve, 20));
+    assert.ok(queries.includes(`DROP TABLE IF EXISTS ${table}`));
+  });
+
+  it('does not index quantiles of non-numeric columns', async () => {
+    const queries = [];
+    const db = {
+      query: async ({ type, sql }) => {
+        queries.push(sql);
+        if (sql.startsWith('DESCRIBE')) {
+          return [{ column_type: sql.includes('"d"') ? 'DATE' : 'DOUBLE' }];
+        }
+        return type === 'json' ? [] : sql;
+      }
+    };
+    const mc = new Coordinator(db, { logger: null, cache: false, consolidate: false });
+    const indexer = mc.dataCubeIndexer;
+    const sel = Selection.crossfilter();
+    const clause = clausePoint('y', 0, { source: {} });
+    const query = m => Query.from('t').select({ x: 'x', m }).groupby('x');
+    const dates = new TestClient(query(median('d')), sel);
+    const values = new TestClient(query(median('v')), sel);
+
+    // clients are not indexed until the column type check completes
+    assert.strictEqual(indexer.index(dates, sel, clause), null);
+    assert.strictEqual(indexer.index(values, sel, clause), null);
+    await new Promise(resolve => setTimeout(resolve, 20));
+    assert.strictEqual(indexer.index(dates, sel, clause), null);
+    assert.ok(indexer.index(values, sel, clause).table);
+    const creates = queries.filter(q => q.includes('cube_index_'));
+    assert.strictEqual(creates.length, 1);
+    assert.ok(creates[0].startsWith('CREATE') && creates[0].includes('"v"'));
+  });
+
+  it('drops live indexes that can not be updated incrementally', async () => {
+    const queries = [];
+    const listeners = new Set;
+    const db = {
+      query: async ({ type, sql }) => {
+        queries.push(sql);
+        if (sql.startsWith('INSERT INTO') && sql.includes('COUNT(*)')) {
+          throw new Error('Table has no rowid');
+        }
+        return type === 'json' ? [{ rows: 3 }] : sql;
+      },
+      addEventListener: (type, f) => type === 'notify' && listeners.add(f),
+      removeEventListener: () => {},
+      subscribe: () => {}
+    };
+    const notify = message => listeners.forEach(f => f(message));
+    const mc = new Coordinator(db, {
+      logger: null, cache: false, consolidate: false, prebuild: { delay: 0 }
+    });
+    mc.subscribe('t');
+
+    const sel = Selection.crossfilter();
+    sel.register(clausePoint('y', 0, { source: {} }));
+    await mc.connect(new TestClient(
+      Query.from('t').select({ x: 'x', v: variance('z') }).groupby('x'), sel
+    ));
+    await mc.connect(new TestClient(
+      Query.from('t').select({ x: 'x', n: count() }).groupby('x'), sel
+    ));
+    await new Promise(resolve => setTimeout(resolve, 20));
+    const tables = Array.from(mc.dataCubeIndexer.prebuilt.keys());
+    assert.strictEqual(tables.length, 2);
+
+    // variance aux columns are centered on the table average
+    // failed appends leave the index table incomplete
+    notify({ table: 't', rowid: 5 });
+    await new Promise(resolve => setTimeout(resolve, 20));
+    const inserts = queries.filter(q => q.startsWith('INSERT INTO cube_index'));
+    assert.strictEqual(inserts.length, 1);
+    assert.ok(!inserts[0].includes('rssq'));
+    for (const table of tables) {
+      assert.ok(queries.includes(`DROP TABLE IF EXISTS ${table}`));
+    }
+  });
 });
@@ -1,47 +1,91 @@
 import assert from 'node:assert';
 import {
-  Query, argmax, argmin, avg, corr, count, covarPop, covariance,
-  isNotDistinct, literal, loadObjects, max, min, product, regrAvgX,
-  regrAvgY, regrCount, regrIntercept, regrR2, regrSXX, regrSXY,
-  regrSYY, regrSlope, stddev, stddevPop, sum, varPop, variance
+  Query, argmax, argmin, avg, column, contains, corr, count, covarPop,
+  covariance, entropy, eq, isIn, isNotDistinct, literal, loadObjects, max, median, min, mode,
+  product, quantile, regrAvgX, regrAvgY, regrCount, regrIntercept, regrR2,
+  regrSXX, regrSXY, regrSYY, regrSlope, stddev, stddevPop, sum, varPop,
+  variance
 } from '@uwdata/mosaic-sql';
 import { Coordinator, Selection } from '../src/index.js';
 import { nodeConnector } from './util/node-connector.js';
 import { TestClient } from './util/test-client.js';
 
-async function setup(loadQuery) {
-  const mc = new Coordinator(nodeConnector(), {
+async function setup(loadQuery, indexes, connector = nodeConnector()) {
+  const mc = new Coordinator(connector, {
     logger: null,
     cache: false,
-    consolidate: false
+    consolidate: false,
+    indexes
   });
-  await mc.exec(loadQuery);
+  for (const query of [loadQuery].flat()) {
+    await mc.exec(query);
+  }
   return mc;
 }
 
-async function run(measure) {
-  const loadQuery = loadObjects('testData', [
+// connector that logs the SQL of each query request
+function logConnector(log, db = nodeConnector()) {
+  return { query: req => (log.push(req.sql), db.query(req)) };
+}
+
+// assert that a temporary index table is created and then queried
+function assertIndexed(log) {
+  const create = log.find(sql => sql.startsWith('CREATE TEMP TABLE'));
+  const table = create?.match(/cube_index_\w+/)?.[0];
+  assert.ok(table, 'creates a data cube index table');
+  assert.ok(
+    log.some(sql => sql.startsWith('SELECT') && sql.includes(`FROM "${table}"`)),
+    'queries the data cube index table'
+  );
+}
+
+class UnionClient extends TestClient {
+  query(filter = []) {
+    return Query.unionAll(this._query.queries.map(q => q.clone().where(filter)));
+  }
+}
+
+async function run(measure, indexes, meta) {
+  const q = Query.from('testData').select({ measure });
+  const rows = await runQuery(q, indexes, meta);
+  return rows[0].measure;
+}
+
+const loadQuery = [
+  loadObjects('testData', [
     { dim: 'a', x: 1, y: 9 },
     { dim: 'a', x: 2, y: 8 },
     { dim: 'b', x: 3, y: 7 },
     { dim: 'b', x: 4, y: 6 },
     { dim: 'b', x: null, y: null }
-  ]);
-  const mc = await setup(loadQuery);
+  ]),
+  loadObjects('testDims', [
+    { key: 'a', label: 'A' },
+    { key: 'b', label: 'B' }
+  ])
+];
+
+async function runQuery(
+  q, indexes, meta = undefined,
+  predicate = isNotDistinct('dim', literal('b')),
+  connector = undefined
+) {
+  const mc = await setup(loadQuery, indexes, connector);
   const sel = Selection.single({ cross: true });
-  const q = Query.from('testData').select({ measure });
+  const Client = q.select ? TestClient : UnionClient;
 
   return new Promise((resolve) => {
     let iter = 0;
-    mc.connect(new TestClient(q, sel, {
+    mc.connect(new Client(q, sel, {
       queryResult: data => iter
-        ? resolve(Array.from(data)[0].measure)
+        ? resolve(Array.from(data))
         : ++iter
     }));
     sel.update({
       source: 'test',
       schema: { type: 'point' },
-      predicate: isNotDistinct('dim', literal('b'))
+      meta,
+      predicate
     });
   });
 }
@@ -107,4 +151,171 @@ describe('DataCubeIndexer', () => {
     assert.strictEqual(await run(regrIntercept('y', 'x')), 10);
     assert.strictEqual(await run(regrR2('y', 'x')), 1);
   });
+  it('supports approximate quantile aggregates', async () => {
+    const meta = { type: 'point' };
+    const approx = async (measure, value) => {
+      const v = await run(measure, undefined, meta);
+      assert.ok(Math.abs(v - value) <= 0.01 * value, `${v} ~ ${value}`);
+    };
+    await approx(median('x'), 3.5);
+    await approx(quantile('x', 0), 3);
+    await approx(quantile('x', 1), 4);
+  });
+  it('supports distinct count aggregates', async () => {
+    const q = Query.from('testData').select({ measure: count('y').distinct() });
+    const meta = { type: 'point' };
+    const pred = isNotDistinct('dim', literal('b'));
+
+    // exact distinct counts are not indexed by default
+    const exact = [];
+    const rows1 = await runQuery(q, undefined, meta, pred, logConnector(exact));
+    assert.strictEqual(rows1[0].measure, 2);
+    assert.ok(!exact.some(sql => sql.includes('cube_index_')));
+
+    // approximate indexing uses distinct count sketches
+    const approx = [];
+    const indexes = { approximate: true };
+    const rows2 = await runQuery(q, indexes, meta, pred, logConnector(approx));
+    assert.strictEqual(rows2[0].measure, 2);
+    assertIndexed(approx);
+    assert.ok(approx.some(
+      sql => sql.startsWith('CREATE TEMP TABLE') && sql.includes('__kmv_y__')
+    ));
+  });
+  it('supports mode and entropy aggregates', async () => {
+    const meta = { type: 'point' };
+    assert.strictEqual(await run(mode('dim'), undefined, meta), 'b');
+    assert.strictEqual(await run(entropy('x'), undefined, meta), 1);
+  });
+  it('supports negated clauses over null values', async () => {
+    const q = Query.from('testData').select({ measure: count() });
+    const meta = { type: 'point', negate: true };
+    // rows with a null x value are outside the selected point
+    const [{ measure }] = await runQuery(q, undefined, meta, eq('x', literal(3)));
+    assert.strictEqual(measure, 4);
+  });
+  it('supports persistent index tables', async () => {
+    const db = nodeConnector();
+    const log = [];
+    const connector = logConnector(log, db);
+    const q = Query.from('testData').select({ measure: sum('x') });
+    const meta = { type: 'point' };
+    const indexes = { temp: false };
+    const pred = isNotDistinct('dim', literal('b'));
+    const created = () => log.filter(
+      sql => /^CREATE TABLE .*mosaic\.cube_index_(?!usage)/.test(sql)
+    );
+
+    const rows1 = await runQuery(q, indexes, meta, pred, connector);
+    assert.strictEqual(rows1[0].measure, 7);
+    assert.strictEqual(created().length, 1);
+    const tables = await db.query({
+      type: 'json',
+      sql: `SELECT table_name FROM duckdb_tables()
+        WHERE schema_name = 'mosaic' AND table_name LIKE 'cube_index_%'
+          AND table_name <> 'cube_index_usage'`
+    });
+    assert.strictEqual(Array.from(tables).length, 1);
+
+    // a second coordinator on the same database reuses the index table
+    log.length = 0;
+    const rows2 = await runQuery(q, indexes, meta, pred, connector);
+    assert.strictEqual(rows2[0].measure, 7);
+    assert.strictEqual(created().length, 0);
+    assert.ok(log.some(
+      sql => sql.includes(`FROM "mosaic"."${tables[0].table_name}"`)
+    ));
+  });
+  it('drops least recently used persistent index tables', async () => {
+    const mc = await setup('SELECT 1', { temp: false, maxTables: 1 });
+    const indexer = mc.dataCubeIndexer;
+    indexer.createSchema();
+    await mc.exec('CREATE TABLE mosaic.cube_index_a AS SELECT 1 AS x');
+    await mc.exec('CREATE TABLE mosaic.cube_index_b AS SELECT 1 AS x');
+    await indexer.track('mosaic.cube_index_a');
+    await indexer.track('mosaic.cube_index_b');
+    const tables = await mc.query(
+      'SELECT table_name FROM duckdb_tables() WHERE schema_name = \'mosaic\' ORDER BY table_name',
+      { type: 'json', cache: false }
+    );
+    assert.deepStrictEqual(
+      Array.from(tables, t => t.table_name),
+      ['cube_index_b', 'cube_index_usage']
+    );
+  });
+  it('supports star-schema joins', async () => {
+    const q = Query
+      .from('testData', 'testDims')
+      .select({ label: column('testDims', 'label'), measure: sum('x') })
+      .where(eq(column('testData', 'dim'), column('testDims', 'key')))
+      .groupby('label');
+    const log = [];
+    const pred = isNotDistinct('dim', literal('b'));
+    const rows = await runQuery(q, undefined, { type: 'point' }, pred, logConnector(log));
+    assert.deepStrictEqual(rows.map(r => [r.label, r.measure]), [['B', 7]]);
+    assertIndexed(log);
+  });
+  it('supports union queries over a shared base table', async () => {
+    const q = Query.unionAll(
+      Query.from('testData').select({ key: literal('sum'), measure: sum('x') }),
+      Query.from('testData').select({ key: literal('count'), measure: count() })
+    );
+    const log = [];
+    const pred = isNotDistinct('dim', literal('b'));
+    const rows = await runQuery(q, undefined, { type: 'point' }, pred, logConnector(log));
+    assert.deepStrictEqual(
+      rows.map(r => [r.key, r.measure]).sort(),
+      [['count', 3], ['sum', 7]]
+    );
+    assertIndexed(log);
+  });
+  it('supports multi-point clauses', async () => {
+    const q = Query
+      .from('testData')
+      .select({ dim: 'dim', measure: sum('x') })
+      .groupby('dim');
+    const log = [];
+    const pred = isIn('dim', ['a', 'b']);
+    const rows = await runQuery(q, undefined, { type: 'point' }, pred, logConnector(log));
+    assert.deepStrictEqual(
+      rows.map(r => [r.dim, r.measure]).sort(),
+      [['a', 3], ['b', 7]]
+    );
+    assertIndexed(log);
+  });
+  it('supports text match clauses', async () => {
+    const q = Query.from('testData').select({ measure: sum('x') });
+    const meta = { type: 'match', method: 'contains' };
+    const pred = contains('dim', literal('b'));
+    const rows = await runQuery(q, undefined, meta, pred);
+    assert.strictEqual(rows[0].measure, 7);
+  });
+  it('indexes text match clauses over low cardinality columns', async () => {
+    const q = Query.from('testData').select({ measure: sum('x') });
+    const clause = {
+      source: 'test',
+      meta: { type: 'match', method: 'contains' },
+      predicate: contains('dim', literal('b'))
+    };
+
+    // return the index info once the cardinality check completes
+    async function index(maxCardinality) {
+      const mc = await setup(loadQuery, { maxCardinality });
+      const indexer = mc.dataCubeIndexer;
+      const client = new TestClient(q);
+      const checkIndexable = indexer.checkIndexable;
+      let check;
+      indexer.checkIndexable = (...args) => {
+        return check = checkIndexable.apply(indexer, args);
+      };
+      assert.strictEqual(indexer.index(client, Selection.single(), clause), null);
+      await check;
+      return indexer.indexes.get(client);
+    }
+
+    // the dim column has two distinct values
+    const info = await index(2);
+    assert.ok(info?.table.startsWith('cube_index_'));
+    assert.strictEqual(await index(1), null);
+  });
 });
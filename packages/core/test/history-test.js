@@ -0,0 +1,67 @@
+import assert from 'node:assert';
+import { History, Param, Selection, clausePoint } from '../src/index.js';
+
+describe('History', () => {
+  it('supports undo and redo', async () => {
+    const param = Param.value(1);
+    const sel = Selection.intersect();
+    const history = new History({ params: { param, sel }, merge: 0 });
+    const a = {};
+    const b = {};
+
+    await param.update(2).pending('value');
+    await sel.update(clausePoint('x', 1, { source: a })).pending('value');
+    await sel.update(clausePoint('y', 2, { source: b })).pending('value');
+    assert.strictEqual(history.states.length, 4);
+    assert.ok(history.canUndo);
+    assert.ok(!history.canRedo);
+
+    assert.ok(await history.undo());
+    assert.deepStrictEqual(sel.clauses.map(c => c.source), [a]);
+    assert.ok(await history.undo());
+    assert.strictEqual(sel.clauses.length, 0);
+    assert.strictEqual(param.value, 2);
+    assert.ok(await history.undo());
+    assert.strictEqual(param.value, 1);
+    assert.ok(!await history.undo());
+
+    assert.ok(await history.redo());
+    assert.strictEqual(param.value, 2);
+    assert.ok(history.canRedo);
+    assert.strictEqual(history.states.length, 4);
+
+    // a new update discards undone states
+    await param.update(3).pending('value');
+    assert.strictEqual(history.states.length, 3);
+    assert.ok(!history.canRedo);
+  });
+
+  it('merges rapid updates', async () => {
+    const param = Param.value(0);
+    const history = new History({ params: { param }, merge: 1000 });
+    for (let i = 1; i <= 5; ++i) await param.update(i).pending('value');
+    assert.deepStrictEqual(history.states, [{ param: 0 }, { param: 5 }]);
+  });
+
+  it('restores and replays bookmarks', async () => {
+    const param = Param.value('a');
+    const history = new History({ params: { param }, merge: 0 });
+    history.bookmark('first');
+    await param.update('b').pending('value');
+    history.bookmark('second');
+    await param.update('c').pending('value');
+
+    await history.restore('first');
+    assert.strictEqual(param.value, 'a');
+    assert.strictEqual(history.states.length, 4);
+    await history.undo();
+    assert.strictEqual(param.value, 'c');
+    await assert.rejects(history.restore('unknown'));
+
+    const values = [];
+    param.addEventListener('value', value => values.push(value));
+    await history.replay(['first', 'second', { param: 'd' }], { delay: 0 });
+    assert.deepStrictEqual(values, ['a', 'b', 'd']);
+    assert.strictEqual(history.states.length, 4);
+  });
+});
@@ -0,0 +1,174 @@
+import assert from 'node:assert';
+import { Query } from '@uwdata/mosaic-sql';
+import { Coordinator, hybridConnector } from '../src/index.js';
+import { TestClient } from './util/test-client.js';
+
+function logConnector(name, log, waits = new Map) {
+  const tables = new Map;
+  const running = new Map;
+  return {
+    tables,
+    async query({ type, sql, id }) {
+      log.push(`${name}: ${sql}`);
+      // queries starting with WAIT are gated until released or canceled
+      if (sql.startsWith('WAIT')) {
+        await new Promise((resolve, reject) => {
+          waits.set(sql, resolve);
+          running.set(id, reject);
+        });
+        log.push(`${name}: done ${sql}`);
+      }
+      return type === 'arrow' ? { numRows: 2 } : [];
+    },
+    async cancel(id) {
+      log.push(`${name}: cancel ${id}`);
+      running.get(id)?.('Canceled');
+    },
+    async insertArrow(table, data) {
+      log.push(`${name}: insert ${table}`);
+      tables.set(table, data);
+    }
+  };
+}
+
+const tick = () => new Promise(resolve => setTimeout(resolve, 20));
+
+describe('hybridConnector', () => {
+  it('answers queries over pulled tables locally', async () => {
+    const log = [];
+    const local = logConnector('local', log);
+    const remote = logConnector('remote', log);
+    const db = hybridConnector({ remote, local });
+
+    await db.query({ type: 'json', sql: 'SELECT * FROM subset' });
+    const rows = await db.pull('subset', 'SELECT * FROM flights WHERE delay > 10');
+    assert.strictEqual(rows, 2);
+    assert.ok(local.tables.has('subset'));
+    assert.deepStrictEqual(db.pulledTables(), ['subset']);
+
+    await db.query({ type: 'json', sql: 'SELECT * FROM subset' });
+    await db.query({ type: 'json', sql: 'SELECT * FROM flights' });
+    await db.release('subset');
+    await db.query({ type: 'json', sql: 'SELECT * FROM subset' });
+
+    assert.deepStrictEqual(log, [
+      'remote: SELECT * FROM subset',
+      'remote: SELECT * FROM flights WHERE delay > 10',
+      'local: insert subset',
+      'local: SELECT * FROM subset',
+      'remote: SELECT * FROM flights',
+      'local: DROP TABLE IF EXISTS subset',
+      'remote: SELECT * FROM subset'
+    ]);
+  });
+
+  it('updates clients of subscribed tables when pulled', async () => {
+    const log = [];
+    const db = hybridConnector({
+      remote: logConnector('remote', log),
+      local: logConnector('local', log)
+    });
+    const mc = new Coordinator(db, { logger: null, cache: false });
+    mc.subscribe(['subset']);
+
+    await mc.connect(new TestClient(Query.from('subset').select('x')));
+    await new Promise(resolve => setTimeout(resolve, 20));
+    assert.deepStrictEqual(log.splice(0), ['remote: SELECT "x" FROM "subset"']);
+
+    await db.pull('subset', 'SELECT * FROM flights');
+    await new Promise(resolve => setTimeout(resolve, 20));
+    assert.deepStrictEqual(log, [
+      'remote: SELECT * FROM flights',
+      'local: insert subset',
+      'local: SELECT "x" FROM "subset"'
+    ]);
+  });
+
+  it('invalidates cached results and indexes when pulled', async () => {
+    const log = [];
+    const db = hybridConnector({
+      remote: logConnector('remote', log),
+      local: logConnector('local', log)
+    });
+    const mc = new Coordinator(db, { logger: null });
+    const query = Query.from('subset').select('x');
+    mc.dataCubeIndexer.sources.set('cube_index_a', { from: ['subset'], ready: true });
+
+    await mc.query(query);
+    await mc.query(query);
+    await db.pull('subset', 'SELECT * FROM flights');
+    await new Promise(resolve => setTimeout(resolve, 20));
+    await mc.query(query);
+    assert.deepStrictEqual(log, [
+      'remote: SELECT "x" FROM "subset"',
+      'remote: SELECT * FROM flights',
+      'local: insert subset',
+      'remote: DROP TABLE IF EXISTS cube_index_a',
+      'local: SELECT "x" FROM "subset"'
+    ]);
+    assert.ok(!mc.dataCubeIndexer.sources.has('cube_index_a'));
+  });
+
+  it('issues pulls through the coordinator', async () => {
+    const log = [];
+    const waits = new Map;
+    const remote = logConnector('remote', log, waits);
+    const db = hybridConnector({ remote, local: logConnector('local', log) });
+    const mc = new Coordinator(db, { logger: null });
+    const traces = [];
+    mc.addEventListener('query', trace => traces.push(trace));
+
+    const data = await mc.pull('subset', 'SELECT * FROM flights');
+    assert.strictEqual(data.numRows, 2);
+    assert.deepStrictEqual(db.pulledTables(), ['subset']);
+    assert.deepStrictEqual(
+      traces.map(({ type, sql, status }) => ({ type, sql, status })),
+      [{ type: 'arrow', sql: 'SELECT * FROM flights', status: 'done' }]
+    );
+
+    // a running pull is canceled using the remote connector
+    const request = mc.pull('other', 'WAIT SELECT * FROM flights');
+    await tick();
+    mc.cancel([request]);
+    await assert.rejects(request, err => err === 'Canceled');
+    assert.match(log.at(-1), /^remote: cancel /);
+    assert.deepStrictEqual(db.pulledTables(), ['subset']);
+  });
+
+  it('rejects pulls for connectors without pull support', () => {
+    const mc = new Coordinator(logConnector('remote', []), { logger: null });
+    assert.throws(() => mc.pull('subset', 'SELECT * FROM flights'));
+  });
+
+  it('does not pull tables during local queries over them', async () => {
+    const log = [];
+    const waits = new Map;
+    const local = logConnector('local', log, waits);
+    const db = hybridConnector({
+      remote: logConnector('remote', log),
+      local,
+      tables: ['subset']
+    });
+
+    // a pull waits for a running local query over the table
+    const read = db.query({ type: 'json', sql: 'WAIT SELECT * FROM subset' });
+    const pull = db.pull('subset', 'SELECT * FROM flights');
+    await tick();
+
+    // a local query over the table waits for the pull
+    const next = db.query({ type: 'json', sql: 'SELECT * FROM subset' });
+    await tick();
+    assert.deepStrictEqual(log.splice(0), [
+      'local: WAIT SELECT * FROM subset',
+      'remote: SELECT * FROM flights'
+    ]);
+
+    waits.get('WAIT SELECT * FROM subset')();
+    await Promise.all([read, pull, next]);
+    assert.deepStrictEqual(log, [
+      'local: done WAIT SELECT * FROM subset',
+      'local: insert subset',
+      'local: SELECT * FROM subset'
+    ]);
+  });
+});
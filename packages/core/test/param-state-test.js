@@ -0,0 +1,88 @@
+import assert from 'node:assert';
+import { sql } from '@uwdata/mosaic-sql';
+import {
+  History, Param, Selection, clauseInterval, clauseMatch, clausePoints,
+  decodeHash, encodeHash, restoreState, serializeState
+} from '../src/index.js';
+
+describe('ParamState', () => {
+  it('serializes and restores params and selections', async () => {
+    const brush = { id: 'brush' };
+    const toggle = {};
+    const search = {};
+    const scale = { type: 'utc', domain: [new Date(0), new Date(1000)], range: [0, 100] };
+    const sel = Selection.crossfilter();
+    sel.register(clausePoints(['a', 'b'], null, { source: toggle }));
+    sel.update(clauseInterval('t', [new Date(10), new Date(20)], { source: brush, scale }));
+    await sel.pending('value');
+    sel.update(clausePoints(['a', 'b'], [[1, 'x']], { source: toggle }));
+    await sel.pending('value');
+    sel.update(clauseMatch(sql`lower(${'name'})`, 'ab', { source: search }));
+    await sel.pending('value');
+    const bins = Param.value(20);
+
+    const data = JSON.parse(JSON.stringify(serializeState({ sel, bins })));
+    assert.deepStrictEqual(data.params.bins, { value: 20 });
+    assert.deepStrictEqual(
+      data.params.sel.clauses.map(c => [c.type, c.source]),
+      [['interval', 'brush'], ['points', null], ['match', null]]
+    );
+
+    // restore to new params, resolving sources by id and registration
+    const brush2 = { id: 'brush' };
+    const toggle2 = {};
+    const sel2 = Selection.crossfilter();
+    sel2.register(clausePoints(['a', 'b'], null, { source: toggle2 }));
+    const bins2 = Param.value(10);
+    await restoreState({ sel: sel2, bins: bins2 }, data, { sources: { brush: brush2 } });
+
+    assert.strictEqual(bins2.value, 20);
+    const [interval, points, match] = sel2.clauses;
+    assert.strictEqual(interval.source, brush2);
+    assert.ok(interval.clients.has(brush2));
+    assert.deepStrictEqual(interval.value, [new Date(10), new Date(20)]);
+    assert.deepStrictEqual(interval.meta.scales[0].domain, scale.domain);
+    assert.strictEqual(points.source, toggle2);
+    assert.deepStrictEqual(points.value, [[1, 'x']]);
+    assert.deepStrictEqual(match.source, { id: null });
+    assert.strictEqual(`${match.predicate}`, `${sel.clauses[2].predicate}`);
+    assert.deepStrictEqual(
+      sel2.predicate(null).map(String),
+      sel.predicate(null).map(String)
+    );
+
+    // restored states may be recorded in a history
+    const history = new History({ params: { sel: sel2 } });
+    await restoreState(history, { version: 1, params: { sel: { clauses: [] } } });
+    assert.strictEqual(sel2.clauses.length, 0);
+  });
+
+  it('notifies clause sources of restored clauses', async () => {
+    const restored = [];
+    const source = id => ({ id, restore: clause => restored.push([id, clause.value]) });
+    const a = source('a');
+    const b = source('b');
+    const sel = Selection.crossfilter();
+    sel.update(clauseInterval('x', [0, 1], { source: a }));
+    await sel.pending('value');
+    const data = serializeState({ sel });
+
+    // restoring updates the display of the source, clearing others
+    sel.update(clauseInterval('x', [2, 3], { source: a }));
+    await sel.pending('value');
+    sel.update(clauseInterval('y', [4, 5], { source: b }));
+    await sel.pending('value');
+    await restoreState({ sel }, data, { sources: [a, b] });
+    assert.deepStrictEqual(restored, [['b', null], ['a', [0, 1]]]);
+    assert.deepStrictEqual(sel.clauses.map(c => c.value), [[0, 1]]);
+  });
+
+  it('encodes state in a URL hash', () => {
+    const data = { version: 1, params: { p: { value: 'ünïcode?&=' } } };
+    const hash = encodeHash(data);
+    assert.match(hash, /^#mosaic=[\w-]+$/);
+    assert.deepStrictEqual(decodeHash(hash), data);
+    assert.deepStrictEqual(decodeHash(`#a=1&${hash.slice(1)}`), data);
+    assert.strictEqual(decodeHash('#other=1'), null);
+  });
+});
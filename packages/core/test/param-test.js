@@ -0,0 +1,78 @@
+import assert from 'node:assert';
+import { Query, max, sql } from '@uwdata/mosaic-sql';
+import {
+  Coordinator, Param, QueryParam, Selection, clausePoint, queryParam
+} from '../src/index.js';
+
+describe('Param', () => {
+  it('derives values from other params', async () => {
+    const a = Param.value(1);
+    const b = Param.value(2);
+    const sum = Param.derive((x, y, z) => x + y + z, a, b, 10);
+    assert.strictEqual(sum.value, 13);
+
+    const values = [];
+    sum.addEventListener('value', value => values.push(value));
+    await a.update(5).pending('value');
+    await sum.pending('value');
+    await b.update(3).pending('value');
+    await sum.pending('value');
+    assert.deepStrictEqual(values, [17, 18]);
+    assert.strictEqual(`${sql`${sum} + 1`}`, '18 + 1');
+
+    // disposed params are no longer updated
+    sum.dispose();
+    await a.update(0).pending('value');
+    assert.strictEqual(sum.value, 18);
+    assert.deepStrictEqual(values, [17, 18]);
+  });
+
+  it('updates query params upon selection changes', async () => {
+    const queries = [];
+    const mc = new Coordinator({
+      query: async ({ sql }) => {
+        queries.push(sql);
+        return [{ v: sql.includes('WHERE') ? 2 : 1 }];
+      }
+    }, { logger: null, cache: false, indexes: { enabled: false } });
+
+    const sel = Selection.intersect();
+    const param = queryParam(
+      Query.from('flights').select({ v: max('date') }),
+      { filterBy: sel, value: 0, coordinator: mc }
+    );
+    assert.ok(param instanceof QueryParam);
+    assert.strictEqual(param.value, 0);
+
+    const values = [];
+    param.addEventListener('value', value => values.push(value));
+    const next = () => new Promise(resolve => {
+      const listener = () => {
+        param.removeEventListener('value', listener);
+        resolve();
+      };
+      param.addEventListener('value', listener);
+    });
+
+    await next();
+    sel.update(clausePoint('carrier', 'AA', { source: {} }));
+    await next();
+    assert.deepStrictEqual(values, [1, 2]);
+    assert.deepStrictEqual(queries, [
+      'SELECT MAX("date") AS "v" FROM "flights"',
+      'SELECT MAX("date") AS "v" FROM "flights" WHERE ("carrier" IS NOT DISTINCT FROM \'AA\')'
+    ]);
+
+    // derived params accept query params as dependencies
+    const label = Param.derive(v => `max: ${v}`, param);
+    assert.strictEqual(label.value, 'max: 2');
+
+    // disposed query params are no longer updated
+    param.dispose();
+    assert.strictEqual(param.coordinator, null);
+    sel.update(clausePoint('carrier', 'UA', { source: {} }));
+    await new Promise(resolve => setTimeout(resolve, 20));
+    assert.strictEqual(queries.length, 2);
+    assert.strictEqual(param.value, 2);
+  });
+});
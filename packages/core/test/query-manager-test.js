@@ -0,0 +1,183 @@
+import assert from 'node:assert';
+import { Query } from '@uwdata/mosaic-sql';
+import { Priority } from '../src/index.js';
+import { QueryManager } from '../src/QueryManager.js';
+import { lruCache, voidCache } from '../src/util/cache.js';
+import { voidLogger } from '../src/util/void-logger.js';
+
+function delayConnector(log) {
+  let active = 0;
+  return {
+    get active() {
+      return active;
+    },
+    async query({ type, sql }) {
+      log.push(`start ${sql}`);
+      ++active;
+      await new Promise(resolve => setTimeout(resolve, 5));
+      --active;
+      log.push(`end ${sql}`);
+      return type === 'exec' ? undefined : sql;
+    }
+  };
+}
+
+function manager(db, concurrency) {
+  const qm = new QueryManager(concurrency);
+  qm.cache(voidCache());
+  qm.logger(voidLogger());
+  qm.connector(db);
+  return qm;
+}
+
+describe('QueryManager', () => {
+  it('submits concurrent requests', async () => {
+    const log = [];
+    const db = delayConnector(log);
+    const qm = manager(db, 2);
+    const results = ['a', 'b', 'c'].map(
+      query => qm.request({ type: 'json', query })
+    );
+    assert.strictEqual(db.active, 2);
+    assert.deepStrictEqual(await Promise.all(results), ['a', 'b', 'c']);
+    assert.deepStrictEqual(log.slice(0, 2), ['start a', 'start b']);
+  });
+
+  it('supports per-priority concurrency limits', async () => {
+    const log = [];
+    const db = delayConnector(log);
+    const qm = manager(db, [1, 1, 1]);
+    const results = [
+      qm.request({ type: 'json', query: 'a' }, Priority.Normal),
+      qm.request({ type: 'json', query: 'b' }, Priority.Normal),
+      qm.request({ type: 'json', query: 'c' }, Priority.High)
+    ];
+    assert.strictEqual(db.active, 2);
+    assert.deepStrictEqual(log, ['start a', 'start c']);
+    await Promise.all(results);
+  });
+
+  it('runs exec requests in isolation', async () => {
+    const log = [];
+    const db = delayConnector(log);
+    const qm = manager(db, 4);
+    await Promise.all([
+      qm.request({ type: 'json', query: 'a' }),
+      qm.request({ type: 'exec', query: 'b' }),
+      qm.request({ type: 'json', query: 'c' })
+    ]);
+    assert.deepStrictEqual(log, [
+      'start a', 'end a', 'start b', 'end b', 'start c', 'end c'
+    ]);
+  });
+
+  it('invalidates cached results of modified tables', async () => {
+    const log = [];
+    const qm = manager(delayConnector(log));
+    qm.cache(lruCache());
+    const a = Query.from('a').select('x');
+    const b = Query.from({ t: Query.from('main."B"').select('y') }).select('y');
+    const c = 'SELECT COUNT(*) FROM a';
+    const usage = 'INSERT OR REPLACE INTO mosaic.cube_index_usage VALUES (1)';
+    const request = query => qm.request({ type: 'json', query, cache: true });
+    await Promise.all([request(a), request(b), request(c)]);
+    await qm.request({ type: 'exec', query: usage });
+    await Promise.all([request(a), request(b), request(c)]);
+    await qm.request({ type: 'exec', query: 'INSERT INTO b VALUES (1)' });
+    await Promise.all([request(a), request(b), request(c)]);
+    assert.deepStrictEqual(
+      log.filter(entry => entry.startsWith('start')),
+      [
+        `start ${a}`, `start ${b}`, `start ${c}`,
+        `start ${usage}`,
+        'start INSERT INTO b VALUES (1)',
+        `start ${b}`, `start ${c}`
+      ]
+    );
+  });
+
+  it('traces query requests', async () => {
+    const qm = manager(delayConnector([]), 1);
+    qm.cache(lruCache());
+    const traces = [];
+    qm.addEventListener('query', trace => traces.push(trace));
+    const client = {};
+    const request = () => qm.request(
+      { type: 'json', query: 'a', cache: true, trace: { client } }
+    );
+    await request();
+    await request();
+    const running = qm.request({ type: 'json', query: 'b' });
+    const canceled = qm.request({ type: 'json', query: 'c' });
+    qm.cancel([canceled]);
+    await running;
+
+    const [first, second, third, fourth] = traces;
+    assert.strictEqual(first.client, client);
+    assert.strictEqual(first.cached, false);
+    assert.strictEqual(first.status, 'done');
+    assert.strictEqual(first.bytes, 2);
+    assert.ok(first.time >= 0 && first.wait >= 0);
+    assert.strictEqual(second.cached, true);
+    assert.strictEqual(third.sql, 'c');
+    assert.strictEqual(third.status, 'canceled');
+    assert.strictEqual(third.wait, null);
+    assert.strictEqual(fourth.sql, 'b');
+    assert.strictEqual(fourth.status, 'done');
+    assert.strictEqual(traces.length, 4);
+  });
+
+  it('traces result bytes reported by the connector', async () => {
+    const qm = manager({
+      async query({ sql }, { onBytes }) {
+        if (sql === 'ipc') onBytes?.(1024);
+        return sql;
+      }
+    });
+    const traces = [];
+    qm.addEventListener('query', trace => traces.push(trace));
+    await qm.request({ type: 'arrow', query: 'ipc' });
+    await qm.request({ type: 'json', query: 'rows' });
+    await qm.request({ type: 'json', query: 'skip', trace: false });
+    assert.deepStrictEqual(
+      traces.map(({ sql, bytes }) => [sql, bytes]),
+      [['ipc', 1024], ['rows', 8]]
+    );
+  });
+
+  it('cancels submitted requests', async () => {
+    const canceled = [];
+    const qm = manager({
+      query: ({ id }) => new Promise((resolve, reject) => {
+        canceled.push(() => reject(`Canceled ${id}`));
+      }),
+      cancel: id => canceled.shift()(id)
+    }, 4);
+    const result = qm.request({ type: 'json', query: 'a' });
+    qm.cancel([result]);
+    await assert.rejects(result, /^Canceled/);
+    assert.strictEqual(qm.inflight, 0);
+  });
+
+  it('streams record batches', async () => {
+    const batches = [];
+    const qm = manager({
+      async query({ sql, stream }, { onBatch } = {}) {
+        if (!stream) return sql;
+        [...sql].forEach(onBatch);
+        return sql;
+      }
+    }, 4);
+    const onBatch = batch => batches.push(batch);
+    assert.strictEqual(
+      await qm.request({ type: 'arrow', query: 'ab', onBatch }),
+      'ab'
+    );
+    assert.deepStrictEqual(batches, ['a', 'b']);
+    assert.strictEqual(
+      await qm.request({ type: 'json', query: 'cd', onBatch }),
+      'cd'
+    );
+    assert.deepStrictEqual(batches, ['a', 'b']);
+  });
+});
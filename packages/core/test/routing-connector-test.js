@@ -0,0 +1,163 @@
+import assert from 'node:assert';
+import { Query, count, sql } from '@uwdata/mosaic-sql';
+import {
+  Coordinator, Selection, clausePoint, clausePoints, routingConnector
+} from '../src/index.js';
+import { voidLogger } from '../src/util/void-logger.js';
+import { TestClient } from './util/test-client.js';
+
+function logConnector(name, log, results = () => []) {
+  const listeners = { state: new Set, notify: new Set };
+  const subscribed = [];
+  return {
+    subscribed,
+    state: 'open',
+    async query({ sql }) {
+      log.push(`${name}: ${sql}`);
+      return results(sql);
+    },
+    subscribe(tables) {
+      subscribed.push(...tables);
+    },
+    addEventListener(type, callback) {
+      listeners[type].add(callback);
+    },
+    removeEventListener(type, callback) {
+      listeners[type].delete(callback);
+    },
+    emit(type, value) {
+      listeners[type].forEach(callback => callback(value));
+    }
+  };
+}
+
+describe('routingConnector', () => {
+  it('routes queries by table and schema', async () => {
+    const log = [];
+    const local = logConnector('local', log);
+    const server = logConnector('server', log);
+    const db = routingConnector({
+      routes: [{ connector: local, tables: ['lookup'], schemas: ['Mem'] }],
+      fallback: server
+    });
+
+    await db.query({ type: 'json', sql: 'SELECT * FROM "lookup"' });
+    await db.query({ type: 'json', sql: 'SELECT * FROM mem.codes AS c' });
+    await db.query({ type: 'json', sql: 'SELECT * FROM flights' });
+    await db.query({ type: 'json', sql: 'SELECT 1' });
+    assert.deepStrictEqual(log.splice(0), [
+      'local: SELECT * FROM "lookup"',
+      'local: SELECT * FROM mem.codes AS c',
+      'server: SELECT * FROM flights',
+      'server: SELECT 1'
+    ]);
+
+    // tables created by exec statements follow their source tables
+    await db.query({ type: 'exec', sql: 'CREATE TABLE cube AS SELECT a FROM lookup' });
+    await db.query({ type: 'json', sql: 'SELECT a FROM cube' });
+    assert.strictEqual(db.locate('cube'), local);
+    assert.strictEqual(db.locate('other.lookup'), server);
+
+    // setup statements apply to all connectors
+    await db.query({ type: 'exec', sql: 'CREATE SCHEMA IF NOT EXISTS mosaic' });
+    assert.deepStrictEqual(log.splice(2), [
+      'server: CREATE SCHEMA IF NOT EXISTS mosaic',
+      'local: CREATE SCHEMA IF NOT EXISTS mosaic'
+    ]);
+
+    await assert.rejects(
+      db.query({ type: 'json', sql: 'SELECT * FROM flights JOIN lookup USING (k)' })
+    );
+  });
+
+  it('routes filtered queries by their table references', async () => {
+    const log = [];
+    const local = logConnector('local', log);
+    const server = logConnector('server', log);
+    const db = routingConnector({
+      routes: [{ connector: local, tables: ['lookup'] }],
+      fallback: server
+    });
+
+    // point selection predicates use IS NOT DISTINCT FROM comparisons
+    const point = clausePoint('code', 5, { source: {} });
+    const points = clausePoints(['code', 'name'], [[1, 'b'], [2, 'from c']], { source: {} });
+    const query = Query.from('lookup')
+      .select({ year: sql`EXTRACT(year FROM "date")` })
+      .where(point.predicate, points.predicate);
+    await db.query({ type: 'json', sql: `${query}` });
+    assert.deepStrictEqual(log, [`local: ${query}`]);
+  });
+
+  it('routes persistent index tables to their base table connector', async () => {
+    async function index(results) {
+      const log = [];
+      const errors = [];
+      const local = logConnector('local', log, results);
+      const server = logConnector('server', log);
+      const db = routingConnector({
+        routes: [{ connector: local, tables: ['lookup'] }],
+        fallback: server
+      });
+      const mc = new Coordinator(db, {
+        logger: { ...voidLogger(), error: e => errors.push(e) },
+        cache: false,
+        indexes: { temp: false }
+      });
+      const client = new TestClient(
+        Query.from('lookup').select({ key: 'key', n: count() }).groupby('key')
+      );
+      const selection = Selection.crossfilter();
+      const clause = clausePoint('code', 1, { source: {} });
+      const info = mc.dataCubeIndexer.index(client, selection, clause);
+      await info.result;
+      await mc.dataCubeIndexer.track(info.table);
+      assert.deepStrictEqual(errors, []);
+      assert.strictEqual(db.locate(info.table), local);
+      return { log, table: info.table };
+    }
+
+    // usage statements run on the database of the index table
+    const { log, table } = await index(() => []);
+    const server = log.filter(entry => entry.startsWith('server:'));
+    assert.deepStrictEqual(server, [
+      'server: CREATE SCHEMA IF NOT EXISTS mosaic'
+    ]);
+    assert.ok(log.includes(
+      'local: CREATE TABLE IF NOT EXISTS mosaic.cube_index_usage '
+        + '(name VARCHAR PRIMARY KEY, accessed TIMESTAMP, rows BIGINT)'
+    ));
+    assert.ok(log.some(entry => entry.startsWith(
+      `local: INSERT OR REPLACE INTO mosaic.cube_index_usage SELECT '${table}'`
+    )));
+
+    // index tables of earlier sessions are reused and routed
+    const name = table.split('.')[1];
+    const reused = await index(
+      sql => sql.includes('duckdb_tables()') ? [{ table_name: name }] : []
+    );
+    assert.strictEqual(reused.table, table);
+    assert.ok(!reused.log.some(entry => entry.includes(`TABLE IF NOT EXISTS ${table}`)));
+  });
+
+  it('forwards subscriptions and events', async () => {
+    const local = logConnector('local', []);
+    const server = logConnector('server', []);
+    const db = routingConnector({
+      routes: [{ connector: local, tables: ['lookup'] }],
+      fallback: server
+    });
+    const mc = new Coordinator(db, { logger: null, cache: false });
+
+    mc.subscribe(['lookup', 'flights']);
+    assert.deepStrictEqual(local.subscribed, ['lookup']);
+    assert.deepStrictEqual(server.subscribed, ['flights']);
+
+    const states = [];
+    db.addEventListener('state', value => states.push(value));
+    server.state = 'reconnecting';
+    server.emit('state', server.state);
+    assert.deepStrictEqual(states, ['reconnecting']);
+    assert.strictEqual(mc.connectionState, 'reconnecting');
+  });
+});
@@ -0,0 +1,105 @@
+import assert from 'node:assert';
+import { Query } from '@uwdata/mosaic-sql';
+import {
+  Selection, SelectionResolver, clauseInterval, clauseNegate, clausePoint,
+  registerResolver
+} from '../src/index.js';
+import { TestClient } from './util/test-client.js';
+
+describe('Selection', () => {
+  it('filters related tables using semi-joins', () => {
+    const flights = new TestClient(Query.from('flights').select('delay'));
+    const carriers = new TestClient(Query.from('carriers').select('name'));
+    const alliances = new TestClient(Query.from('alliances').select('name'));
+    const airports = new TestClient(Query.from('airports').select('name'));
+
+    const sel = Selection.crossfilter({
+      relations: [['flights.carrier', 'carriers.code']]
+    }).relate('carriers.alliance', { table: 'alliances', column: 'id' });
+
+    sel.update(clauseInterval('delay', [0, 10], { source: flights }));
+    assert.strictEqual(sel.predicate(flights), undefined);
+    assert.strictEqual(
+      String(sel.predicate(carriers)),
+      '("code" IN (SELECT "carrier" FROM "flights" WHERE ("delay" BETWEEN 0 AND 10)))'
+    );
+    assert.strictEqual(
+      String(sel.predicate(alliances)),
+      '("id" IN (SELECT "alliance" FROM "carriers" WHERE ("code" IN '
+        + '(SELECT "carrier" FROM "flights" WHERE ("delay" BETWEEN 0 AND 10)))))'
+    );
+    // unrelated tables receive the clause predicate as-is
+    assert.strictEqual(
+      String(sel.predicate(airports)),
+      '("delay" BETWEEN 0 AND 10)'
+    );
+    assert.ok(sel.related(carriers, sel.active));
+    assert.ok(!sel.related(airports, sel.active));
+
+    // relationships apply in both directions
+    sel.update(clausePoint('name', 'Delta', { source: carriers }));
+    assert.deepStrictEqual(sel.predicate(flights).map(String), [
+      '("carrier" IN (SELECT "code" FROM "carriers" WHERE '
+        + '("name" IS NOT DISTINCT FROM \'Delta\')))'
+    ]);
+  });
+
+  it('queries client tables once per client', () => {
+    let calls = 0;
+    const flights = new TestClient(Query.from('flights').select('delay'));
+    const carriers = new TestClient(Query.from('carriers').select('name'), null, {
+      query() { ++calls; return Query.from('carriers').select('name'); }
+    });
+    const sel = Selection.crossfilter({
+      relations: [['flights.carrier', 'carriers.code']]
+    });
+    sel.update(clauseInterval('delay', [0, 10], { source: flights }));
+    for (let i = 0; i < 3; ++i) {
+      assert.ok(String(sel.predicate(carriers)).startsWith('("code" IN'));
+    }
+    assert.strictEqual(calls, 1);
+  });
+
+  it('resolves clauses per field', () => {
+    const sel = Selection.perField();
+    const [a, b, c] = [{}, {}, {}];
+    sel.update(clausePoint('carrier', 'AA', { source: a }));
+    sel.update(clausePoint('carrier', 'UA', { source: b }));
+    sel.update(clausePoint('origin', 'SEA', { source: c }));
+    assert.deepStrictEqual(sel.predicate(null).map(String), [
+      '(("carrier" IS NOT DISTINCT FROM \'AA\') OR ("carrier" IS NOT DISTINCT FROM \'UA\'))',
+      '("origin" IS NOT DISTINCT FROM \'SEA\')'
+    ]);
+  });
+
+  it('applies negated clauses', () => {
+    const sel = Selection.intersect();
+    const source = {};
+    sel.update(clauseNegate(clauseInterval('delay', [0, 10], { source })));
+    assert.deepStrictEqual(sel.predicate(null).map(String), [
+      '(("delay" BETWEEN 0 AND 10) IS NOT TRUE)'
+    ]);
+    assert.strictEqual(sel.active.meta.negate, true);
+  });
+
+  it('creates selections using registered resolvers', () => {
+    class FirstResolver extends SelectionResolver {
+      combine(predicates) {
+        return predicates.slice(0, 1);
+      }
+    }
+    registerResolver('first', options => new FirstResolver(options));
+    assert.ok(Selection.has('first'));
+    assert.ok(Selection.has('perField'));
+    assert.throws(() => Selection.create('unknown'));
+
+    const sel = Selection.create('first', { empty: true });
+    assert.deepStrictEqual(sel.predicate(null), ['FALSE']);
+    sel.update(clausePoint('x', 1, { source: {} }));
+    sel.update(clausePoint('y', 2, { source: {} }));
+    assert.deepStrictEqual(sel.predicate(null).map(String), [
+      '("x" IS NOT DISTINCT FROM 1)'
+    ]);
+    assert.strictEqual(Selection.create('union').resolver.union, true);
+  });
+});
@@ -0,0 +1,201 @@
+import assert from 'node:assert';
+import { socketConnector } from '../src/index.js';
+
+// fake WebSocket, records sent messages and lets tests emit events
+class FakeWebSocket {
+  static instances = [];
+
+  constructor(uri) {
+    this.uri = uri;
+    this.sent = [];
+    this.listeners = {};
+    FakeWebSocket.instances.push(this);
+  }
+
+  addEventListener(type, callback) {
+    this.listeners[type] = callback;
+  }
+
+  send(message) {
+    this.sent.push(JSON.parse(message));
+  }
+
+  emit(type, event = {}) {
+    this.listeners[type]?.(event);
+  }
+
+  receive(data) {
+    this.emit('message', {
+      data: typeof data === 'string' ? data
+        : { size: data.byteLength, arrayBuffer: () => data }
+    });
+  }
+}
+
+describe('socketConnector', () => {
+  let WebSocket;
+  let setTimeout;
+  let timers;
+
+  beforeEach(() => {
+    WebSocket = globalThis.WebSocket;
+    globalThis.WebSocket = FakeWebSocket;
+    FakeWebSocket.instances = [];
+    // record reconnection timers, tests run them explicitly
+    setTimeout = globalThis.setTimeout;
+    timers = [];
+    globalThis.setTimeout = (callback, delay) => {
+      timers.push({ callback, delay });
+    };
+  });
+
+  afterEach(() => {
+    globalThis.WebSocket = WebSocket;
+    globalThis.setTimeout = setTimeout;
+  });
+
+  // close the current socket and open a new one after the backoff delay
+  function reconnect() {
+    FakeWebSocket.instances.at(-1).emit('close');
+    timers.shift().callback();
+    FakeWebSocket.instances.at(-1).emit('open');
+    return FakeWebSocket.instances.at(-1);
+  }
+
+  it('matches tagged responses by request id', async () => {
+    const db = socketConnector();
+    const a = db.query({ type: 'json', sql: 'SELECT 1', id: 'a' });
+    const b = db.query({ type: 'json', sql: 'SELECT 2', id: 'b' });
+    const [ws] = FakeWebSocket.instances;
+    ws.emit('open');
+    assert.deepStrictEqual(ws.sent.map(m => m.id), ['a', 'b']);
+
+    ws.receive(JSON.stringify({ id: 'b', result: [{ x: 2 }] }));
+    ws.receive(JSON.stringify({ id: 'a', result: [{ x: 1 }] }));
+    assert.deepStrictEqual(await a, [{ x: 1 }]);
+    assert.deepStrictEqual(await b, [{ x: 2 }]);
+  });
+
+  it('matches untagged responses in request order', async () => {
+    const db = socketConnector();
+    const a = db.query({ type: 'json', sql: 'SELECT 1' });
+    const b = db.query({ type: 'arrow', sql: 'SELECT 2' });
+    const c = db.query({ type: 'exec', sql: 'CREATE TABLE t (x INT)' });
+    const [ws] = FakeWebSocket.instances;
+    ws.emit('open');
+
+    const ipc = new Uint8Array([1, 2, 3]);
+    ws.receive(JSON.stringify([{ x: 1 }]));
+    ws.receive(ipc);
+    ws.receive(JSON.stringify({}));
+    assert.deepStrictEqual(await a, [{ x: 1 }]);
+    assert.strictEqual(await b, ipc);
+    assert.strictEqual(await c, undefined);
+  });
+
+  it('rejects untagged errors in request order', async () => {
+    const db = socketConnector();
+    const a = db.query({ type: 'json', sql: 'SELECT x' });
+    const b = db.query({ type: 'json', sql: 'SELECT 1' });
+    const [ws] = FakeWebSocket.instances;
+    ws.emit('open');
+
+    ws.receive(JSON.stringify({ error: 'Unknown column x' }));
+    ws.receive(JSON.stringify([{ x: 1 }]));
+    await assert.rejects(a, err => err === 'Unknown column x');
+    assert.deepStrictEqual(await b, [{ x: 1 }]);
+  });
+
+  it('passes untagged notifications to listeners', async () => {
+    const db = socketConnector();
+    const events = [];
+    db.addEventListener('notify', event => events.push(event));
+    const a = db.query({ type: 'json', sql: 'SELECT 1' });
+    const [ws] = FakeWebSocket.instances;
+    ws.emit('open');
+
+    ws.receive(JSON.stringify({ type: 'notify', table: 't', rowid: 3 }));
+    ws.receive(JSON.stringify([{ x: 1 }]));
+    assert.deepStrictEqual(events, [{ table: 't', rowid: 3 }]);
+    assert.deepStrictEqual(await a, [{ x: 1 }]);
+  });
+
+  it('reconnects with exponential backoff', () => {
+    const db = socketConnector('ws://localhost:3000/', {
+      minDelay: 100, maxDelay: 500
+    });
+    const states = [];
+    db.addEventListener('state', state => states.push(state));
+    db.subscribe(['t']);
+    assert.strictEqual(db.state, 'connecting');
+
+    const [ws] = FakeWebSocket.instances;
+    ws.emit('open');
+    assert.strictEqual(db.connected, true);
+
+    // failed attempts double the delay up to the maximum
+    const delays = [];
+    ws.emit('close');
+    for (let i = 0; i < 4; ++i) {
+      const { callback, delay } = timers.shift();
+      delays.push(delay);
+      callback();
+      FakeWebSocket.instances.at(-1).emit('close');
+    }
+    assert.deepStrictEqual(delays, [100, 200, 400, 500]);
+
+    // a successful connection resets the delay and renews subscriptions
+    timers.shift().callback();
+    const last = FakeWebSocket.instances.at(-1);
+    last.emit('open');
+    assert.deepStrictEqual(last.sent, [{ type: 'subscribe', tables: ['t'] }]);
+    last.emit('close');
+    assert.deepStrictEqual(timers.map(t => t.delay), [100]);
+    assert.strictEqual(FakeWebSocket.instances.length, 6);
+    assert.deepStrictEqual(
+      states,
+      ['connecting', 'open', 'reconnecting', 'open', 'reconnecting']
+    );
+  });
+
+  it('replays idempotent requests after reconnection', async () => {
+    const db = socketConnector();
+    const a = db.query({ type: 'json', sql: 'SELECT 1', id: 'a' });
+    const b = db.query({ type: 'exec', sql: 'INSERT INTO t VALUES (1)' });
+    FakeWebSocket.instances[0].emit('open');
+
+    const ws = reconnect();
+    await assert.rejects(b, err => err === 'Socket closed');
+    assert.deepStrictEqual(ws.sent, [{ type: 'json', sql: 'SELECT 1', id: 'a' }]);
+    ws.receive(JSON.stringify({ id: 'a', result: [{ x: 1 }] }));
+    assert.deepStrictEqual(await a, [{ x: 1 }]);
+  });
+
+  it('limits the number of replays per request', async () => {
+    const db = socketConnector('ws://localhost:3000/', { replays: 2 });
+    const a = db.query({ type: 'json', sql: 'SELECT 1', id: 'a' });
+    FakeWebSocket.instances[0].emit('open');
+
+    // the request is resent after the first two closes only
+    assert.strictEqual(reconnect().sent.length, 1);
+    const ws = reconnect();
+    assert.strictEqual(ws.sent.length, 1);
+    ws.emit('close');
+    await assert.rejects(a, err => err === 'Socket closed');
+
+    // the connector itself keeps reconnecting
+    assert.strictEqual(timers.length, 1);
+    assert.strictEqual(db.state, 'reconnecting');
+  });
+
+  it('rejects requests once retries are exhausted', async () => {
+    const db = socketConnector('ws://localhost:3000/', { retries: 1 });
+    const a = db.query({ type: 'json', sql: 'SELECT 1' });
+    FakeWebSocket.instances[0].emit('close');
+    timers.shift().callback();
+    FakeWebSocket.instances[1].emit('close');
+    await assert.rejects(a, err => err === 'Socket closed');
+    assert.strictEqual(timers.length, 0);
+    assert.strictEqual(db.state, 'closed');
+  });
+});
@@ -0,0 +1,83 @@
+import assert from 'node:assert';
+import { tableFromArrays, tableToIPC } from 'apache-arrow';
+import { wasmConnector } from '../src/index.js';
+
+// fake DuckDB-WASM instance, queries wait until released by name
+function fakeDuckDB(log) {
+  const ipc = tableToIPC(tableFromArrays({ x: [1] }));
+  const waits = new Map;
+  let count = 0;
+  return {
+    release(sql) {
+      waits.get(sql)?.();
+    },
+    async connect() {
+      const name = `con${count++}`;
+      return {
+        async send(sql) {
+          log.push(`${name}: ${sql}`);
+          if (sql.startsWith('WAIT')) {
+            await new Promise(resolve => waits.set(sql, resolve));
+          }
+          return ipc;
+        },
+        async cancelSent() {
+          log.push(`${name}: cancel`);
+        }
+      };
+    }
+  };
+}
+
+const tick = () => new Promise(resolve => setTimeout(resolve, 0));
+
+describe('wasmConnector', () => {
+  it('runs queries concurrently on pooled connections', async () => {
+    const log = [];
+    const duckdb = fakeDuckDB(log);
+    const db = wasmConnector({ duckdb });
+
+    // both queries are sent before either completes
+    const a = db.query({ type: 'arrow', sql: 'WAIT a' });
+    const b = db.query({ type: 'arrow', sql: 'WAIT b' });
+    await tick();
+    assert.deepStrictEqual(log.slice().sort(), ['con0: WAIT a', 'con1: WAIT b']);
+    duckdb.release('WAIT a');
+    duckdb.release('WAIT b');
+    await Promise.all([a, b]);
+
+    // exec statements and temporary tables use the primary connection
+    await db.query({ type: 'exec', sql: 'CREATE TEMP TABLE t AS SELECT 1' });
+    await db.query({ type: 'arrow', sql: 'SELECT * FROM t' });
+    // idle pooled connections are reused
+    await db.query({ type: 'arrow', sql: 'SELECT 1' });
+    assert.deepStrictEqual(log.slice(2, 4), [
+      'con2: CREATE TEMP TABLE t AS SELECT 1',
+      'con2: SELECT * FROM t'
+    ]);
+    assert.match(log[4], /^con[01]: SELECT 1$/);
+  });
+
+  it('cancels queued and running queries', async () => {
+    const log = [];
+    const duckdb = fakeDuckDB(log);
+    const db = wasmConnector({ duckdb });
+
+    // exec statements run one at a time, so the second one is queued
+    const a = db.query({ type: 'exec', sql: 'WAIT a', id: 'a' });
+    const b = db.query({ type: 'exec', sql: 'SELECT 2', id: 'b' });
+    await tick();
+    await db.cancel('b');
+    await db.cancel('a');
+    duckdb.release('WAIT a');
+    await a;
+    await assert.rejects(b);
+    assert.deepStrictEqual(log, ['con0: WAIT a', 'con0: cancel']);
+
+    // ids of unknown or completed requests are not recorded
+    await db.cancel('c');
+    await db.cancel('a');
+    await db.query({ type: 'exec', sql: 'SELECT 3', id: 'c' });
+    assert.deepStrictEqual(log.slice(2), ['con0: SELECT 3']);
+  });
+});
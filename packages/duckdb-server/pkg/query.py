@@ -1,15 +1,159 @@
 import logging
+import re
 from hashlib import sha256
 
 import pyarrow as pa
 
 logger = logging.getLogger(__name__)
 
+ROWS_PER_BATCH = 100_000
+
+# a possibly qualified and quoted table name
+NAME = r'((?:"(?:[^"]|"")+"|[\w$]+)(?:\s*\.\s*(?:"(?:[^"]|"")+"|[\w$]+))*)'
+
+# statements that modify a table, paired with a flag for append-only statements
+# these patterns mirror MUTATIONS in packages/sql/src/tables.js,
+# which the Node.js data server shares, keep both in sync
+# both are tested against the cases in packages/sql/test/table-mutations.json
+TABLE_MUTATIONS = [
+    (re.compile(rf"\bINSERT\s+INTO\s+{NAME}", re.IGNORECASE), True),
+    (re.compile(rf"\bCOPY\s+{NAME}\s+FROM\b", re.IGNORECASE), True),
+    (re.compile(rf"\bINSERT\s+OR\s+(?:REPLACE|IGNORE)\s+INTO\s+{NAME}", re.IGNORECASE), False),
+    (re.compile(rf"\bINSERT\s+INTO\s+{NAME}[^;]*\bON\s+CONFLICT\b", re.IGNORECASE), False),
+    (re.compile(rf"\bUPDATE\s+(?!SET\b){NAME}", re.IGNORECASE), False),
+    (re.compile(rf"\bDELETE\s+FROM\s+{NAME}", re.IGNORECASE), False),
+    (re.compile(rf"\bTRUNCATE\s+(?:TABLE\s+)?{NAME}", re.IGNORECASE), False),
+    (re.compile(rf"\bCREATE\s+OR\s+REPLACE\s+(?:(?:TEMP|TEMPORARY)\s+)?(?:TABLE|VIEW)\s+{NAME}", re.IGNORECASE), False),
+    (re.compile(rf"\b(?:DROP|ALTER)\s+(?:TABLE|VIEW)\s+(?:IF\s+EXISTS\s+)?{NAME}", re.IGNORECASE), False),
+]
+
+# table references, excluding table functions, the IS [NOT] DISTINCT FROM
+# operator, and numeric literals, mirroring REFERENCES in tables.js
+REFERENCES = re.compile(
+    r"\b(DISTINCT\s+)?(?:FROM|JOIN|INTO|UPDATE|TABLE|VIEW|COPY)\s+"
+    rf"(?:IF\s+(?:NOT\s+)?EXISTS\s+)?(?!\d){NAME}(?![\w$.\"]|\s*\()",
+    re.IGNORECASE,
+)
+
+# common table expression definitions
+CTES = re.compile(
+    rf"(?:\bWITH(?:\s+RECURSIVE)?|,)\s*{NAME}\s+AS\s+(?:(?:NOT\s+)?MATERIALIZED\s+)?\(",
+    re.IGNORECASE,
+)
+
+# string literals, which may contain SQL keywords
+STRINGS = re.compile(r"'(?:[^']|'')*'")
+
+# function calls whose arguments may include a FROM keyword
+FROM_CALLS = re.compile(r"\b(?:EXTRACT|SUBSTRING|TRIM|OVERLAY)\s*\(", re.IGNORECASE)
+
+# cache key suffix for the tables read by a cached query result
+TABLES_SUFFIX = ".tables"
+
 
 def get_key(sql, command):
     return f"{sha256(sql.encode('utf-8')).hexdigest()}.{command}"
 
 
+def get_cached(cache, query):
+    command = query.get("type")
+    if command not in ("arrow", "json"):
+        return None
+    return cache.get(get_key(query.get("sql"), command))
+
+
+def is_mutation(sql):
+    """Return True if the SQL string may modify an existing table.
+
+    Statements are matched by pattern rather than parsed, so the result may
+    be a false positive but should not miss a modification.
+    """
+    return any(pattern.search(sql) for pattern, _ in TABLE_MUTATIONS)
+
+
+def table_parts(name):
+    """Split a possibly qualified and quoted table name into its unquoted parts."""
+    parts = re.findall(r'"(?:[^"]|"")+"|[^."\s]+', name) or [""]
+    return tuple(
+        part[1:-1].replace('""', '"') if len(part) > 1 and part.startswith('"') and part.endswith('"') else part
+        for part in parts
+    )
+
+
+def table_name(name):
+    """Normalize a table name by removing qualifiers and quotes and lower-casing."""
+    return table_parts(name)[-1].lower()
+
+
+def mutated_tables(sql, qualified=False):
+    """Return the normalized names of tables modified by a SQL string.
+
+    Each name maps to True if the table is only appended to, such as by
+    insert statements, and False otherwise. If qualified is True, tables
+    are instead keyed by a tuple of their unquoted name parts, including
+    any schema and database qualifiers.
+    """
+    tables = {}
+    for pattern, append in TABLE_MUTATIONS:
+        for match in pattern.finditer(sql):
+            name = table_parts(match.group(1)) if qualified else table_name(match.group(1))
+            tables[name] = tables.get(name, True) and append
+    return tables
+
+
+def is_index_table(name):
+    """Return True if the normalized table name is a Mosaic data cube index table."""
+    return name.startswith("cube_index_")
+
+
+def mask_sql(sql):
+    """Blank out string literals and function arguments that may contain a FROM keyword."""
+    text = STRINGS.sub(lambda m: "'" + " " * (len(m.group(0)) - 2) + "'", sql)
+    for match in FROM_CALLS.finditer(text):
+        start = end = match.end()
+        depth = 1
+        while end < len(text) and depth > 0:
+            if text[end] == "(":
+                depth += 1
+            elif text[end] == ")":
+                depth -= 1
+            end += 1
+        text = text[:start] + " " * (end - 1 - start) + text[end - 1 :]
+    return text
+
+
+def sql_tables(sql):
+    """Return the normalized names of tables referenced by a SQL string.
+
+    References are matched by pattern rather than parsed. References to common
+    table expressions and table functions are not included.
+    """
+    text = mask_sql(sql)
+    names = {table_name(m.group(2)) for m in REFERENCES.finditer(text) if not m.group(1)}
+    names -= {table_name(m.group(1)) for m in CTES.finditer(text)}
+    return sorted(names)
+
+
+def invalidate(cache, sql):
+    """Evict cached results that read tables modified by the SQL string.
+
+    Results with unknown tables, such as those loaded from bundles, may read any
+    table and are evicted unless only data cube index tables are modified.
+    """
+    tables = mutated_tables(sql)
+    if not tables:
+        return
+    unknown = not all(is_index_table(t) for t in tables)
+    for key in list(cache):
+        if key.endswith(TABLES_SUFFIX):
+            continue
+        read = cache.get(key + TABLES_SUFFIX)
+        if unknown if read is None else any(t in tables for t in read):
+            logger.debug(f"Cache invalidated {key}")
+            cache.pop(key, None)
+            cache.pop(key + TABLES_SUFFIX, None)
+
+
 def retrieve(cache, query, get):
     sql = query.get("sql")
     command = query.get("type")
@@ -23,6 +167,7 @@ def retrieve(cache, query, get):
         result = get(sql)
         if query.get("persist", False):
             cache[key] = result
+            cache[key + TABLES_SUFFIX] = sql_tables(sql)
     return result
 
 
@@ -41,6 +186,21 @@ def get_arrow_bytes(con, sql):
     return arrow_to_bytes(get_arrow(con, sql))
 
 
+def get_arrow_batches(con, sql, rows_per_batch=ROWS_PER_BATCH):
+    """Yield the query result as Arrow IPC bytes, one record batch at a time.
+
+    Each yielded buffer is a complete IPC stream, so that clients can decode
+    batches independently. An empty result yields a single empty table.
+    """
+    reader = con.execute(sql).fetch_record_batch(rows_per_batch)
+    empty = True
+    for batch in reader:
+        empty = False
+        yield arrow_to_bytes(batch)
+    if empty:
+        yield arrow_to_bytes(pa.Table.from_batches([], reader.schema))
+
+
 def get_json(con, sql):
     result = con.query(sql).df()
     return result.to_json(orient="records")
@@ -1,6 +1,12 @@
+from __future__ import annotations
+
+import asyncio
 import logging
+import re
 import sys
+import threading
 import time
+from concurrent.futures import ThreadPoolExecutor
 from functools import partial
 from pathlib import Path
 
@@ -8,21 +14,39 @@ import ujson
 from socketify import App, CompressOptions, OpCode
 
 from pkg.bundle import create_bundle, load_bundle
-from pkg.query import get_arrow_bytes, get_json, retrieve
+from pkg.query import (
+    get_arrow_batches,
+    get_arrow_bytes,
+    get_cached,
+    get_json,
+    invalidate,
+    mutated_tables,
+    retrieve,
+    table_name,
+)
 
 logger = logging.getLogger(__name__)
 
 BUNDLE_DIR = Path(".mosaic/bundle")
 SLOW_QUERY_THRESHOLD = 5000
 
+# statements that manage transactions, which can not be nested
+TRANSACTION = re.compile(r"\b(?:BEGIN|COMMIT|ROLLBACK|ABORT)\b", re.IGNORECASE)
+
 
 class Handler:
+    # whether the handler can send Arrow record batches incrementally
+    streaming = False
+
     def done(self):
         raise Exception("NotImplementedException")
 
     def arrow(self, _buffer):
         raise Exception("NotImplementedException")
 
+    def batch(self, _buffer):
+        raise Exception("NotImplementedException")
+
     def json(self, _data):
         raise Exception("NotImplementedException")
 
@@ -31,30 +55,244 @@ class Handler:
 
 
 class SocketHandler(Handler):
-    def __init__(self, ws):
+    """Sends responses over a WebSocket.
+
+    If the request includes an id, every response is a JSON message tagged
+    with that id, so that clients can match responses that arrive out of
+    order. Arrow results are sent as a tagged header message followed by a
+    binary message with the Arrow IPC bytes. Streamed Arrow results are sent
+    as a sequence of tagged batch headers and binary messages, followed by a
+    tagged done message. Requests without an id receive untagged responses in
+    request order.
+    """
+
+    def __init__(self, ws, query_id=None):
         self.ws = ws
+        self.query_id = query_id
+        self.streaming = query_id is not None
 
     def check(self, ok):
         if not ok:
             logger.warning(f"WebSocket backpressure: {self.ws.get_buffered_amount()}")
 
+    def tag(self, message):
+        return message if self.query_id is None else {"id": self.query_id, **message}
+
     def done(self):
-        ok = self.ws.send({}, OpCode.TEXT)
+        ok = self.ws.send(self.tag({}), OpCode.TEXT)
         self.check(ok)
 
     def arrow(self, buffer):
+        if self.query_id is not None:
+            self.ws.send(self.tag({"type": "arrow"}), OpCode.TEXT)
+        ok = self.ws.send(buffer, OpCode.BINARY)
+        self.check(ok)
+
+    def batch(self, buffer):
+        self.ws.send(self.tag({"type": "batch"}), OpCode.TEXT)
         ok = self.ws.send(buffer, OpCode.BINARY)
         self.check(ok)
 
     def json(self, data):
+        if self.query_id is not None:
+            # embed the serialized result to avoid parsing it again
+            result = data if isinstance(data, str) else ujson.dumps(data)
+            data = f'{{"id":{ujson.dumps(self.query_id)},"result":{result}}}'
         ok = self.ws.send(data, OpCode.TEXT)
         self.check(ok)
 
     def error(self, error):
-        ok = self.ws.send({"error": str(error)}, OpCode.TEXT)
+        ok = self.ws.send(self.tag({"error": str(error)}), OpCode.TEXT)
         self.check(ok)
 
 
+class DeferredHandler(Handler):
+    """Records a response so that it can be sent later from the event loop.
+
+    If a stream callback is provided, record batches are forwarded to it as
+    soon as they are available rather than recorded.
+    """
+
+    def __init__(self, stream=None):
+        self.response = None
+        self.stream = stream
+        self.streaming = stream is not None
+
+    def done(self):
+        self.response = ("done",)
+
+    def arrow(self, buffer):
+        self.response = ("arrow", buffer)
+
+    def batch(self, buffer):
+        self.stream(buffer)
+
+    def json(self, data):
+        self.response = ("json", data)
+
+    def error(self, error):
+        self.response = ("error", error)
+
+    def send(self, handler: Handler):
+        method, *args = self.response
+        getattr(handler, method)(*args)
+
+
+class QueryCanceledError(Exception):
+    pass
+
+
+class QueryTracker:
+    """Tracks submitted queries so that they can be canceled by request id.
+
+    Queries run one at a time on the shared connection. Canceling the running
+    query interrupts the connection, while canceling a submitted query that
+    has not started yet causes it to be skipped.
+    """
+
+    def __init__(self, con):
+        self.con = con
+        self.lock = threading.Lock()
+        self.submitted = set()
+        self.canceled = set()
+        self.running = None
+
+    def submit(self, query_id):
+        if query_id is not None:
+            with self.lock:
+                self.submitted.add(query_id)
+
+    def start(self, query_id):
+        with self.lock:
+            self.submitted.discard(query_id)
+            if query_id in self.canceled:
+                self.canceled.discard(query_id)
+                raise QueryCanceledError(f"Query {query_id} was canceled")
+            self.running = query_id
+
+    def finish(self, query_id):
+        with self.lock:
+            if self.running == query_id:
+                self.running = None
+
+    def cancel(self, query_id):
+        if query_id is None:
+            return
+        with self.lock:
+            if self.running == query_id:
+                logger.info(f"Interrupting query {query_id}")
+                self.con.interrupt()
+            elif query_id in self.submitted:
+                self.canceled.add(query_id)
+
+
+class LiveTables:
+    """Tracks WebSocket subscriptions to table modifications.
+
+    Subscribers are notified after an exec query modifies a subscribed table.
+    If the query only appended rows to the table, the notification includes
+    the largest row id prior to the append, so that subscribers can query
+    just the appended rows. Notifications are recorded on the worker thread
+    and sent from the event loop.
+    """
+
+    def __init__(self):
+        self.lock = threading.Lock()
+        self.subscribers = {}
+        self.pending = []
+
+    def subscribe(self, ws, tables):
+        with self.lock:
+            for table in tables:
+                self.subscribers.setdefault(table_name(table), set()).add(ws)
+
+    def unsubscribe(self, ws, tables=None):
+        with self.lock:
+            names = list(self.subscribers) if tables is None else [table_name(t) for t in tables]
+            for name in names:
+                sockets = self.subscribers.get(name, set())
+                sockets.discard(ws)
+                if not sockets:
+                    self.subscribers.pop(name, None)
+
+    def watched(self, sql):
+        """Return the subscribed tables modified by a SQL string, mapped to an append-only flag.
+
+        Tables are keyed by a tuple of their qualified name parts.
+        """
+        with self.lock:
+            return {
+                parts: append
+                for parts, append in mutated_tables(sql, qualified=True).items()
+                if parts[-1].lower() in self.subscribers
+            }
+
+    def rowids(self, con, tables):
+        """Return the largest row id of each append-only table, or -1 if the table is empty.
+
+        Row ids are keyed by normalized table name. Views and missing tables
+        have no row ids and are omitted.
+        """
+        rowids = {}
+        for parts, append in tables.items():
+            if not append:
+                continue
+            # match qualifiers from the innermost (table) to the outermost part
+            columns = ["table_name", "schema_name", "database_name"][: len(parts)]
+            found = con.execute(
+                "SELECT 1 FROM duckdb_tables() WHERE "
+                + " AND ".join(f"lower({column}) = lower(?)" for column in columns),
+                list(reversed(parts))[: len(columns)],
+            ).fetchone()
+            name = ".".join(parts)
+            if found is None:
+                logger.debug(f"No row id for table {name}")
+                continue
+            quoted = ".".join('"' + part.replace('"', '""') + '"' for part in parts)
+            (rowid,) = con.execute(f"SELECT MAX(rowid) FROM {quoted}").fetchone()
+            rowids[parts[-1].lower()] = -1 if rowid is None else rowid
+        return rowids
+
+    def execute(self, con, sql, tables):
+        """Execute a mutation, returning the largest prior row id of each append-only table.
+
+        Row ids are read in the same transaction as the mutation, so that they
+        describe the table state the mutation applies to. Statements that manage
+        their own transactions are executed as-is, without row ids.
+        """
+        if not any(tables.values()) or TRANSACTION.search(sql):
+            con.execute(sql)
+            return {}
+        try:
+            con.execute("BEGIN TRANSACTION")
+        except Exception:
+            # already within a transaction opened by an earlier statement
+            con.execute(sql)
+            return {}
+        try:
+            rowids = self.rowids(con, tables)
+            con.execute(sql)
+            con.execute("COMMIT")
+        except Exception:
+            con.execute("ROLLBACK")
+            raise
+        return rowids
+
+    def changed(self, tables, rowids):
+        names = dict.fromkeys(parts[-1].lower() for parts in tables)
+        with self.lock:
+            for table in names:
+                self.pending.append({"type": "notify", "table": table, "rowid": rowids.get(table)})
+
+    def flush(self):
+        with self.lock:
+            messages, self.pending = self.pending, []
+            targets = [(message, list(self.subscribers.get(message["table"], ()))) for message in messages]
+        for message, sockets in targets:
+            for ws in sockets:
+                ws.send(message, OpCode.TEXT)
+
+
 class HTTPHandler(Handler):
     def __init__(self, res):
         self.res = res
@@ -75,17 +313,34 @@ class HTTPHandler(Handler):
         self.res.end(str(error))
 
 
-def handle_query(handler: Handler, con, cache, query):
+def handle_query(
+    handler: Handler, con, cache, query, tracker: QueryTracker | None = None, live: LiveTables | None = None
+):
     logger.debug(f"{query=}")
 
     start = time.time()
 
-    sql = query["sql"]
+    sql = query.get("sql")
     command = query["type"]
+    query_id = query.get("id")
 
     try:
+        if tracker is not None:
+            tracker.start(query_id)
+
         if command == "exec":
-            con.execute(sql)
+            tables = live.watched(sql) if live is not None else {}
+            if tables:
+                rowids = live.execute(con, sql, tables)
+            else:
+                con.execute(sql)
+            invalidate(cache, sql)
+            if tables:
+                live.changed(tables, rowids)
+            handler.done()
+        elif command == "arrow" and query.get("stream") and handler.streaming:
+            for buffer in get_arrow_batches(con, sql):
+                handler.batch(buffer)
             handler.done()
         elif command == "arrow":
             buffer = retrieve(cache, query, partial(get_arrow_bytes, con))
@@ -101,9 +356,15 @@ def handle_query(handler: Handler, con, cache, query):
             handler.done()
         else:
             raise ValueError(f"Unknown command {command}")
+    except QueryCanceledError as e:
+        logger.info(str(e))
+        handler.error(e)
     except Exception as e:
         logger.exception("Eror processing query")
         handler.error(e)
+    finally:
+        if tracker is not None:
+            tracker.finish(query_id)
 
     total = round((time.time() - start) * 1_000)
     if total > SLOW_QUERY_THRESHOLD:
@@ -112,6 +373,48 @@ def handle_query(handler: Handler, con, cache, query):
         logger.info(f"DONE. Query took { total } ms.\n{ sql }")
 
 
+async def submit_query(
+    handler: Handler, con, cache, query, tracker: QueryTracker, executor, live: LiveTables | None = None
+):
+    """Run a query on the worker thread and send the response from the event loop.
+
+    Running queries off the event loop keeps the server responsive to cancel
+    commands while a query executes. Table modification notifications are
+    sent after the response of the modifying query.
+    """
+    command = query.get("type")
+    if command == "cancel":
+        tracker.cancel(query.get("id"))
+        return False
+
+    if command in ("subscribe", "unsubscribe"):
+        if live is not None and isinstance(handler, SocketHandler):
+            getattr(live, command)(handler.ws, query.get("tables", []))
+        return False
+
+    # tagged requests may be answered out of order, respond to cache hits now
+    if isinstance(handler, SocketHandler) and handler.query_id is not None:
+        cached = get_cached(cache, query)
+        if cached is not None:
+            logger.debug("Cache hit")
+            if query["type"] == "arrow":
+                handler.arrow(cached)
+            else:
+                handler.json(cached)
+            return True
+
+    tracker.submit(query.get("id"))
+    loop = asyncio.get_running_loop()
+    # record batches are forwarded to the event loop as they are produced
+    stream = partial(loop.call_soon_threadsafe, handler.batch) if handler.streaming else None
+    deferred = DeferredHandler(stream)
+    await loop.run_in_executor(executor, partial(handle_query, deferred, con, cache, query, tracker, live))
+    deferred.send(handler)
+    if live is not None:
+        live.flush()
+    return True
+
+
 def on_error(error, res, req):
     logger.error(str(error))
     if res is not None:
@@ -127,17 +430,23 @@ def server(con, cache):
     # faster serialization than standard json
     app.json_serializer(ujson)
 
-    def ws_message(ws, message, opcode):
-        handler = SocketHandler(ws)
+    # queries run one at a time on a worker thread, in the order received
+    executor = ThreadPoolExecutor(max_workers=1)
+    tracker = QueryTracker(con)
+    live = LiveTables()
 
+    async def ws_message(ws, message, opcode):
         try:
             query = ujson.loads(message)
         except Exception as e:
             logger.exception("Error reading message from WebSocket")
-            handler.error(e)
+            SocketHandler(ws).error(e)
             return
 
-        handle_query(handler, con, cache, query)
+        # cancel and subscription commands do not receive a response over the socket
+        # the canceled query itself still responds with an error
+        handler = SocketHandler(ws, query.get("id"))
+        await submit_query(handler, con, cache, query, tracker, executor, live)
 
     async def http_handler(res, req):
         res.write_header("Access-Control-Allow-Origin", "*")
@@ -152,18 +461,23 @@ def server(con, cache):
 
         if method == "OPTIONS":
             handler.done()
+            return
         elif method == "GET":
             data = ujson.loads(req.get_query("query"))
-            handle_query(handler, con, cache, data)
         elif method == "POST":
             data = await res.get_json()
-            handle_query(handler, con, cache, data)
+        else:
+            return
+
+        if not await submit_query(handler, con, cache, data, tracker, executor, live):
+            handler.done()
 
     app.ws(
         "/*",
         {
             "compression": CompressOptions.SHARED_COMPRESSOR,
             "message": ws_message,
+            "close": lambda ws, code, message: live.unsubscribe(ws),
             "drain": lambda ws: logger.warning(f"WebSocket backpressure: {ws.get_buffered_amount()}"),
         },
     )
@@ -1,9 +1,26 @@
+import json
 from functools import partial
+from pathlib import Path
 
 import duckdb
 import pyarrow as pa
+import pytest
 
-from pkg.query import get_arrow, get_json, get_key
+from pkg.query import (
+    get_arrow,
+    get_arrow_batches,
+    get_cached,
+    get_json,
+    get_key,
+    invalidate,
+    is_mutation,
+    mutated_tables,
+    retrieve,
+    sql_tables,
+)
+
+# cases shared with the JavaScript table matching tests (mosaic-sql)
+TABLE_CASES = json.loads((Path(__file__).parents[3] / "sql" / "test" / "table-mutations.json").read_text())
 
 
 def test_key():
@@ -23,3 +40,73 @@ def test_query_arrow():
     table = pa.Table.from_pylist([{"a": 1}], schema=my_schema)
 
     assert partial(get_arrow, con)("SELECT 1 AS a") == table
+
+
+def test_get_cached():
+    cache = {get_key("SELECT 1", "json"): '[{"1":1}]'}
+
+    assert get_cached(cache, {"sql": "SELECT 1", "type": "json"}) == '[{"1":1}]'
+    assert get_cached(cache, {"sql": "SELECT 1", "type": "arrow"}) is None
+    assert get_cached(cache, {"sql": "SELECT 1", "type": "exec"}) is None
+
+
+def test_is_mutation():
+    assert is_mutation("INSERT INTO t VALUES (1)")
+    assert is_mutation("create or replace table t AS SELECT 1")
+    assert is_mutation("DROP TABLE IF EXISTS t")
+    assert not is_mutation("CREATE TEMP TABLE IF NOT EXISTS t AS SELECT 1")
+    assert not is_mutation("SELECT * FROM t")
+
+
+def test_invalidate():
+    cache = {get_key("SELECT 1", "json"): '[{"1":1}]'}
+
+    invalidate(cache, "CREATE TABLE t AS SELECT 1")
+    assert len(cache) == 1
+
+    invalidate(cache, "INSERT INTO t VALUES (2)")
+    assert len(cache) == 0
+
+
+def test_invalidate_by_table():
+    cache = {get_key("SELECT 1", "json"): '[{"1":1}]'}
+    for sql in ["SELECT * FROM t", "SELECT * FROM u WHERE (a IS NOT DISTINCT FROM 5)"]:
+        retrieve(cache, {"sql": sql, "type": "json", "persist": True}, lambda _: "[]")
+
+    invalidate(cache, "INSERT OR REPLACE INTO mosaic.cube_index_usage VALUES (1)")
+    assert len(cache) == 5
+
+    invalidate(cache, "INSERT INTO t VALUES (2)")
+    assert get_cached(cache, {"sql": "SELECT * FROM t", "type": "json"}) is None
+    assert get_cached(cache, {"sql": "SELECT * FROM u WHERE (a IS NOT DISTINCT FROM 5)", "type": "json"}) == "[]"
+    assert get_cached(cache, {"sql": "SELECT 1", "type": "json"}) is None
+
+
+def test_sql_tables():
+    assert sql_tables("SELECT EXTRACT(year FROM d) FROM t WHERE s = 'from x'") == ["t"]
+    assert sql_tables("WITH c AS (SELECT * FROM a.b) SELECT * FROM c JOIN u USING (k)") == ["b", "u"]
+
+
+def test_query_arrow_batches():
+    con = duckdb.connect()
+
+    batches = list(get_arrow_batches(con, "SELECT range AS a FROM range(10)", 4))
+    tables = [pa.ipc.open_stream(b).read_all() for b in batches]
+
+    assert [t.num_rows for t in tables] == [4, 4, 2]
+    assert pa.concat_tables(tables).column("a").to_pylist() == list(range(10))
+
+
+def test_query_arrow_batches_empty():
+    con = duckdb.connect()
+
+    batches = list(get_arrow_batches(con, "SELECT 1 AS a WHERE false"))
+
+    assert len(batches) == 1
+    assert pa.ipc.open_stream(batches[0]).read_all().num_rows == 0
+
+
+@pytest.mark.parametrize("case", TABLE_CASES, ids=lambda case: case["sql"])
+def test_table_cases(case):
+    assert mutated_tables(case["sql"]) == case["mutations"]
+    assert sql_tables(case["sql"]) == case["tables"]
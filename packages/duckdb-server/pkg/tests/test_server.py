@@ -0,0 +1,128 @@
+import duckdb
+
+from pkg.server import DeferredHandler, LiveTables, QueryTracker, SocketHandler, handle_query
+
+
+def test_handle_query():
+    con = duckdb.connect()
+    handler = DeferredHandler()
+
+    handle_query(handler, con, {}, {"type": "json", "sql": "SELECT 1 AS a", "id": "q1"}, QueryTracker(con))
+
+    assert handler.response == ("json", '[{"a":1}]')
+
+
+def test_handle_query_stream():
+    con = duckdb.connect()
+    batches = []
+    handler = DeferredHandler(batches.append)
+
+    query = {"type": "arrow", "sql": "SELECT range AS a FROM range(10)", "id": "q1", "stream": True}
+    handle_query(handler, con, {}, query, QueryTracker(con))
+
+    assert len(batches) == 1
+    assert handler.response == ("done",)
+
+
+def test_cancel_submitted_query():
+    con = duckdb.connect()
+    tracker = QueryTracker(con)
+    handler = DeferredHandler()
+
+    tracker.submit("q1")
+    tracker.cancel("q1")
+    handle_query(handler, con, {}, {"type": "json", "sql": "SELECT 1 AS a", "id": "q1"}, tracker)
+
+    assert handler.response[0] == "error"
+    assert tracker.running is None
+
+
+class FakeSocket:
+    def __init__(self):
+        self.messages = []
+
+    def send(self, message, opcode):
+        self.messages.append(message)
+        return True
+
+
+def test_socket_handler_tags_responses():
+    ws = FakeSocket()
+    handler = SocketHandler(ws, "q1")
+
+    handler.done()
+    handler.json('[{"a":1}]')
+    handler.arrow(b"arrow")
+    handler.error("oops")
+
+    assert ws.messages == [
+        {"id": "q1"},
+        '{"id":"q1","result":[{"a":1}]}',
+        {"id": "q1", "type": "arrow"},
+        b"arrow",
+        {"id": "q1", "error": "oops"},
+    ]
+
+
+def test_socket_handler_batches():
+    ws = FakeSocket()
+    handler = SocketHandler(ws, "q1")
+
+    handler.batch(b"batch1")
+    handler.batch(b"batch2")
+    handler.done()
+
+    assert handler.streaming
+    assert ws.messages == [
+        {"id": "q1", "type": "batch"},
+        b"batch1",
+        {"id": "q1", "type": "batch"},
+        b"batch2",
+        {"id": "q1"},
+    ]
+
+
+def test_socket_handler_without_id():
+    ws = FakeSocket()
+    handler = SocketHandler(ws)
+
+    handler.json('[{"a":1}]')
+    handler.arrow(b"arrow")
+
+    assert ws.messages == ['[{"a":1}]', b"arrow"]
+
+
+def test_live_tables_notify():
+    con = duckdb.connect()
+    con.execute("CREATE TABLE t AS SELECT range AS a FROM range(3)")
+    con.execute("CREATE TABLE u (a INTEGER)")
+    live = LiveTables()
+    ws = FakeSocket()
+    live.subscribe(ws, ["T"])
+
+    for sql in ["INSERT INTO t VALUES (3)", "INSERT INTO u VALUES (1)", "DELETE FROM t WHERE a = 0"]:
+        handle_query(DeferredHandler(), con, {}, {"type": "exec", "sql": sql}, QueryTracker(con), live)
+    live.flush()
+
+    assert ws.messages == [
+        {"type": "notify", "table": "t", "rowid": 2},
+        {"type": "notify", "table": "t", "rowid": None},
+    ]
+
+    live.unsubscribe(ws)
+    assert live.subscribers == {}
+
+
+def test_live_tables_notify_qualified():
+    con = duckdb.connect()
+    con.execute('CREATE SCHEMA s; CREATE TABLE s."My Table" AS SELECT range AS a FROM range(3)')
+    con.execute('CREATE TABLE "My Table" (a INTEGER)')
+    live = LiveTables()
+    ws = FakeSocket()
+    live.subscribe(ws, ['s."My Table"'])
+
+    sql = 'INSERT INTO s."My Table" VALUES (3)'
+    handle_query(DeferredHandler(), con, {}, {"type": "exec", "sql": sql}, QueryTracker(con), live)
+    live.flush()
+
+    assert ws.messages == [{"type": "notify", "table": "my table", "rowid": 2}]
@@ -10,8 +10,9 @@ export function cacheKey(hashable, type) {
 }
 
 class CacheEntry {
-  constructor(data, ttl = DEFAULT_TTL) {
+  constructor(data, ttl = DEFAULT_TTL, tables = null) {
     this.data = data;
+    this.tables = tables;
     this.touch(ttl);
   }
   touch(ttl = DEFAULT_TTL) {
@@ -45,12 +46,34 @@ export class Cache {
     return deleted;
   }
 
+  clear() {
+    for (const key of Array.from(this.cache.keys())) {
+      this.delete(key);
+    }
+  }
+
+  /**
+   * Delete entries whose queries read any of the given tables.
+   * @param {string[]} tables The normalized names of modified tables.
+   * @param {object} [options] Invalidation options.
+   * @param {boolean} [options.unknown=true] Whether to delete entries whose
+   *  tables are unknown, such as entries read from disk.
+   */
+  invalidate(tables, { unknown = true } = {}) {
+    const names = new Set(tables);
+    for (const [key, entry] of Array.from(this.cache)) {
+      if (entry.tables ? entry.tables.some(t => names.has(t)) : unknown) {
+        this.delete(key);
+      }
+    }
+  }
+
   get(key) {
     return this.cache.get(key)?.touch(this.ttl).data;
   }
 
-  set(key, data, { persist = false, ttl = this.ttl } = {}) {
-    const entry = new CacheEntry(data, persist ? Infinity : ttl);
+  set(key, data, { persist = false, ttl = this.ttl, tables = null } = {}) {
+    const entry = new CacheEntry(data, persist ? Infinity : ttl, tables);
     this.cache.set(key, entry);
     if (persist) writeEntry(this.dir, key, entry);
     if (this.shouldEvict()) setTimeout(() => this.evict());
@@ -2,12 +2,16 @@ import http from 'node:http';
 import path from 'node:path';
 import url from 'node:url';
 import { WebSocketServer } from 'ws';
+import { isIndexTable, sqlTables, tableMutations, tableName } from '@uwdata/mosaic-sql';
 import { Cache, cacheKey } from './Cache.js';
 import { createBundle, loadBundle } from './load/bundle.js';
 
 const CACHE_DIR = '.mosaic/cache';
 const BUNDLE_DIR = '.mosaic/bundle';
 
+// statements that manage transactions, which can not be nested
+const TRANSACTION = /\b(?:BEGIN|COMMIT|ROLLBACK|ABORT)\b/i;
+
 export function dataServer(db, {
   cache = true,
   rest = true,
@@ -15,9 +19,10 @@ export function dataServer(db, {
   port = 3000
 } = {}) {
   const queryCache = cache ? new Cache({ dir: CACHE_DIR }) : null;
-  const handleQuery = queryHandler(db, queryCache);
+  const live = socket ? liveTables() : null;
+  const handleQuery = queryHandler(db, queryCache, live);
   const app = createHTTPServer(handleQuery, rest);
-  if (socket) createSocketServer(app, handleQuery);
+  if (socket) createSocketServer(app, handleQuery, live);
 
   app.listen(port);
   console.log(`Data server running on port ${port}`);
@@ -59,16 +64,16 @@ function createHTTPServer(handleQuery, rest) {
   });
 }
 
-function createSocketServer(server, handleQuery) {
+function createSocketServer(server, handleQuery, live) {
   const wss = new WebSocketServer({ server });
 
   wss.on('connection', socket => {
-    const res = socketResponse(socket);
-    socket.on('message', data => handleQuery(res, data));
+    socket.on('message', data => handleQuery(socketResponse(socket), data));
+    socket.on('close', () => live?.unsubscribe(socket));
   });
 }
 
-export function queryHandler(db, queryCache) {
+export function queryHandler(db, queryCache, live) {
 
   // retrieve query result
   async function retrieve(query, get) {
@@ -81,7 +86,9 @@ export function queryHandler(db, queryCache) {
     } else {
       result = await get(sql);
       if (persist) {
-        queryCache?.set(key, result, { persist });
+        // record the tables read, to invalidate the entry upon modification
+        const tables = sqlTables(sql).map(parts => parts[parts.length - 1]);
+        queryCache?.set(key, result, { persist, tables });
       }
     }
 
@@ -101,17 +108,32 @@ export function queryHandler(db, queryCache) {
       return;
     }
 
+    // tag socket responses with the request id, if provided
+    res.id = query.id;
+
     try {
       const { sql, type = 'json' } = query;
       console.log(`> ${type.toUpperCase()}${sql ? ' ' + sql : ''}`);
 
       // process query and return result
       switch (type) {
-        case 'exec':
+        case 'exec': {
           // Execute query with no return value
-          await db.exec(sql);
+          const tables = live?.watched(sql);
+          let rowids = null;
+          if (tables?.size) rowids = await live.execute(db, sql, tables);
+          else await db.exec(sql);
+          // evict cached results that read modified tables, results with
+          // unknown tables are kept if only index tables are modified
+          const mutated = Array.from(tableMutations(sql).keys());
+          if (mutated.length) {
+            const unknown = !mutated.every(isIndexTable);
+            queryCache?.invalidate(mutated, { unknown });
+          }
           res.done();
+          if (tables?.size) live.notify(tables, rowids);
           break;
+        }
         case 'arrow':
           // Apache Arrow response format
           res.arrow(await retrieve(query, sql => db.arrowBuffer(sql)));
@@ -128,6 +150,19 @@ export function queryHandler(db, queryCache) {
           );
           res.done();
           break;
+        case 'cancel':
+          // the DuckDB Node.js API can not interrupt a running query, so
+          // cancel requests are ignored and queries run to completion
+          // socket requests are not answered, rest requests are acknowledged
+          if (!res.socket) res.done();
+          break;
+        case 'subscribe':
+        case 'unsubscribe':
+          // (un)subscribe the socket to table modification notifications
+          // subscription commands do not receive a response over the socket
+          if (res.socket) live?.[type](res.ws, query.tables ?? []);
+          else res.done();
+          break;
         case 'load-bundle':
           // Load a named bundle of precomputed resources
           await loadBundle(db, queryCache, path.resolve(BUNDLE_DIR, query.name));
@@ -144,6 +179,96 @@ export function queryHandler(db, queryCache) {
   };
 }
 
+/**
+ * Track WebSocket subscriptions to table modifications. Subscribers are
+ * notified after an exec query modifies a subscribed table. If the query
+ * only appended rows, the notification includes the largest row id prior
+ * to the append, such that subscribers can query just the appended rows.
+ */
+export function liveTables() {
+  const subscribers = new Map;
+  // mutations of watched tables run one at a time
+  let pending = Promise.resolve();
+
+  return {
+    subscribers,
+    subscribe(ws, tables) {
+      for (const table of tables) {
+        const name = tableName(table);
+        if (!subscribers.has(name)) subscribers.set(name, new Set);
+        subscribers.get(name).add(ws);
+      }
+    },
+    unsubscribe(ws, tables) {
+      const names = tables ? tables.map(tableName) : [...subscribers.keys()];
+      for (const name of names) {
+        const sockets = subscribers.get(name);
+        sockets?.delete(ws);
+        if (!sockets?.size) subscribers.delete(name);
+      }
+    },
+    watched(sql) {
+      const tables = tableMutations(sql);
+      for (const name of tables.keys()) {
+        if (!subscribers.has(name)) tables.delete(name);
+      }
+      return tables;
+    },
+    async rowids(db, tables) {
+      const rowids = new Map;
+      for (const [name, append] of tables) {
+        if (!append) continue;
+        // views and missing tables have no row ids
+        const found = await db.query(
+          `SELECT 1 FROM duckdb_tables() WHERE lower(table_name) = '${name.replaceAll("'", "''")}'`
+        );
+        if (!found.length) continue;
+        const [{ rowid }] = await db.query(
+          `SELECT MAX(rowid) AS rowid FROM "${name.replaceAll('"', '""')}"`
+        );
+        rowids.set(name, rowid == null ? -1 : Number(rowid));
+      }
+      return rowids;
+    },
+    execute(db, sql, tables) {
+      // read row ids in the same transaction as the mutation, so that they
+      // describe the table state the mutation applies to
+      const run = async () => {
+        if (![...tables.values()].some(x => x) || TRANSACTION.test(sql)) {
+          await db.exec(sql);
+          return new Map;
+        }
+        try {
+          await db.exec('BEGIN TRANSACTION');
+        } catch (err) { // eslint-disable-line no-unused-vars
+          // already within a transaction opened by an earlier statement
+          await db.exec(sql);
+          return new Map;
+        }
+        try {
+          const rowids = await this.rowids(db, tables);
+          await db.exec(sql);
+          await db.exec('COMMIT');
+          return rowids;
+        } catch (err) {
+          await db.exec('ROLLBACK').catch(() => {});
+          throw err;
+        }
+      };
+      const result = pending.then(run);
+      pending = result.catch(() => {});
+      return result;
+    },
+    notify(tables, rowids) {
+      for (const name of tables.keys()) {
+        const rowid = rowids.get(name) ?? null;
+        const message = JSON.stringify({ type: 'notify', table: name, rowid });
+        subscribers.get(name)?.forEach(ws => ws.send(message));
+      }
+    }
+  };
+}
+
 function httpResponse(res) {
   return {
     arrow(data) {
@@ -166,23 +291,38 @@ function httpResponse(res) {
   }
 }
 
+/**
+ * Create a response handler for a WebSocket request. If the response *id*
+ * property is set, responses are JSON messages tagged with the request id,
+ * with Arrow data sent as a tagged header followed by a binary message.
+ * @param {import('ws').WebSocket} ws The WebSocket.
+ */
 export function socketResponse(ws) {
   const STRING = { binary: false, fin: true };
   const BINARY = { binary: true, fin: true };
 
   return {
+    id: undefined,
+    socket: true,
+    ws,
+    send(message) {
+      const { id } = this;
+      ws.send(JSON.stringify(id == null ? message : { id, ...message }), STRING);
+    },
     arrow(data) {
+      if (this.id != null) this.send({ type: 'arrow' });
       ws.send(data, BINARY);
     },
     json(data) {
-      ws.send(JSON.stringify(data), STRING);
+      if (this.id != null) this.send({ result: data });
+      else ws.send(JSON.stringify(data), STRING);
     },
     done() {
-      this.json({});
+      this.send({});
     },
     error(err) {
       console.error(err);
-      this.json({ error: String(err) });
+      this.send({ error: String(err) });
     }
   };
 }
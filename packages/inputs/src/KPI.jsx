@@ -0,0 +1,197 @@
+import {MosaicClient} from '@uwdata/mosaic-core';
+import {Query, avg, count, max, median, min, sum} from '@uwdata/mosaic-sql';
+import {input} from './input.js';
+import * as React from "react";
+import ReactDOM from 'react-dom/client';
+import {Card, Space, Typography} from 'antd';
+import {formatAuto, formatShare} from './util/format.js';
+import {sparklineData, sparklinePoints, toNumber} from './util/sparkline.js';
+
+let _id = 0;
+
+const AGGREGATES = {avg, count, max, median, min, sum};
+
+// aggregates whose filtered values are a share of the unfiltered total
+const ADDITIVE = new Set(['count', 'sum']);
+
+export const kpi = options => input(KPI, options);
+
+const Sparkline = ({data, width = 120, height = 32}) => {
+    const points = sparklinePoints(data, width, height);
+    if (points == null) return null;
+    return (
+        <svg width={width} height={height} style={{display: 'block'}}>
+            <polyline points={points} fill='none' stroke='currentColor' strokeWidth={1.5}/>
+        </svg>
+    );
+};
+
+const ShapeletsKPI = ({label, value, comparison, sparkline}) => {
+    return (
+        <Card size='small' style={{display: 'inline-block', minWidth: 150, marginRight: '8px'}}>
+            <Space size={0} direction='vertical'>
+                <Typography.Text type='secondary'>{label}</Typography.Text>
+                <Typography.Text strong style={{fontSize: 24}}>{value}</Typography.Text>
+                {comparison && <Typography.Text type='secondary'>{comparison}</Typography.Text>}
+                {sparkline && <Sparkline data={sparkline}/>}
+            </Space>
+        </Card>
+    );
+};
+
+export class KPI extends MosaicClient {
+    /**
+     * Create a new KPI (key performance indicator) summary card, showing a
+     * scalar aggregate value filtered by a selection.
+     * @param {object} [options] Options object
+     * @param {HTMLElement} [options.element] The parent DOM element in which to
+     *  place the summary elements. If undefined, a new `div` element is created.
+     * @param {Selection} [options.filterBy] A selection to filter the database
+     *  table indicated by the *from* option.
+     * @param {string} options.from The name of a database table to summarize.
+     * @param {string} [options.column] The name of a database column to
+     *  aggregate. Not required for counts.
+     * @param {'count' | 'sum' | 'avg' | 'min' | 'max' | 'median'} [options.aggregate]
+     *  The aggregate function to apply. Defaults to `'count'`.
+     * @param {*} [options.value] An aggregate SQL expression to compute, such
+     *  as `sum('delay')`. If specified, overrides the *aggregate* and *column*
+     *  options.
+     * @param {string} [options.label] A text label for this summary.
+     * @param {(value: any) => string} [options.format] A format function for
+     *  aggregate values. Defaults to locale-aware automatic formatting.
+     * @param {boolean} [options.compare] A flag indicating if the filtered value
+     *  should be compared to the unfiltered total (default `true`). The
+     *  percentage of the total is shown for count and sum aggregates only.
+     * @param {*} [options.sparkline] A database column name or SQL expression
+     *  to group by in order to show a sparkline of the aggregate value, such as
+     *  a date column. The sparkline is filtered by the same selection.
+     */
+    constructor({
+                    element,
+                    filterBy,
+                    from,
+                    column,
+                    aggregate = 'count',
+                    value,
+                    label,
+                    format = formatAuto,
+                    compare = true,
+                    sparkline
+                } = {}) {
+        super(filterBy);
+        if (!value && !AGGREGATES[aggregate]) {
+            throw new Error(`Unrecognized aggregate: ${aggregate}`);
+        }
+        this.id = 'kpi_' + (++_id);
+        this.from = from;
+        this.column = column;
+        this.aggregate = value ?? AGGREGATES[aggregate](column);
+        this.label = label ?? (column ? `${aggregate} ${column}` : aggregate);
+        this.format = format;
+        this.compare = compare;
+        this.additive = !value && ADDITIVE.has(aggregate);
+
+        this.value = undefined;
+        this.total = undefined;
+        this.sparkline = sparkline != null
+            ? new KPISparkline(this, sparkline, filterBy)
+            : null;
+
+        this.element = element ?? document.createElement('div');
+        this.root = ReactDOM.createRoot(this.element);
+    }
+
+    /**
+     * Set this client's connected coordinator. The sparkline client, if any,
+     * is connected to and disconnected from the same coordinator.
+     */
+    set coordinator(mc) {
+        const prev = super.coordinator;
+        super.coordinator = mc;
+        if (this.sparkline && mc !== prev) {
+            prev?.disconnect(this.sparkline);
+            mc?.connect(this.sparkline);
+        }
+    }
+
+    /**
+     * Return this client's connected coordinator.
+     */
+    get coordinator() {
+        return super.coordinator;
+    }
+
+    query(filter = []) {
+        return Query
+            .from(this.from)
+            .select({value: this.aggregate})
+            .where(filter);
+    }
+
+    queryPending() {
+        // request the unfiltered total once, for comparisons
+        if (this.compare && this.total === undefined && this.coordinator) {
+            this.total = null;
+            this.coordinator
+                .query(this.query(), {type: 'json'})
+                .then(data => {
+                    this.total = toNumber(Array.from(data)[0]?.value) ?? null;
+                    this.update();
+                })
+                .catch(() => {});
+        }
+        return this;
+    }
+
+    queryResult(data) {
+        this.value = toNumber(Array.from(data)[0]?.value);
+        return this;
+    }
+
+    update() {
+        const {value, total, format} = this;
+        const comparison = this.compare && total != null && value !== undefined
+            ? formatShare(value, total, format, this.additive)
+            : null;
+        this.root.render(<ShapeletsKPI
+            key={this.id}
+            label={this.label}
+            value={value === undefined ? '' : format(value)}
+            comparison={comparison}
+            sparkline={this.sparkline?.data}
+        />);
+        return this;
+    }
+}
+
+/**
+ * Client that queries aggregate values grouped by a sparkline dimension.
+ */
+class KPISparkline extends MosaicClient {
+    constructor(kpi, x, filterBy) {
+        super(filterBy);
+        this.kpi = kpi;
+        this.x = x;
+        this.data = null;
+    }
+
+    query(filter = []) {
+        const {from, aggregate} = this.kpi;
+        return Query
+            .from(from)
+            .select({x: this.x, y: aggregate})
+            .groupby('x')
+            .orderby('x')
+            .where(filter);
+    }
+
+    queryResult(data) {
+        this.data = sparklineData(data);
+        return this;
+    }
+
+    update() {
+        this.kpi.update();
+        return this;
+    }
+}
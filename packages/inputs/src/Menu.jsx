@@ -9,11 +9,16 @@ const isObject = v => {
     return v && typeof v === 'object' && !Array.isArray(v);
 };
 
+let _id = 0;
+
 export const menu = options => input(Menu, options);
 
 const ShapeletsSelect = ({label, options, value, onAction}) => {
     const [componentValue, setComponentValue] = React.useState(value);
 
+    // follow value updates, such as restored selection state
+    React.useEffect(() => setComponentValue(value), [value]);
+
     const handleChange = (selectedValue) => {
         onAction(selectedValue);
         setComponentValue(selectedValue)
@@ -61,6 +66,8 @@ export class Menu extends MosaicClient {
      *  to pull menu options. The unique column values are used as menu options.
      *  Used in conjunction with the *from* option.
      * @param {string} [options.label] A text label for this input.
+     * @param {string} [options.id] An identifier for this input, used to
+     *  associate restored selection clauses with this input.
      */
     constructor({
                     element,
@@ -72,9 +79,11 @@ export class Menu extends MosaicClient {
                     options,
                     value,
                     field = column,
-                    as
+                    as,
+                    id
                 } = {}) {
         super(filterBy);
+        this.id = id ?? 'menu_' + (++_id);
         this.from = from;
         this.column = column;
         this.format = format;
@@ -130,6 +139,17 @@ export class Menu extends MosaicClient {
         this.selectedValue(this.from ? 0 : -1);
     }
 
+    /**
+     * Update the menu to match a restored selection clause, without
+     * publishing a new clause.
+     * @param {import('@uwdata/mosaic-core').SelectionClause} clause The
+     *  restored clause, with a null value if the selection was cleared.
+     */
+    restore(clause) {
+        this.selectedValue(clause.value ?? '');
+        if (this.data) this.update();
+    }
+
     publish(value) {
         const {selection, field} = this;
         if (isSelection(selection)) {
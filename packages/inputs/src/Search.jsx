@@ -14,6 +14,9 @@ const ShapeletsSearch = ({key, label, options, value, onAction, onSearch}) => {
     const [selectOptions, setSelectOptions] = React.useState([...options]);
     const [componentValue, setComponentValue] = React.useState(value);
 
+    // follow value updates, such as restored selection state
+    React.useEffect(() => setComponentValue(value), [value]);
+
     const handleSearch = (newValue) => {
         const newList = newValue ? onSearch(newValue) : [...options]
         setSelectOptions(newList)
@@ -76,6 +79,8 @@ export class Search extends MosaicClient {
      *  to pull valid search results. The unique column values are used as search
      *  autocomplete values. Used in conjunction with the *from* option.
      * @param {string} [options.label] A text label for this input.
+     * @param {string} [options.id] An identifier for this input, used to
+     *  associate restored selection clauses with this input.
      */
     constructor({
                     element,
@@ -85,10 +90,11 @@ export class Search extends MosaicClient {
                     label,
                     type = 'contains',
                     field = column,
-                    as
+                    as,
+                    id
                 } = {}) {
         super(filterBy);
-        this.id = 'search_' + (++_id);
+        this.id = id ?? 'search_' + (++_id);
         this.type = type;
         this.from = from;
         this.column = column;
@@ -118,6 +124,17 @@ export class Search extends MosaicClient {
         this.value = '';
     }
 
+    /**
+     * Update the search box to match a restored selection clause, without
+     * publishing a new clause.
+     * @param {import('@uwdata/mosaic-core').SelectionClause} clause The
+     *  restored clause, with a null value if the selection was cleared.
+     */
+    restore(clause) {
+        this.value = clause.value ?? '';
+        this.update();
+    }
+
     publish(value) {
         const {selection, field, type} = this;
         if (isSelection(selection)) {
@@ -12,6 +12,9 @@ export const slider = options => input(Slider, options);
 const ShapeletsSlider = ({range, key, value, label, width, min, max, step, onAction}) => {
     const [componentValue, setComponentValue] = React.useState(value);
 
+    // follow value updates, such as restored selection state
+    React.useEffect(() => setComponentValue(value), [value]);
+
     const handleChange = (value) => {
         onAction(value);
         setComponentValue(value)
@@ -77,6 +80,8 @@ export class Slider extends MosaicClient {
      *  determine the slider range. Used in conjunction with the *from* option.
      *  The minimum and maximum values of the column determine the slider range.
      * @param {string} [options.label] A text label for this input.
+     * @param {string} [options.id] An identifier for this input, used to
+     *  associate restored selection clauses with this input.
      * @param {number} [options.width] The width of the slider in screen pixels.
      */
     constructor({
@@ -92,10 +97,11 @@ export class Slider extends MosaicClient {
                     value = as?.value,
                     select = 'point',
                     field = column,
-                    width
+                    width,
+                    id
                 } = {}) {
         super(filterBy);
-        this.id = 'slider_' + (++_id);
+        this.id = id ?? 'slider_' + (++_id);
         this.from = from;
         this.column = column || 'value';
         this.selection = as;
@@ -108,30 +114,47 @@ export class Slider extends MosaicClient {
         // New this
         this.value = value
         this.range = this.selectionType == 'interval'
+        this.label = label;
+        this.width = width;
 
         this.element = element ?? document.createElement('div');
         this.root = ReactDOM.createRoot(this.element);
+        this.render();
+
+        // track param updates
+        if (this.selection && !isSelection(this.selection)) {
+            this.selection.addEventListener('value', value => {
+                if (value !== +this.value) {
+                    this.value = value;
+                }
+            });
+        }
+    }
 
+    render() {
         this.root.render(<ShapeletsSlider
             range={this.range}
             key={this.id}
             value={this.value}
-            label={label}
-            width={`${width??100}px`}
+            label={this.label}
+            width={`${this.width??100}px`}
             min={this.min}
             max={this.max}
             step={this.step}
             onAction={this.handleAction.bind(this)}
         />);
+    }
 
-        // track param updates
-        if (this.selection && !isSelection(this.selection)) {
-            this.selection.addEventListener('value', value => {
-                if (value !== +this.value) {
-                    this.value = value;
-                }
-            });
-        }
+    /**
+     * Update the slider to match a restored selection clause, without
+     * publishing a new clause. Interval clause values are shown as a
+     * slider range.
+     * @param {import('@uwdata/mosaic-core').SelectionClause} clause The
+     *  restored clause, with a null value if the selection was cleared.
+     */
+    restore(clause) {
+        this.value = clause.value ?? undefined;
+        this.render();
     }
 
     query(filter = []) {
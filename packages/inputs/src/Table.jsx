@@ -1,119 +1,188 @@
-import {coordinator, MosaicClient, toDataColumns} from '@uwdata/mosaic-core';
-import {column, desc, Query} from '@uwdata/mosaic-sql';
+import {
+    clauseInterval, clauseMatch, clausePoints, isSelection, MosaicClient, toDataColumns
+} from '@uwdata/mosaic-core';
+import {column, count, desc, gte, lte, Query} from '@uwdata/mosaic-sql';
 import {input} from './input.js';
+import {scrollToRow, viewRange} from './util/virtual-scroll.js';
 import * as React from "react";
 import ReactDOM from 'react-dom/client';
-import {Space, Table as AntTable, Typography} from 'antd';
+import {Button, Checkbox, Input, InputNumber, Space, Table as AntTable, Typography} from 'antd';
 
 let _id = -1;
 
+// Maximum number of cached row batches, beyond which the batches farthest
+// from the current view are evicted.
+const MAX_CACHED_BATCHES = 64;
+
+// Maximum number of rows in a selected range. Each selected row adds a
+// predicate term to the published clause, so larger ranges are truncated.
+const MAX_RANGE_ROWS = 1000;
+
+// Supported row selection modes.
+const SELECT_MODES = new Set(['hover', 'single', 'multiple', 'checkbox']);
+
+// Outline style for the current (keyboard-focused) row.
+const CURRENT_ROW_STYLE = {outline: '2px solid #1677ff', outlineOffset: -2};
+
+export const table = options => input(Table, options);
+
 /**
- * Creates a future object that encapsulates a Promise along with its associated resolve and reject functions.
- *
- * This function facilitates the integration of a Promise into the Shapelets Table, ensuring that the values
- * for sorting and scrolling are returned only after the Mosaic component has completed its queries and updated
- * the component accordingly. Once the update is complete, the Promise is resolved, providing the necessary data.
- *
- * @returns {Object} An object containing:
- *   - {Promise} promise - The Promise that can be resolved or rejected.
- *   - {Function} resolve - A function to resolve the Promise.
- *   - {Function} reject - A function to reject the Promise.
- *
+ * Table body row that renders spacer rows with a given height, which stand
+ * in for all rows above and below the current view.
  */
-function createFuture() {
-    let resolveFn, rejectFn;
-    const promise = new Promise((resolve, reject) => {
-        resolveFn = resolve;
-        rejectFn = reject;
-    });
-    return {promise, resolve: resolveFn, reject: rejectFn};
-}
+const TableRow = ({spacer, span, children, ...props}) => {
+    return spacer != null
+        ? <tr style={{height: spacer}}><td colSpan={span} style={{padding: 0, border: 0}}/></tr>
+        : <tr {...props}>{children}</tr>;
+};
 
-export const table = options => input(Table, options);
+/**
+ * Return a string key for an array of row key values.
+ * @param {any[]} values The key column values of a row.
+ */
+function keyString(values) {
+    return JSON.stringify(values, (_, v) => typeof v === 'bigint' ? `${v}n` : v);
+}
 
 /**
- * ShapeletsTable enhances the visualization of Mosaic Table by leveraging the Mosaic engine while rendering an AntD table.
- * This component improves upon the standard table by incorporating features such as asynchronous data loading,
- * infinite scrolling, and sorting capabilities.
- *
- * @param {string} key - A unique identifier for the component.
- * @param {Array} columns - An array of column definitions that specify the structure and content of the table.
- * @param {Array} data - The initial dataset used to populate the table, formatted to comply with AntD specifications.
- * @param {Function} onAction - A function that retrieves additional data when invoked, particularly useful for infinite scrolling.
- *                              The Mosaic component manages the offset for fetching the next batch of data.
- * @param {boolean} loaded - A flag indicating whether all data for the table has been loaded, which determines if further scrolling is necessary.
- * @param {number} maxWidth - The maximum allowable width of the table.
- * @param {number} maxHeight - The maximum allowable height of the table.
- * @param {Function} sortFn - A function responsible for sorting the table data based on specified criteria.
- * @param {Promise} promise - A promise that resolves to the initial dataset for the table.
+ * ShapeletsTable renders a windowed view over a Mosaic Table using an AntD table. Only the rows in view are
+ * rendered, with spacer rows standing in for all other rows, such that any row range can be scrolled to directly.
  *
+ * @param {Array} columns - An array of AntD column definitions, including sort and filter state.
+ * @param {number} total - The total number of table rows.
+ * @param {Function} getRow - A function that returns the row object for a row index, or a placeholder row if
+ *                            the row has not yet been loaded.
+ * @param {Function} onRange - A function invoked with the start and end row indices in view, which requests any
+ *                             rows that have not yet been loaded.
+ * @param {Function} onChange - A function invoked with AntD filter and sorter state upon header interactions.
+ * @param {number} rowHeight - The height of a table row in pixels.
+ * @param {number} height - The height of the table body in pixels.
+ * @param {string} maxWidth - The maximum allowable width of the table.
+ * @param {number} version - A counter that changes whenever loaded table data changes.
+ * @param {string} select - The row selection mode, or null if rows are not selectable.
+ * @param {Function} isSelected - A function that indicates if a row object is selected.
+ * @param {number} selectedCount - The number of selected rows.
+ * @param {number} current - The index of the current (keyboard-focused) row, or -1 if none.
+ * @param {Function} onSelect - A function invoked with the row index and event upon row clicks.
+ * @param {Function} onHover - A function invoked with the row index upon row hover, or -1 upon leaving the table.
+ * @param {Function} onKeyDown - A function invoked with keyboard events for the focused table.
  */
-const ShapeletsTable = ({key, columns, data, onAction, loaded, maxWidth, maxHeight, sortFn, promise}) => {
-    const [tableData, setTableData] = React.useState(data ? [...data] : []);
-    const [loading, setLoading] = React.useState(false);
+const ShapeletsTable = ({
+                            columns, total, getRow, onRange, onChange, rowHeight, height, maxWidth, version,
+                            select, isSelected, selectedCount, current, onSelect, onHover, onKeyDown
+                        }) => {
+    const [scrollTop, setScrollTop] = React.useState(0);
+    const wrapper = React.useRef(null);
+    const {start, end, top, bottom} = viewRange(scrollTop, total, rowHeight, height);
 
     React.useEffect(() => {
-        async function loadData() {
-            setLoading(true)
-            setTableData(await promise)
-            setLoading(false)
-        }
+        onRange(start, end);
+    }, [start, end, version]);
 
-        loadData()
-    }, [promise]);
+    // scroll the current row into view
+    React.useEffect(() => {
+        const body = wrapper.current?.querySelector('.ant-table-body');
+        if (!body || current < 0 || current >= total) return;
+        const next = scrollToRow(body.scrollTop, current, total, rowHeight, height);
+        if (next !== body.scrollTop) body.scrollTop = next;
+    }, [current]);
+
+    const dataSource = [];
+    if (top > 0) dataSource.push({key: '__top', __spacer: top});
+    for (let i = start; i < end; ++i) dataSource.push(getRow(i));
+    if (bottom > 0) dataSource.push({key: '__bottom', __spacer: bottom});
 
     const handleOnScroll = (e) => {
-        // Detect when the user has scrolled near the bottom of the table and to trigger a request for more data if the
-        // table is not fully loaded.
-        if (!loaded) {
-            const {scrollTop, clientHeight, scrollHeight} = e.target;
-            // Checks if the user has scrolled to within 95% of the total scrollable height
-            if (scrollTop + clientHeight >= scrollHeight * 0.95 && !loading) {
-                setLoading(true)
-                const moreData = onAction();
-                moreData.then((x) => {
-                    setTableData(prevTableData => [...prevTableData, ...x])
-                    setLoading(false)
-                })
-            }
-        }
+        setScrollTop(e.target.scrollTop);
     };
 
-    const handleSorting = (pagination, filters, sorter) => {
-        const {field, order} = sorter;
-        setLoading(true)
-        const sortedData = sortFn(field, order)
-        sortedData.then((x) => {
-            setTableData(x);
-            setLoading(false)
-        })
+    const handleChange = (pagination, filters, sorter, {action}) => {
+        onChange(action, filters, sorter);
+    };
+
+    const rowProps = row => {
+        if (row.__spacer != null) return {spacer: row.__spacer, span: columns.length};
+        if (!select) return {};
+        return {
+            className: isSelected(row) ? 'ant-table-row-selected' : undefined,
+            style: row.key === current ? CURRENT_ROW_STYLE : undefined,
+            onClick: e => onSelect(row.key, e),
+            // prevent text selection upon shift-click
+            onMouseDown: e => { if (e.shiftKey) e.preventDefault(); },
+            onMouseEnter: () => onHover(row.key)
+        };
     };
 
     return (
         <Typography>
-                <Space size='small' direction="horizontal" style={{marginRight: '8px', rowGap: 0}}>
+            <Space size='small' direction="vertical" style={{marginRight: '8px', rowGap: 0}}>
+                <div
+                    ref={wrapper}
+                    tabIndex={select && select !== 'hover' ? 0 : undefined}
+                    onKeyDown={e => {
+                        // ignore key events from header filter inputs
+                        if (select && e.target === e.currentTarget) onKeyDown(e);
+                    }}
+                    onMouseLeave={select ? () => onHover(-1) : undefined}
+                    style={{outline: 'none'}}>
                     <AntTable
-                        key={key}
                         bordered={true}
                         showHeader={true}
                         size={'small'}
                         columns={columns}
-                        dataSource={tableData}
+                        dataSource={dataSource}
+                        components={{body: {row: TableRow}}}
+                        onRow={rowProps}
                         scroll={{
                             x: 'max-content',
-                            y: 50 * 5,
+                            y: height,
                         }}
                         pagination={false}
                         onScroll={handleOnScroll}
-                        style={{
-                            maxWidth: maxWidth,
-                            maxHeight: maxHeight,
-                        }}
+                        style={{maxWidth}}
                         tableLayout='fixed'
-                        onChange={handleSorting}
-                        showSorterTooltip={{ target: 'sorter-icon' }}/>
+                        onChange={handleChange}
+                        showSorterTooltip={{target: 'sorter-icon'}}/>
+                </div>
+                <Typography.Text type='secondary'>
+                    {total.toLocaleString()} rows
+                    {selectedCount > 0 && `, ${selectedCount.toLocaleString()} selected`}
+                </Typography.Text>
             </Space>
-        </Typography>        
+        </Typography>
+    );
+};
+
+/**
+ * Header filter dropdown for text search over string-valued columns.
+ */
+const TextFilter = ({selectedKeys, setSelectedKeys, confirm, clearFilters}) => (
+    <Space size='small' style={{padding: 8}}>
+        <Input
+            placeholder='Contains'
+            value={selectedKeys[0]}
+            onChange={e => setSelectedKeys(e.target.value ? [e.target.value] : [])}
+            onPressEnter={() => confirm()}
+            style={{width: 150}}
+        />
+        <Button type='primary' size='small' onClick={() => confirm()}>Filter</Button>
+        <Button size='small' onClick={() => { clearFilters(); confirm(); }}>Reset</Button>
+    </Space>
+);
+
+/**
+ * Header filter dropdown for ranges over number-valued columns.
+ */
+const RangeFilter = ({selectedKeys, setSelectedKeys, confirm, clearFilters}) => {
+    const [lo = null, hi = null] = selectedKeys;
+    const set = (a, b) => setSelectedKeys(a == null && b == null ? [] : [a, b]);
+    return (
+        <Space size='small' style={{padding: 8}}>
+            <InputNumber placeholder='Min' value={lo} onChange={v => set(v, hi)} style={{width: 90}}/>
+            <InputNumber placeholder='Max' value={hi} onChange={v => set(lo, v)} style={{width: 90}}/>
+            <Button type='primary' size='small' onClick={() => confirm()}>Filter</Button>
+            <Button size='small' onClick={() => { clearFilters(); confirm(); }}>Reset</Button>
+        </Space>
     );
 };
 
@@ -125,173 +194,740 @@ export class Table extends MosaicClient {
     constructor({
                     element,
                     filterBy,
+                    filterAs = filterBy,
+                    filters = false,
                     from,
                     columns = ['*'],
                     align = {},
-                    format,
+                    format = {},
                     width,
                     maxWidth,
                     height = 500,
                     rowBatch = 25,
-                    as
+                    rowHeight = 39,
+                    as,
+                    select = 'single',
+                    primaryKey,
+                    id
                 } = {}) {
         super(filterBy);
-        this.id = `table-${++_id}`;
+        if (select && !SELECT_MODES.has(select)) {
+            throw new Error(`Unrecognized select mode: ${select}`);
+        }
+        this.id = id ?? `table-${++_id}`;
         this.from = from;
         this.columns = columns;
         this.format = format;
         this.align = align;
         this.widths = typeof width === 'object' ? width : {};
 
-        this.offset = 0;
         this.limit = +rowBatch;
-        this.pending = false;
+        this.rowHeight = +rowHeight;
 
+        // row selection state, selected rows are tracked by key values
         this.selection = as;
+        this.select = isSelection(as) ? select : null;
+        this.primaryKey = primaryKey == null ? null : [primaryKey].flat();
+        this.selected = new Map;
+        this.anchor = -1;
         this.currentRow = -1;
+        if (this.select) {
+            // keep selected rows in sync with the output selection, for
+            // example upon selection resets or restored selection state
+            as.addEventListener('value', () => this.syncSelection());
+        }
+
+        // multi-column sort criteria, in order of precedence
+        this.sort = [];
+
+        // header filter state and per-column clause sources
+        this.filterAs = filters && isSelection(filterAs) ? filterAs : null;
+        this.filterValues = {};
+        this.filterSources = new Map;
+        if (this.filterAs && this.filterAs !== filterBy) {
+            // re-query upon header filter updates, as the coordinator
+            // only tracks the filterBy selection
+            this.filterAs.addEventListener('value', () => this.requestQuery());
+        }
 
-        this.sortColumn = null;
-        this.sortDesc = false;
+        // streamed row count, loaded row batches, and total row count
+        this.streamed = null;
+        this.frame = null;
+        this.cache = new Map;
+        this.requested = new Set;
+        this.generation = 0;
+        this.total = 0;
+        this.counted = false;
+        this.version = 0;
 
-        // New this
         this.columns_final = [];
-        this.data_final = [];
         this.maxWidth = maxWidth ? `${maxWidth}px` : '100%'
-        this.height = `${height}px`
+        this.height = height;
 
         this.element = element ?? document.createElement('div');
         this.root = ReactDOM.createRoot(this.element);
     }
 
-    requestData(offset = 0) {
-        this.offset = offset;
-        // Request next data batch
-        const query = this.query(this.filterBy?.predicate(this));
-        this.requestQuery(query);
-        // Prefetch subsequent data batch
-        coordinator().prefetch(query.clone().offset(offset + this.limit));
-    }
-
     fields() {
         return this.columns.map(name => column(this.from, name));
     }
 
     fieldInfo(info) {
         this.schema = info;
-        this.future = createFuture()
-        this.columns_final = info.map(item => {
-            const tpe = item.type;
-            return ({
-                title: item.column,
-                dataIndex: item.column,
-                key: item.column,
-                width: this.widths[item.column] || 100,
-                align: this.align[item.column] || tpe === 'number' ? 'right' : 'left',
-                // Set sorter so the icons are shown.
-                sorter: true
-            });
+        this.renderTable();
+        return this;
+    }
+
+    /**
+     * Return the AntD column definitions, including current sort and
+     * filter state.
+     */
+    antColumns() {
+        const {schema = [], sort, filterValues, filterAs} = this;
+        const columns = schema.map(({column: name, type}) => {
+            const s = sort.find(s => s.column === name);
+            const fmt = this.format[name];
+            return {
+                title: name,
+                dataIndex: name,
+                key: name,
+                width: this.widths[name] || 100,
+                align: this.align[name] || (type === 'number' ? 'right' : 'left'),
+                render: fmt ? (value => value == null ? '' : fmt(value)) : undefined,
+                // all columns share a sort priority, the table tracks precedence
+                sorter: {multiple: 1},
+                sortOrder: s ? (s.desc ? 'descend' : 'ascend') : null,
+                ...(filterAs ? headerFilter(type, filterValues[name]) : {})
+            };
         });
+        if (this.select === 'checkbox') {
+            // checkboxes only indicate state, row clicks toggle selection
+            columns.unshift({
+                key: '__select',
+                width: 40,
+                align: 'center',
+                render: (_, row) => row.__placeholder ? null : <Checkbox
+                    checked={this.isSelected(row)}
+                    style={{pointerEvents: 'none'}}/>
+            });
+        }
+        return columns;
+    }
+
+    renderTable() {
         this.root.render(<ShapeletsTable
             key={this.id}
-            columns={this.columns_final}
-            data={this.data_final}
-            loaded={this.loaded}
+            columns={this.antColumns()}
+            total={this.total}
+            getRow={this.getRow.bind(this)}
+            onRange={this.handleRange.bind(this)}
+            onChange={this.handleChange.bind(this)}
+            rowHeight={this.rowHeight}
+            height={this.height}
             maxWidth={this.maxWidth}
-            maxHeight={this.height}
-            onAction={this.handleAction.bind(this)}
-            sortFn={this.handleSorting.bind(this)}
-            promise={this.future.promise}
+            version={this.version}
+            select={this.select}
+            isSelected={this.isSelected.bind(this)}
+            selectedCount={this.selected.size}
+            current={this.select === 'hover' ? -1 : this.currentRow}
+            onSelect={this.handleRowClick.bind(this)}
+            onHover={this.handleHover.bind(this)}
+            onKeyDown={this.handleKey.bind(this)}
         />);
-        return this;
     }
 
+    /**
+     * Return a query for the first batch of table rows.
+     * @param {*} [filter] The filtering criteria to apply in the query.
+     */
     query(filter = []) {
-        const {from, limit, offset, schema, sortColumn, sortDesc} = this;
+        const {from, limit} = this;
         return Query.from(from)
-            .select(schema.map(s => s.column))
-            .where(filter)
-            .orderby(sortColumn ? (sortDesc ? desc(sortColumn) : sortColumn) : [])
+            .select(this.rowColumns())
+            .where(filter, this.headerPredicate())
+            .orderby(this.orderby())
             .limit(limit)
-            .offset(offset);
+            .offset(0);
     }
 
-    queryResult(data) {
-        if (!this.pending) {
-            // data is not from an internal request, so reset table
-            this.loaded = false;
-            this.data_final = [];
-            this.data = [];
-            this.offset = 0;
+    /**
+     * Return a query for the key column values of a range of table rows.
+     * @param {number} start The start row index (inclusive).
+     * @param {number} end The end row index (exclusive).
+     */
+    keyQuery(start, end) {
+        return Query.from(this.from)
+            .select(this.keyColumns())
+            .where(this.filterBy?.predicate(this), this.headerPredicate())
+            .orderby(this.orderby())
+            .limit(end - start)
+            .offset(start);
+    }
+
+    /**
+     * Return a query for the row batch with the given index.
+     * @param {number} index The batch index.
+     */
+    batchQuery(index) {
+        return this.query(this.filterBy?.predicate(this)).offset(index * this.limit);
+    }
+
+    /**
+     * Return a query for the total number of table rows.
+     */
+    countQuery() {
+        return Query.from(this.from)
+            .select({count: count()})
+            .where(this.filterBy?.predicate(this), this.headerPredicate());
+    }
+
+    /**
+     * Return the sort criteria as query order by expressions.
+     */
+    orderby() {
+        return this.sort.map(s => s.desc ? desc(s.column) : s.column);
+    }
+
+    /**
+     * Return the names of the key columns that identify selected rows.
+     * Defaults to all table columns if no primary key is specified.
+     */
+    keyColumns() {
+        return this.primaryKey ?? (this.schema ?? []).map(s => s.column);
+    }
+
+    /**
+     * Return the names of columns to query for table rows: the table
+     * columns followed by any key columns not already included.
+     */
+    rowColumns() {
+        const names = (this.schema ?? []).map(s => s.column);
+        return names.concat(this.keyColumns().filter(name => !names.includes(name)));
+    }
+
+    /**
+     * Return the header filter predicate, if header filter clauses are not
+     * already included in the filterBy selection predicate.
+     */
+    headerPredicate() {
+        const {filterAs, filterBy} = this;
+        return filterAs && filterAs !== filterBy ? filterAs.predicate(this) : [];
+    }
+
+    queryPending() {
+        // Streamed record batches of the next query reset the table
+        this.streamed = null;
+        return this;
+    }
+
+    queryResultBatch(batch) {
+        // Render streamed rows progressively when the table is reset. Rows
+        // of later batches are appended and rendered once per frame.
+        const data = toDataColumns(batch);
+        if (this.streamed == null) {
+            this.resetRows([data]);
+            this.streamed = data.numRows;
+        } else {
+            this.appendRows(data, this.streamed);
+            this.streamed += data.numRows;
         }
-        this.data.push(toDataColumns(data));
+        this.requestRender();
         return this;
     }
 
-    transformData() {
-        // Transform data to AntD style.
-        const {data, schema, offset} = this;
-        const nf = schema.length;
-        const n = data.length - 1;
-        const colNames = this.fields().map(field => field.column)
-        const {numRows, columns} = data[n];
-        const cols = schema.map(s => columns[s.column]);
-        // Clear data before update
-        this.data_final = []
+    queryResult(data) {
+        // Results from the coordinator reset the table, for example upon
+        // selection or sort updates. Other batches are loaded on demand.
+        this.streamed = null;
+        this.resetRows([toDataColumns(data)]);
+        this.requestCount();
+        return this;
+    }
+
+    update() {
+        this.renderTable();
+        return this;
+    }
+
+    /**
+     * Reset loaded rows to the given data for the first row batch.
+     * Row indices change, so the current row and range anchor are cleared.
+     * Selected rows are tracked by key values and persist.
+     * @param {object[]} data Data columns objects for the first row batch.
+     */
+    resetRows(data) {
+        ++this.generation;
+        this.anchor = -1;
+        this.currentRow = -1;
+        ++this.version;
+        this.cache.clear();
+        this.requested.clear();
+        let offset = 0;
+        const rows = data.flatMap(d => {
+            const r = this.toRows(d, offset);
+            offset += d.numRows;
+            return r;
+        });
+        this.cache.set(0, rows);
+        // a partial first batch indicates the total row count, otherwise
+        // the loaded row count stands in until the count query returns
+        this.counted = rows.length < this.limit;
+        this.total = rows.length;
+    }
+
+    /**
+     * Append streamed rows to the first row batch.
+     * @param {object} data Data columns object of a streamed record batch.
+     * @param {number} offset The row index of the first appended row.
+     */
+    appendRows(data, offset) {
+        const rows = this.cache.get(0);
+        for (const row of this.toRows(data, offset)) rows.push(row);
+        ++this.version;
+        this.total = rows.length;
+    }
+
+    /**
+     * Render the table in the next animation frame, unless a render is
+     * already scheduled.
+     */
+    requestRender() {
+        if (this.frame != null) return;
+        this.frame = requestAnimationFrame(() => {
+            this.frame = null;
+            this.renderTable();
+        });
+    }
+
+    /**
+     * Request the total row count for the current filter criteria.
+     */
+    requestCount() {
+        if (this.counted) return;
+        const generation = this.generation;
+        this.coordinator?.query(this.countQuery(), {type: 'json'})
+            .then(data => {
+                if (generation !== this.generation) return;
+                this.total = Number(Array.from(data)[0]?.count ?? this.total);
+                this.counted = true;
+                this.renderTable();
+            })
+            .catch(err => this.logError(err));
+    }
+
+    /**
+     * Report a failed table query using the coordinator's logger.
+     * @param {*} err The query error.
+     */
+    logError(err) {
+        this.coordinator?.logger().error(err);
+    }
+
+    /**
+     * Return the row object for a row index, or a placeholder row if the row
+     * has not yet been loaded.
+     * @param {number} index The row index.
+     */
+    getRow(index) {
+        const batch = this.cache.get(Math.floor(index / this.limit));
+        return batch?.[index % this.limit] ?? {key: index, __placeholder: true};
+    }
+
+    /**
+     * Load any row batches within the given row range that have not yet
+     * been loaded or requested, and prefetch the subsequent batch.
+     * @param {number} start The start row index (inclusive).
+     * @param {number} end The end row index (exclusive).
+     */
+    handleRange(start, end) {
+        const {cache, requested, limit, coordinator} = this;
+        if (!coordinator || !this.schema || end <= start) return;
+        const b0 = Math.floor(start / limit);
+        const b1 = Math.floor((end - 1) / limit);
+        const generation = this.generation;
+
+        for (let b = b0; b <= b1; ++b) {
+            if (cache.has(b) || requested.has(b)) continue;
+            requested.add(b);
+            coordinator.query(this.batchQuery(b))
+                .then(data => {
+                    if (generation !== this.generation) return;
+                    requested.delete(b);
+                    cache.set(b, this.toRows(toDataColumns(data), b * limit));
+                    this.evict(b0, b1);
+                    ++this.version;
+                    this.renderTable();
+                })
+                .catch(err => {
+                    // after a reset, the batch may have been requested anew
+                    if (generation !== this.generation) return;
+                    requested.delete(b);
+                    this.logError(err);
+                });
+        }
+
+        // Prefetch subsequent data batch
+        if ((b1 + 1) * limit < this.total && !cache.has(b1 + 1)) {
+            coordinator.prefetch(this.batchQuery(b1 + 1))
+                .catch(err => this.logError(err));
+        }
+    }
+
+    /**
+     * Evict cached row batches farthest from the current view.
+     * @param {number} b0 The first batch index in view.
+     * @param {number} b1 The last batch index in view.
+     */
+    evict(b0, b1) {
+        const {cache} = this;
+        if (cache.size <= MAX_CACHED_BATCHES) return;
+        const dist = b => b < b0 ? b0 - b : b > b1 ? b - b1 : 0;
+        const keys = Array.from(cache.keys()).sort((a, b) => dist(b) - dist(a));
+        keys.slice(0, cache.size - MAX_CACHED_BATCHES).forEach(b => cache.delete(b));
+    }
+
+    toRows({numRows, columns}, offset) {
+        const colNames = this.rowColumns();
+        const nf = colNames.length;
+        const cols = colNames.map(name => columns[name]);
+        const rows = [];
         for (let i = 0; i < numRows; ++i) {
             const data = {
                 key: offset + i
             }
             for (let j = 0; j < nf; ++j) {
-                const value = cols[j][i];
-                data[colNames[j]] = value
+                data[colNames[j]] = cols[j][i];
             }
-            this.data_final.push(data)
+            rows.push(data)
         }
+        return rows;
     }
 
-    update() {
-        const {data, limit} = this;
-        const n = data.length - 1;
-        const {numRows} = data[n];
-        this.transformData()
-        if (numRows < limit) {
-            // Data table has been fully loaded
-            this.loaded = true;
+    handleChange(action, filters, sorter) {
+        if (action === 'sort') {
+            this.handleSorting([sorter].flat());
+        } else if (action === 'filter') {
+            this.handleFilter(filters);
         }
-        if (!this.future) {
-            this.future = createFuture();
-            this.root.render(<ShapeletsTable
-                key={this.id}
-                columns={this.columns_final}
-                data={this.data_final}
-                loaded={this.loaded}
-                maxWidth={this.maxWidth}
-                maxHeight={this.height}
-                onAction={this.handleAction.bind(this)}
-                sortFn={this.handleSorting.bind(this)}
-                promise={this.future.promise}
-            />);
+    }
+
+    /**
+     * Update multi-column sort criteria. Previously sorted columns retain
+     * their precedence, newly sorted columns are added last.
+     * @param {object[]} sorters AntD sorter states.
+     */
+    handleSorting(sorters) {
+        const active = sorters.filter(s => s.order && s.field != null);
+        const order = new Map(active.map(s => [s.field, s.order === 'descend']));
+        const sort = this.sort
+            .filter(s => order.has(s.column))
+            .map(s => ({column: s.column, desc: order.get(s.column)}));
+        for (const [name, isDesc] of order) {
+            if (!sort.some(s => s.column === name)) sort.push({column: name, desc: isDesc});
         }
-        this.future.resolve(this.data_final);
-        this.future = null;
+        this.sort = sort;
+        this.requestQuery();
+    }
 
-        this.pending = false;
-        return this;
+    /**
+     * Publish header filter clauses for columns whose filter values changed.
+     * @param {object} filters AntD filter states, keyed by column name.
+     */
+    handleFilter(filters) {
+        const {filterAs, filterValues, schema} = this;
+        if (!filterAs) return;
+        for (const {column: name, type} of schema) {
+            const value = filters[name]?.length ? filters[name] : null;
+            if (JSON.stringify(value) === JSON.stringify(filterValues[name] ?? null)) continue;
+            if (value) filterValues[name] = value;
+            else delete filterValues[name];
+            filterAs.update(headerClause(this.filterSource(name), name, type, value));
+        }
+        this.renderTable();
     }
 
-    handleAction() {
-        this.pending = true;
-        this.requestData(this.offset + this.limit);
-        this.future = createFuture()
-        return this.future.promise
+    /**
+     * Return the selection clause source for header filters of a column.
+     * @param {string} name The column name.
+     */
+    filterSource(name) {
+        let source = this.filterSources.get(name);
+        if (!source) {
+            source = {
+                id: `${this.id}:${name}`,
+                from: this.from,
+                reset: () => {
+                    delete this.filterValues[name];
+                    this.renderTable();
+                }
+            };
+            this.filterSources.set(name, source);
+        }
+        return source;
+    }
+
+    /**
+     * Return the key column values of a row object.
+     * @param {object} row The row object.
+     */
+    rowKey(row) {
+        return this.keyColumns().map(name => row[name]);
+    }
+
+    /**
+     * Indicate if a row object is currently selected.
+     * @param {object} row The row object.
+     */
+    isSelected(row) {
+        return !row.__placeholder && this.selected.has(keyString(this.rowKey(row)));
     }
 
-    handleSorting(columnName, sorting) {
-        this.pending = true;
-        this.sortDesc = sorting === "descend" ? true : false;
-        this.sortColumn = columnName;
-        this.requestData()
-        this.future = createFuture()
-        return this.future.promise
+    /**
+     * Handle a click on a table row. In *multiple* mode, command- or
+     * control-click toggles a row, in *multiple* and *checkbox* modes
+     * shift-click selects the range of rows from the last clicked row.
+     * @param {number} index The row index.
+     * @param {MouseEvent} event The click event.
+     */
+    handleRowClick(index, event) {
+        const {select} = this;
+        if (!select || select === 'hover') return;
+        this.currentRow = index;
+        this.selectRow(index, {
+            range: select !== 'single' && event.shiftKey,
+            toggle: select === 'checkbox' || (select === 'multiple' && (event.metaKey || event.ctrlKey))
+        });
+    }
+
+    /**
+     * Handle row hover, which selects the hovered row in *hover* mode.
+     * @param {number} index The row index, or -1 if no row is hovered.
+     */
+    handleHover(index) {
+        if (this.select !== 'hover' || index === this.currentRow) return;
+        this.currentRow = index;
+        this.selected.clear();
+        const row = index < 0 ? null : this.getRow(index);
+        if (row && !row.__placeholder) {
+            const values = this.rowKey(row);
+            this.selected.set(keyString(values), values);
+        }
+        this.publish();
     }
-}
\ No newline at end of file
+
+    /**
+     * Handle keyboard navigation. Arrow, page, home and end keys move the
+     * current row, with the shift key extending the selected range in
+     * multi-select modes. Enter or space selects the current row, and
+     * escape clears the selection. Not supported in *hover* mode.
+     * @param {KeyboardEvent} event The keyboard event.
+     */
+    handleKey(event) {
+        const {select, total, currentRow} = this;
+        if (!select || select === 'hover' || !total) return;
+        const page = Math.max(1, Math.floor(this.height / this.rowHeight) - 1);
+        let next;
+        switch (event.key) {
+            case 'ArrowDown': next = currentRow + 1; break;
+            case 'ArrowUp': next = currentRow - 1; break;
+            case 'PageDown': next = currentRow + page; break;
+            case 'PageUp': next = currentRow - page; break;
+            case 'Home': next = 0; break;
+            case 'End': next = total - 1; break;
+            case 'Enter':
+            case ' ':
+                event.preventDefault();
+                if (currentRow >= 0) {
+                    this.selectRow(currentRow, {toggle: select !== 'single'});
+                }
+                return;
+            case 'Escape':
+                this.clearSelection();
+                return;
+            default:
+                return;
+        }
+        event.preventDefault();
+        next = Math.max(0, Math.min(total - 1, next));
+        if (event.shiftKey && select !== 'single') {
+            if (this.anchor < 0) this.anchor = Math.max(0, currentRow);
+            this.selectRow(next, {range: true});
+        }
+        this.currentRow = next;
+        this.renderTable();
+    }
+
+    /**
+     * Select a table row, or a range of rows, and publish the selection.
+     * @param {number} index The row index.
+     * @param {object} [options] Selection options.
+     * @param {boolean} [options.range] If true, select the range of rows
+     *  between the anchor row (the last selected row) and the given row.
+     *  The range is limited to at most `MAX_RANGE_ROWS` rows from the anchor.
+     * @param {boolean} [options.toggle] If true, toggle the row or add the
+     *  row range to the current selection. Otherwise, replace the selection.
+     */
+    selectRow(index, {range = false, toggle = false} = {}) {
+        if (range && this.anchor >= 0) {
+            const {anchor} = this;
+            const lo = Math.max(Math.min(anchor, index), anchor - MAX_RANGE_ROWS + 1);
+            const hi = Math.min(Math.max(anchor, index), anchor + MAX_RANGE_ROWS - 1);
+            this.rangeKeys(lo, hi + 1)
+                .then(keys => {
+                    if (!keys) return;
+                    if (!toggle) this.selected.clear();
+                    keys.forEach(values => this.selected.set(keyString(values), values));
+                    this.publish();
+                })
+                .catch(err => this.logError(err));
+            return;
+        }
+
+        const row = this.getRow(index);
+        if (row.__placeholder) return;
+        const {selected} = this;
+        const values = this.rowKey(row);
+        const key = keyString(values);
+        if (toggle && this.select !== 'single') {
+            if (selected.has(key)) selected.delete(key);
+            else selected.set(key, values);
+        } else {
+            // clicking the only selected row clears the selection
+            const only = selected.size === 1 && selected.has(key);
+            selected.clear();
+            if (!only) selected.set(key, values);
+        }
+        this.anchor = index;
+        this.publish();
+    }
+
+    /**
+     * Return a promise for the key column values of a range of rows. Loaded
+     * rows are used directly, otherwise the key values are queried.
+     * @param {number} start The start row index (inclusive).
+     * @param {number} end The end row index (exclusive).
+     * @returns {Promise<any[][] | null>} The key values per row, or null if
+     *  the table was reset before the key values were queried.
+     */
+    rangeKeys(start, end) {
+        const keys = [];
+        for (let i = start; i < end; ++i) {
+            const row = this.getRow(i);
+            if (row.__placeholder) break;
+            keys.push(this.rowKey(row));
+        }
+        if (keys.length === end - start) return Promise.resolve(keys);
+
+        const generation = this.generation;
+        const names = this.keyColumns();
+        return this.coordinator.query(this.keyQuery(start, end))
+            .then(data => {
+                if (generation !== this.generation) return null;
+                const {numRows, columns} = toDataColumns(data);
+                return Array.from({length: numRows}, (_, i) => names.map(name => columns[name][i]));
+            });
+    }
+
+    /**
+     * Clear all selected rows and publish the empty selection.
+     */
+    clearSelection() {
+        this.anchor = -1;
+        this.selected.clear();
+        this.publish();
+    }
+
+    /**
+     * Reset the row selection state. Invoked when a selection clause of this
+     * table is removed by another component, for example by a single
+     * selection.
+     */
+    reset() {
+        this.anchor = -1;
+        this.selected.clear();
+        this.renderTable();
+    }
+
+    /**
+     * Publish the current row selection to the output selection.
+     */
+    publish() {
+        this.selection?.update(this.clause());
+        this.renderTable();
+    }
+
+    /**
+     * Return a selection clause for the selected rows, testing equality of
+     * all key column values. The clause is null if no rows are selected.
+     */
+    clause() {
+        const fields = this.keyColumns().map(name => column(name));
+        const values = this.selected.size ? Array.from(this.selected.values()) : null;
+        return clausePoints(fields, values, {source: this});
+    }
+
+    /**
+     * Synchronize selected rows with the clause for this table, if any, in
+     * the output selection.
+     */
+    syncSelection() {
+        const clause = this.selection.clauses.find(c => c.source === this);
+        const values = clause?.value ?? [];
+        const keys = values.map(keyString);
+        const {selected} = this;
+        if (keys.length === selected.size && keys.every(key => selected.has(key))) return;
+        this.selected = new Map(values.map((v, i) => [keys[i], v]));
+        this.renderTable();
+    }
+}
+
+/**
+ * Return AntD header filter properties for a column of the given type.
+ * @param {string} type The column data type.
+ * @param {any[]} [value] The current filter value.
+ */
+function headerFilter(type, value) {
+    const filteredValue = value ?? null;
+    switch (type) {
+        case 'string':
+            return {filterDropdown: props => <TextFilter {...props}/>, filteredValue};
+        case 'number':
+            return {filterDropdown: props => <RangeFilter {...props}/>, filteredValue};
+        case 'boolean':
+            return {
+                filters: [{text: 'true', value: true}, {text: 'false', value: false}],
+                filteredValue
+            };
+        default:
+            return {};
+    }
+}
+
+/**
+ * Generate a selection clause for a header filter. Clauses have no
+ * associated clients, so the table is filtered even when cross-filtering.
+ * @param {*} source The clause source.
+ * @param {string} name The column name.
+ * @param {string} type The column data type.
+ * @param {any[] | null} value The filter value, or null to clear.
+ */
+function headerClause(source, name, type, value) {
+    const field = column(name);
+    const clients = new Set;
+    if (type === 'string') {
+        return clauseMatch(field, value?.[0] ?? null, {source, clients});
+    } else if (type === 'boolean') {
+        return clausePoints([field], value?.map(v => [v]) ?? null, {source, clients});
+    }
+    const [lo, hi] = value ?? [];
+    if (lo != null && hi != null) {
+        return clauseInterval(field, [lo, hi], {source, clients});
+    }
+    // one-sided ranges are not interval clauses
+    return {
+        source,
+        clients,
+        value,
+        predicate: lo != null ? gte(field, lo) : hi != null ? lte(field, hi) : null
+    };
+}
@@ -1,3 +1,4 @@
+export {KPI, kpi} from './KPI.jsx';
 export {Menu, menu} from './Menu.jsx';
 export {Search, search} from './Search.jsx';
 export {Slider, slider} from './Slider.jsx';
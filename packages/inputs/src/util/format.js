@@ -36,6 +36,22 @@ export function formatTrim(value) {
   return i0 > 0 ? s.slice(0, i0) + s.slice(i1 + 1) : s;
 }
 
+/**
+ * Format a comparison of a value to a total, such as "of 1,200 (25%)".
+ * Values may be BigInts, as returned for integer aggregates.
+ * @param {*} value The value to compare.
+ * @param {*} total The total to compare to.
+ * @param {(value: any) => string} [format] A format function for the total.
+ * @param {boolean} [percent=true] If true, include the value as a
+ *  percentage of the total. Only meaningful for additive values.
+ * @returns {string} The formatted comparison.
+ */
+export function formatShare(value, total, format = formatAuto, percent = true) {
+  const t = Number(total);
+  const pct = percent && t ? ` (${formatTrim((100 * Number(value) / t).toFixed(1))}%)` : '';
+  return `of ${format(total)}${pct}`;
+}
+
 export function formatDate(date) {
   return isoformat(date, 'Invalid Date');
 }
@@ -0,0 +1,34 @@
+// integer aggregates may be BigInts, convert them to numbers
+export const toNumber = value => typeof value === 'bigint' ? Number(value) : value;
+
+/**
+ * Convert sparkline query results to data points, such that BigInt
+ * aggregate values (for example, counts) are mapped to numbers.
+ * @param {Iterable<{ x: any, y: any }>} data The query result rows.
+ * @returns {{ x: any, y: number | null }[]} The sparkline data points.
+ */
+export function sparklineData(data) {
+  return Array.from(data, ({ x, y }) => ({ x, y: toNumber(y) }));
+}
+
+/**
+ * Compute the SVG polyline points of a sparkline. Points with missing
+ * values are skipped.
+ * @param {{ y: number | null }[]} data The sparkline data points.
+ * @param {number} width The sparkline width in pixels.
+ * @param {number} height The sparkline height in pixels.
+ * @returns {string | null} The polyline points, or null if there are
+ *  fewer than two valid values.
+ */
+export function sparklinePoints(data, width, height) {
+  const values = data.map(d => d.y).filter(y => y != null && !Number.isNaN(+y));
+  if (values.length < 2) return null;
+  const lo = Math.min(...values);
+  const hi = Math.max(...values);
+  const sx = width / (data.length - 1);
+  const sy = hi > lo ? (height - 2) / (hi - lo) : 0;
+  return data
+    .map((d, i) => d.y == null ? null : `${i * sx},${height - 1 - (d.y - lo) * sy}`)
+    .filter(p => p)
+    .join(' ');
+}
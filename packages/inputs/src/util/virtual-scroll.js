@@ -0,0 +1,60 @@
+// Maximum pixel height of the virtual scroll area. Browsers limit element
+// heights, so for very large tables scroll positions are scaled to rows.
+export const MAX_SCROLL_HEIGHT = 1e7;
+
+/**
+ * Determine the rows within view for a table scroll position.
+ * @param {number} scrollTop The scroll offset in pixels.
+ * @param {number} total The total number of rows.
+ * @param {number} rowHeight The height of a table row in pixels.
+ * @param {number} height The height of the table view in pixels.
+ * @returns {{start: number, end: number, top: number, bottom: number}}
+ *  The start (inclusive) and end (exclusive) rows in view, along with the
+ *  pixel heights of the spacers above and below those rows.
+ */
+export function viewRange(scrollTop, total, rowHeight, height) {
+  const full = total * rowHeight;
+  const virtual = Math.min(full, MAX_SCROLL_HEIGHT);
+  const visible = Math.ceil(height / rowHeight) + 1;
+  const maxStart = Math.max(0, total - visible);
+  let start, top;
+  if (full === virtual) {
+    start = Math.min(maxStart, Math.floor(scrollTop / rowHeight));
+    top = start * rowHeight;
+  } else {
+    // scale the scroll position to the full row range
+    start = Math.round(Math.min(1, scrollTop / Math.max(1, virtual - height)) * maxStart);
+    top = Math.min(scrollTop, virtual - visible * rowHeight);
+  }
+  const end = Math.min(total, start + visible);
+  const bottom = Math.max(0, virtual - top - (end - start) * rowHeight);
+  return { start, end, top, bottom };
+}
+
+/**
+ * Determine a table scroll position that brings a row fully into view.
+ * @param {number} scrollTop The current scroll offset in pixels.
+ * @param {number} index The row index to bring into view.
+ * @param {number} total The total number of rows.
+ * @param {number} rowHeight The height of a table row in pixels.
+ * @param {number} height The height of the table view in pixels.
+ * @returns {number} The new scroll offset, unchanged if the row is
+ *  already in view.
+ */
+export function scrollToRow(scrollTop, index, total, rowHeight, height) {
+  const full = total * rowHeight;
+  const virtual = Math.min(full, MAX_SCROLL_HEIGHT);
+  if (full === virtual) {
+    const y = index * rowHeight;
+    return y < scrollTop ? y
+      : y + rowHeight > scrollTop + height ? y + rowHeight - height
+      : scrollTop;
+  }
+  // invert the scaled mapping from scroll positions to rows
+  const { start } = viewRange(scrollTop, total, rowHeight, height);
+  const inView = Math.max(1, Math.floor(height / rowHeight));
+  if (index >= start && index < start + inView) return scrollTop;
+  const maxStart = Math.max(1, total - Math.ceil(height / rowHeight) - 1);
+  const target = Math.max(0, index < start ? index : index - inView + 1);
+  return Math.min(1, target / maxStart) * Math.max(1, virtual - height);
+}
@@ -1,6 +1,6 @@
 import assert from 'node:assert';
 import { register, unregister } from 'timezone-mock';
-import { formatDate } from '../src/util/format.js';
+import { formatDate, formatShare } from '../src/util/format.js';
 
 describe('formatDate', () => {
   it('formats ISO dates', async () => {
@@ -13,3 +13,16 @@ describe('formatDate', () => {
     assert.strictEqual(formatDate(new Date('invalid')), 'Invalid Date');
   });
 });
+
+describe('formatShare', () => {
+  it('formats a value as a share of a total', async () => {
+    assert.strictEqual(formatShare(25, 200), 'of 200 (12.5%)');
+    assert.strictEqual(formatShare(5, 0), 'of 0');
+    assert.strictEqual(formatShare(3.5, 4.25, undefined, false), 'of 4.25');
+  });
+
+  it('formats BigInt values and numeric totals', async () => {
+    assert.strictEqual(formatShare(1500n, 3000), 'of 3,000 (50%)');
+    assert.strictEqual(formatShare(1500n, 3000n, String), 'of 3000 (50%)');
+  });
+});
@@ -0,0 +1,29 @@
+import assert from 'node:assert';
+import { sparklineData, sparklinePoints } from '../src/util/sparkline.js';
+
+describe('sparkline', () => {
+  it('plots count aggregates', async () => {
+    // integer counts are returned as BigInt values
+    const data = sparklineData([
+      { x: 1, y: 2n },
+      { x: 2, y: 6n },
+      { x: 3, y: 4n }
+    ]);
+    assert.deepStrictEqual(data, [
+      { x: 1, y: 2 },
+      { x: 2, y: 6 },
+      { x: 3, y: 4 }
+    ]);
+    assert.strictEqual(sparklinePoints(data, 100, 10), '0,9 50,1 100,5');
+  });
+
+  it('skips missing values', async () => {
+    const data = sparklineData([
+      { x: 1, y: 1 },
+      { x: 2, y: null },
+      { x: 3, y: 3 }
+    ]);
+    assert.strictEqual(sparklinePoints(data, 100, 10), '0,9 100,1');
+    assert.strictEqual(sparklinePoints(data.slice(0, 2), 100, 10), null);
+  });
+});
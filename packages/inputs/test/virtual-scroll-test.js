@@ -0,0 +1,55 @@
+import assert from 'node:assert';
+import { MAX_SCROLL_HEIGHT, scrollToRow, viewRange } from '../src/util/virtual-scroll.js';
+
+describe('viewRange', () => {
+  it('maps scroll positions to rows', () => {
+    assert.deepStrictEqual(
+      viewRange(0, 100, 20, 100),
+      { start: 0, end: 6, top: 0, bottom: 1880 }
+    );
+    assert.deepStrictEqual(
+      viewRange(210, 100, 20, 100),
+      { start: 10, end: 16, top: 200, bottom: 1680 }
+    );
+    assert.deepStrictEqual(
+      viewRange(1e6, 100, 20, 100),
+      { start: 94, end: 100, top: 1880, bottom: 0 }
+    );
+  });
+
+  it('scales scroll positions for large tables', () => {
+    const total = 1e8;
+    const height = 100;
+    const first = viewRange(0, total, 20, height);
+    assert.deepStrictEqual(first, { start: 0, end: 6, top: 0, bottom: MAX_SCROLL_HEIGHT - 120 });
+
+    const last = viewRange(MAX_SCROLL_HEIGHT - height, total, 20, height);
+    assert.strictEqual(last.start, total - 6);
+    assert.strictEqual(last.end, total);
+    assert.strictEqual(last.top + 6 * 20 + last.bottom, MAX_SCROLL_HEIGHT);
+
+    const mid = viewRange((MAX_SCROLL_HEIGHT - height) / 2, total, 20, height);
+    assert.strictEqual(mid.start, Math.round((total - 6) / 2));
+    assert.strictEqual(mid.top + 6 * 20 + mid.bottom, MAX_SCROLL_HEIGHT);
+  });
+});
+
+describe('scrollToRow', () => {
+  it('scrolls rows into view', () => {
+    assert.strictEqual(scrollToRow(0, 2, 100, 20, 100), 0);
+    assert.strictEqual(scrollToRow(0, 10, 100, 20, 100), 120);
+    assert.strictEqual(scrollToRow(200, 5, 100, 20, 100), 100);
+  });
+
+  it('scrolls rows into view for large tables', () => {
+    const total = 1e8;
+    const height = 100;
+    assert.strictEqual(scrollToRow(0, 2, total, 20, height), 0);
+    for (const index of [10, 1e3, 5e7, total - 1]) {
+      const top = scrollToRow(0, index, total, 20, height);
+      const { start, end } = viewRange(top, total, 20, height);
+      assert.ok(start <= index && index < end, `row ${index} in view`);
+      assert.ok(top <= MAX_SCROLL_HEIGHT - height);
+    }
+  });
+});
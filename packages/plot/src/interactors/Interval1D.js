@@ -1,4 +1,4 @@
-import { clauseInterval } from '@uwdata/mosaic-core';
+import { clauseInterval, clauseNegate } from '@uwdata/mosaic-core';
 import { ascending, min, max, select } from 'd3';
 import { brushX, brushY } from './util/brush.js';
 import { closeTo } from './util/close-to.js';
@@ -14,13 +14,17 @@ export class Interval1D {
     field = undefined,
     pixelSize = 1,
     peers = true,
-    brush: style
+    brush: style,
+    negate = false,
+    id
   }) {
+    this.id = id;
     this.mark = mark;
     this.channel = channel;
     this.pixelSize = pixelSize || 1;
     this.selection = selection;
     this.peers = peers;
+    this.negate = negate;
     this.field = field || getField(mark, channel);
     this.style = style && sanitizeStyles(style);
     this.brush = channel === 'y' ? brushY() : brushX();
@@ -36,6 +40,20 @@ export class Interval1D {
     this.selection.activate(this.clause(this.value || [0, 1]));
   }
 
+  /**
+   * Move the brush to match a restored selection clause, without
+   * publishing a new clause.
+   * @param {import('@uwdata/mosaic-core').SelectionClause} clause The
+   *  restored clause, with a null value if the selection was cleared.
+   */
+  restore(clause) {
+    this.value = clause.value ?? undefined;
+    if (this.g) {
+      const range = this.value?.map(this.scale.apply).sort(ascending);
+      this.g.call(this.brush.moveSilent, range);
+    }
+  }
+
   publish(extent) {
     let range = undefined;
     if (extent) {
@@ -52,12 +70,13 @@ export class Interval1D {
 
   clause(value) {
     const { mark, pixelSize, field, scale } = this;
-    return clauseInterval(field, value, {
+    const clause = clauseInterval(field, value, {
       source: this,
       clients: this.peers ? mark.plot.markSet : new Set().add(mark),
       scale,
       pixelSize
     });
+    return this.negate ? clauseNegate(clause) : clause;
   }
 
   init(svg, root) {
@@ -85,6 +104,9 @@ export class Interval1D {
       }
     }
 
+    // register a representative clause to enable index prebuilding
+    this.selection.register(this.clause(this.value || [0, 1]));
+
     svg.addEventListener('pointerenter', evt => {
       if (!evt.buttons) this.activate();
     });
@@ -1,4 +1,4 @@
-import { clauseIntervals } from '@uwdata/mosaic-core';
+import { clauseIntervals, clauseNegate } from '@uwdata/mosaic-core';
 import { ascending, min, max, select } from 'd3';
 import { brush } from './util/brush.js';
 import { closeTo } from './util/close-to.js';
@@ -14,12 +14,16 @@ export class Interval2D {
     yfield,
     pixelSize = 1,
     peers = true,
-    brush: style
+    brush: style,
+    negate = false,
+    id
   }) {
+    this.id = id;
     this.mark = mark;
     this.pixelSize = pixelSize || 1;
     this.selection = selection;
     this.peers = peers;
+    this.negate = negate;
     this.xfield = xfield || getField(mark, 'x');
     this.yfield = yfield || getField(mark, 'y');
     this.style = style && sanitizeStyles(style);
@@ -36,6 +40,24 @@ export class Interval2D {
     this.selection.activate(this.clause(this.value || [[0, 1], [0, 1]]));
   }
 
+  /**
+   * Move the brush to match a restored selection clause, without
+   * publishing a new clause.
+   * @param {import('@uwdata/mosaic-core').SelectionClause} clause The
+   *  restored clause, with a null value if the selection was cleared.
+   */
+  restore(clause) {
+    this.value = clause.value ?? undefined;
+    if (this.g) this.g.call(this.brush.moveSilent, this.extent());
+  }
+
+  extent() {
+    if (!this.value) return undefined;
+    const [x1, x2] = this.value[0].map(this.xscale.apply).sort(ascending);
+    const [y1, y2] = this.value[1].map(this.yscale.apply).sort(ascending);
+    return [[x1, y1], [x2, y2]];
+  }
+
   publish(extent) {
     const { value, pixelSize, xscale, yscale } = this;
     let xr = undefined;
@@ -55,12 +77,13 @@ export class Interval2D {
 
   clause(value) {
     const { mark, pixelSize, xfield, yfield, xscale, yscale } = this;
-    return clauseIntervals([xfield, yfield], value, {
+    const clause = clauseIntervals([xfield, yfield], value, {
       source: this,
       clients: this.peers ? mark.plot.markSet : new Set().add(mark),
       scales: [xscale, yscale],
       pixelSize
     });
+    return this.negate ? clauseNegate(clause) : clause;
   }
 
   init(svg) {
@@ -87,11 +110,12 @@ export class Interval2D {
     }
 
     if (this.value) {
-      const [x1, x2] = this.value[0].map(xscale.apply).sort(ascending);
-      const [y1, y2] = this.value[1].map(yscale.apply).sort(ascending);
-      this.g.call(brush.moveSilent, [[x1, y1], [x2, y2]]);
+      this.g.call(brush.moveSilent, this.extent());
     }
 
+    // register a representative clause to enable index prebuilding
+    this.selection.register(this.clause(this.value || [[0, 1], [0, 1]]));
+
     svg.addEventListener('pointerenter', evt => {
       if (!evt.buttons) this.activate();
     });
@@ -8,8 +8,10 @@ export class Nearest {
     pointer,
     channels,
     fields,
-    maxRadius = 40
+    maxRadius = 40,
+    id
   }) {
+    this.id = id;
     this.mark = mark;
     this.selection = selection;
     this.clients = new Set().add(mark);
@@ -72,6 +74,9 @@ export class Nearest {
       selection.update(that.clause(undefined));
     });
 
+    // register a representative clause to enable index prebuilding
+    selection.register(this.clause(this.channels.map(() => 0)));
+
     // trigger activation updates
     svg.addEventListener('pointerenter', evt => {
       if (!evt.buttons) {
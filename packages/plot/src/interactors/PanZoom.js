@@ -12,8 +12,10 @@ export class PanZoom {
     yfield,
     zoom = true,
     panx = true,
-    pany = true
+    pany = true,
+    id
   }) {
+    this.id = id;
     this.mark = mark;
     this.xsel = x;
     this.ysel = y;
@@ -81,6 +83,14 @@ export class PanZoom {
 
     select(element).call(z);
 
+    // register representative clauses to enable index prebuilding
+    if (panx) {
+      xsel.register(this.clause(this.xscale.domain, this.xfield, this.xscale));
+    }
+    if (pany) {
+      ysel.register(this.clause(this.yscale.domain, this.yfield, this.yscale));
+    }
+
     if (panx || pany) {
       let enter = false;
       element.addEventListener('pointerenter', evt => {
@@ -8,8 +8,10 @@ export class Toggle {
   constructor(mark, {
     selection,
     channels,
-    peers = true
+    peers = true,
+    id
   }) {
+    this.id = id;
     this.value = null;
     this.mark = mark;
     this.selection = selection;
@@ -51,6 +53,9 @@ export class Toggle {
     selector ??= `[data-index="${mark.index}"]`;
     const groups = new Set(svg.querySelectorAll(selector));
 
+    // register a representative clause to enable index prebuilding
+    selection.register(this.clause([this.fields.map(() => 0)]));
+
     svg.addEventListener('pointerdown', evt => {
       const state = selection.single ? selection.value : this.value;
       const target = evt.target;
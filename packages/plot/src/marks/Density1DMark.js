@@ -28,6 +28,14 @@ export class Density1DMark extends Mark {
     });
   }
 
+  /**
+   * Binned densities are counts or sums over the data, which are not
+   * representative when computed over a data sample.
+   */
+  get progressive() {
+    return false;
+  }
+
   get filterIndexable() {
     const name = this.dim === 'x' ? 'xDomain' : 'yDomain';
     const dom = this.plot.getAttribute(name);
@@ -17,8 +17,8 @@ export class GeoMark extends Mark {
     super('geo', source, encodings, reqs);
   }
 
-  queryResult(data) {
-    super.queryResult(data); // map to columns, set this.data
+  queryResult(data, info) {
+    super.queryResult(data, info); // map to columns, set this.data
 
     // look for an explicit geometry field
     const geom = this.channelField('geometry')?.as;
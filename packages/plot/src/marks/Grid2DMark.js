@@ -70,6 +70,14 @@ export class Grid2DMark extends Mark {
     super.setPlot(plot, index);
   }
 
+  /**
+   * Binned densities are counts or sums over the data, which are not
+   * representative when computed over a data sample.
+   */
+  get progressive() {
+    return false;
+  }
+
   get filterIndexable() {
     const xdom = this.plot.getAttribute('xDomain');
     const ydom = this.plot.getAttribute('yDomain');
@@ -19,6 +19,14 @@ const fieldEntry = (channel, field) => ({
 });
 const valueEntry = (channel, value) => ({ channel, value });
 
+// aggregates whose results over a data sample estimate the full result
+const SAMPLE_INVARIANT = new Set([
+  'avg', 'mad', 'median', 'quantile', 'mode',
+  'variance', 'stddev', 'var_pop', 'stddev_pop', 'skewness', 'kurtosis',
+  'corr', 'covar_samp', 'covar_pop', 'regr_intercept', 'regr_slope',
+  'regr_r2', 'regr_avgx', 'regr_avgy'
+]);
+
 // checks if a data source is an explicit array of values
 // as opposed to a database table refernece
 export const isDataArray = source => Array.isArray(source);
@@ -107,6 +115,19 @@ export class Mark extends MosaicClient {
     return !!this._fieldInfo;
   }
 
+  /**
+   * Marks can render approximate results of sampled queries, which
+   * are refined once the full query completes. Marks with aggregates
+   * that depend on the number of sampled rows, such as counts or sums,
+   * are not sampled, as their approximate results would be misleading.
+   */
+  get progressive() {
+    return this.channels.every(({ field }) => {
+      const op = field?.aggregate;
+      return !op || SAMPLE_INVARIANT.has(`${op}`.toLowerCase());
+    });
+  }
+
   channel(channel) {
     return this.channels.find(c => c.channel === channel);
   }
@@ -165,8 +186,13 @@ export class Mark extends MosaicClient {
 
   /**
    * Provide query result data to the mark.
+   * @param {*} data The query result.
+   * @param {object} [info] Query result metadata.
+   * @param {boolean} [info.approximate] True if the result is an
+   *  approximation computed over a data sample.
    */
-  queryResult(data) {
+  queryResult(data, info) {
+    this.approximate = !!info?.approximate;
     this.data = toDataColumns(data);
     return this;
   }
@@ -22,6 +22,36 @@ export class RasterMark extends Grid2DMark {
   constructor(source, options) {
     super('image', source, options);
     this.image = null;
+    this.batches = [];
+    this.frame = null;
+  }
+
+  queryPending() {
+    this.clearBatches();
+    return super.queryPending();
+  }
+
+  queryResultBatch(batch) {
+    // render a partial raster from the record batches received so far
+    // rasters are rendered at most once per animation frame
+    this.batches.push(batch);
+    this.frame ??= requestAnimationFrame(() => {
+      const [data, ...rest] = this.batches;
+      this.frame = null;
+      super.queryResult(rest.length ? data.concat(...rest) : data).update();
+    });
+    return this;
+  }
+
+  queryResult(data) {
+    this.clearBatches();
+    return super.queryResult(data);
+  }
+
+  clearBatches() {
+    if (this.frame != null) cancelAnimationFrame(this.frame);
+    this.frame = null;
+    this.batches = [];
   }
 
   setPlot(plot, index) {
@@ -50,7 +50,8 @@ export class RegressionMark extends Mark {
       .groupby(groupby);
   }
 
-  queryResult(data) {
+  queryResult(data, info) {
+    this.approximate = !!info?.approximate;
     this.modelFit = toDataColumns(data);
 
     // regression line
@@ -70,6 +70,11 @@ export class Plot {
       return include ? el : [];
     });
     this.element.replaceChildren(svg, ...legends);
+    // flag plots that show approximate results of sampled queries
+    this.element.toggleAttribute(
+      'data-approximate',
+      this.marks.some(mark => mark.approximate)
+    );
     this.synch.resolve();
   }
 
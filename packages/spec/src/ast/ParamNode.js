@@ -1,19 +1,25 @@
+import { Selection } from '@uwdata/mosaic-core';
 import { isArray, isObject, isoparse } from '../util.js';
 import { ASTNode } from './ASTNode.js';
-import { CROSSFILTER, INTERSECT, PARAM, SINGLE, UNION, VALUE } from '../constants.js';
+import {
+  CROSSFILTER, INTERSECT, PARAM, PERFIELD, SINGLE, UNION, VALUE
+} from '../constants.js';
 import { SelectionNode } from './SelectionNode.js';
 
-const paramTypes = new Set([VALUE, SINGLE, CROSSFILTER, INTERSECT, UNION]);
+const paramTypes = new Set([
+  VALUE, SINGLE, CROSSFILTER, INTERSECT, UNION, PERFIELD
+]);
 
 export function parseParam(spec, ctx) {
   const param = isObject(spec) ? spec : { value: spec };
-  const { select = VALUE, cross, empty, date, value } = param;
-  if (!paramTypes.has(select)) {
+  const { select = VALUE, cross, empty, relations, date, value } = param;
+  // custom selection types must be registered using registerResolver
+  if (!paramTypes.has(select) && !Selection.has(select)) {
     ctx.error(`Unrecognized param type: ${select}`, param);
   }
 
   if (select !== VALUE) {
-    return new SelectionNode(select, cross, empty);
+    return new SelectionNode(select, cross, empty, relations);
   } else if (isArray(value)) {
     return new ParamNode(value.map(v => ctx.maybeParam(v)));
   } else {
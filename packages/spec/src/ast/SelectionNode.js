@@ -1,30 +1,43 @@
 import { ASTNode } from './ASTNode.js';
-import { INTERSECT, SELECTION } from '../constants.js';
+import {
+  CROSSFILTER, INTERSECT, PERFIELD, SELECTION, SINGLE, UNION
+} from '../constants.js';
+
+const selectTypes = new Set([CROSSFILTER, INTERSECT, PERFIELD, SINGLE, UNION]);
 
 export class SelectionNode extends ASTNode {
-  constructor(select = INTERSECT, cross, empty) {
+  constructor(select = INTERSECT, cross, empty, relations) {
     super(SELECTION);
     this.select = select;
     this.cross = cross;
     this.empty = empty;
+    this.relations = relations;
   }
 
   instantiate(ctx) {
-    const { select, cross, empty } = this;
-    return ctx.api.Selection[select]({ cross, empty });
+    const { select, cross, empty, relations } = this;
+    return ctx.api.Selection.create(select, { cross, empty, relations });
   }
 
   codegen(ctx) {
-    const { select, cross, empty } = this;
-    const args = [['cross', cross], ['empty', empty]]
+    const { select, cross, empty, relations } = this;
+    const args = [
+      ['cross', cross],
+      ['empty', empty],
+      ['relations', relations && JSON.stringify(relations)]
+    ]
       .filter(a => a[1] != null)
       .map(a => `${a[0]}: ${a[1]}`);
     const arg = args.length ? `{ ${args.join(', ')} }` : '';
-    return `${ctx.ns()}Selection.${select}(${arg})`;
+    if (selectTypes.has(select)) {
+      return `${ctx.ns()}Selection.${select}(${arg})`;
+    }
+    const name = JSON.stringify(select);
+    return `${ctx.ns()}Selection.create(${name}${arg ? `, ${arg}` : ''})`;
   }
 
   toJSON() {
-    const { select, cross, empty } = this;
-    return { select, cross, empty };
+    const { select, cross, empty, relations } = this;
+    return { select, cross, empty, relations };
   }
 }
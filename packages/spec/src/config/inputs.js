@@ -4,6 +4,7 @@
  */
 export function inputNames(overrides = []) {
   return new Set([
+    'kpi',
     'menu',
     'search',
     'slider',
@@ -22,6 +22,7 @@ export const CROSSFILTER = 'crossfilter';
 export const INTERSECT = 'intersect';
 export const UNION = 'union';
 export const SINGLE = 'single';
+export const PERFIELD = 'perField';
 
 // data definitions
 export const DATA = 'data';
@@ -164,10 +164,23 @@ export interface Table {
    */
   input: 'table';
   /**
-   * The output selection. A selection clause is added for each
-   * currently selected table row.
+   * The output selection. A selection clause is added for the
+   * currently selected table rows.
    */
   as?: ParamRef;
+  /**
+   * The row selection mode (default `"single"`). In `"hover"` mode the
+   * row under the pointer is selected. In `"single"` mode a click selects
+   * one row. In `"multiple"` mode a click selects one row, command- or
+   * control-click toggles rows, and shift-click selects a row range. In
+   * `"checkbox"` mode a click toggles rows and shift-click adds a row range.
+   */
+  select?: 'hover' | 'single' | 'multiple' | 'checkbox';
+  /**
+   * One or more key column names that identify selected rows. Selection
+   * clauses test equality of key column values. Defaults to all columns.
+   */
+  primaryKey?: string | string[];
   /**
    * The name of a database table to use as a data source for this widget.
    */
@@ -181,6 +194,16 @@ export interface Table {
    * A selection to filter the database table indicated by the `from` property.
    */
   filterBy?: ParamRef;
+  /**
+   * The output selection for column header filters. A selection clause is
+   * added for each filtered column. Defaults to the `filterBy` selection.
+   */
+  filterAs?: ParamRef;
+  /**
+   * A flag indicating if column header filters are shown (default `true`).
+   * Header filters require a `filterAs` or `filterBy` selection.
+   */
+  filters?: boolean;
   /**
    * An object of per-column alignment values.
    * Column names should be object keys, which map to alignment values.
@@ -203,7 +226,49 @@ export interface Table {
    */
   height?: number;
   /**
-   * The number of rows load in a new batch upon table scroll.
+   * The number of rows to load in each batch upon table scroll.
    */
   rowBatch?: number;
+  /**
+   * The height of a table row, in pixels (default `39`).
+   */
+  rowHeight?: number;
+}
+
+/** A KPI (key performance indicator) summary component. */
+export interface KPI {
+  /**
+   * A summary card showing a scalar aggregate value.
+   */
+  input: 'kpi';
+  /**
+   * The name of a database table to summarize.
+   */
+  from: string;
+  /**
+   * The name of a database column to aggregate. Not required for counts.
+   */
+  column?: string;
+  /**
+   * The aggregate function to apply (default `'count'`).
+   */
+  aggregate?: 'count' | 'sum' | 'avg' | 'min' | 'max' | 'median';
+  /**
+   * A selection to filter the database table indicated by the `from` property.
+   */
+  filterBy?: ParamRef;
+  /**
+   * A text label for this summary.
+   */
+  label?: string;
+  /**
+   * A flag indicating if the filtered value should be compared to the
+   * unfiltered total (default `true`).
+   */
+  compare?: boolean;
+  /**
+   * The name of a database column to group by in order to show a sparkline
+   * of the aggregate value, such as a date column.
+   */
+  sparkline?: string;
 }
@@ -50,8 +50,13 @@ export interface Selection {
    * - `"union"` for a `Selection` that unions clauses (logical "or")
    * - `"single"` for a `Selection` that retains a single clause only
    * - `"crossfilter"` for a cross-filtered intersection `Selection`
+   * - `"perField"` for a `Selection` that unions clauses over the same
+   *   field(s) and intersects clauses over different fields
+   * - the name of a custom resolution strategy added using
+   *   `registerResolver`
    */
-  select: 'crossfilter' | 'intersect' | 'single' | 'union';
+  select: 'crossfilter' | 'intersect' | 'perField' | 'single' | 'union'
+    | (string & {});
 
   /**
    * A flag for cross-filtering, where selections made in a plot filter others
@@ -65,6 +70,14 @@ export interface Selection {
    * false, a selection with no clauses selects all values.
    */
   empty?: boolean;
+
+  /**
+   * Relationships between join key columns of different tables, as pairs of
+   * `"table.column"` strings such as `["flights.carrier", "carriers.code"]`.
+   * Clauses over one table filter clients of a related table using semi-join
+   * (`IN (SELECT ...)`) predicates.
+   */
+  relations?: [string, string][];
 }
 
 /** A Param or Selection definition. */
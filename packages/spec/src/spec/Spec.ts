@@ -4,7 +4,7 @@ import { HConcat } from './HConcat.js';
 import { VConcat } from './VConcat.js';
 import { HSpace } from './HSpace.js';
 import { VSpace } from './VSpace.js';
-import { Menu, Search, Slider, Table } from './Input.js';
+import { KPI, Menu, Search, Slider, Table } from './Input.js';
 import { Plot } from './Plot.js';
 import { PlotMark } from './PlotMark.js';
 import { Legend } from './PlotLegend.js';
@@ -58,6 +58,7 @@ export type Component =
   | VConcat
   | HSpace
   | VSpace
+  | KPI
   | Menu
   | Search
   | Slider
@@ -48,6 +48,12 @@ export interface Interval1DOptions {
    * interactor's selection in cross-filtering setups.
    */
   peers?: boolean;
+  /**
+   * A flag indicating if the selection should be negated (default `false`).
+   * If true, the selection clause selects all values outside of the
+   * brushed interval.
+   */
+  negate?: boolean;
   /**
    * CSS styles for the brush (SVG `rect`) element.
    */
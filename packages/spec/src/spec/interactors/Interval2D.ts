@@ -32,6 +32,12 @@ export interface Interval2DOptions {
    * interactor's selection in cross-filtering setups.
    */
   peers?: boolean;
+  /**
+   * A flag indicating if the selection should be negated (default `false`).
+   * If true, the selection clause selects all values outside of the
+   * brushed interval.
+   */
+  negate?: boolean;
   /**
    * CSS styles for the brush (SVG `rect`) element.
    */
@@ -34,10 +34,13 @@ export {
   gte,
   isBetween,
   isNotBetween,
+  isIn,
+  isNotIn,
   isDistinct,
   isNotDistinct,
   isNull,
-  isNotNull
+  isNotNull,
+  isNotTrue
 } from './operators.js';
 
 export {
@@ -145,6 +148,16 @@ export {
   scaleTransform
 } from './scales.js';
 
+export {
+  createdTables,
+  isIndexTable,
+  mutatedTables,
+  sqlTables,
+  tableMutations,
+  tableName,
+  tableParts
+} from './tables.js';
+
 export { create } from './load/create.js';
 export { loadExtension } from './load/extension.js';
 export {
@@ -1,5 +1,7 @@
 import { sql } from './expression.js';
+import { literal } from './literal.js';
 import { asColumn } from './ref.js';
+import { repeat } from './repeat.js';
 
 function visit(callback) {
   callback(this.op, this);
@@ -29,6 +31,7 @@ const unaryPostOp = op => a => sql`(${asColumn(a)} ${op})`.annotate({ op, a, vis
 
 export const isNull = unaryPostOp('IS NULL');
 export const isNotNull = unaryPostOp('IS NOT NULL');
+export const isNotTrue = unaryPostOp('IS NOT TRUE');
 
 const binaryOp = op => (a, b) => sql`(${asColumn(a)} ${op} ${asColumn(b)})`.annotate({ op, a, b, visit });
 
@@ -52,3 +55,17 @@ function rangeOp(op, a, range, exclusive) {
 
 export const isBetween = (a, range, exclusive) => rangeOp('BETWEEN', a, range, exclusive);
 export const isNotBetween = (a, range, exclusive) => rangeOp('NOT BETWEEN', a, range, exclusive);
+
+function listOp(op, a, values) {
+  a = asColumn(a);
+  const expr = !values ? sql``
+    : !values.length ? sql`${op === 'IN' ? 'FALSE' : 'TRUE'}`
+    : sql(
+        ['(', ` ${op} (`, ...repeat(values.length - 1, ', '), '))'],
+        a, ...values.map(literal)
+      );
+  return expr.annotate({ op, visit, field: a, values });
+}
+
+export const isIn = (a, values) => listOp('IN', a, values);
+export const isNotIn = (a, values) => listOp('NOT IN', a, values);
@@ -0,0 +1,172 @@
+// matches a possibly qualified and quoted table name
+const NAME = '((?:"(?:[^"]|"")+"|[\\w$]+)(?:\\s*\\.\\s*(?:"(?:[^"]|"")+"|[\\w$]+))*)';
+
+// statements that modify the contents or definition of an existing table,
+// paired with a flag indicating if the statement only appends rows
+// the Python data server (duckdb-server/pkg/query.py) mirrors these patterns
+// and both are tested against the cases in test/table-mutations.json
+const MUTATIONS = [
+  [`\\bINSERT\\s+INTO\\s+${NAME}`, true],
+  [`\\bCOPY\\s+${NAME}\\s+FROM\\b`, true],
+  [`\\bINSERT\\s+OR\\s+(?:REPLACE|IGNORE)\\s+INTO\\s+${NAME}`, false],
+  [`\\bINSERT\\s+INTO\\s+${NAME}[^;]*\\bON\\s+CONFLICT\\b`, false],
+  [`\\bUPDATE\\s+(?!SET\\b)${NAME}`, false],
+  [`\\bDELETE\\s+FROM\\s+${NAME}`, false],
+  [`\\bTRUNCATE\\s+(?:TABLE\\s+)?${NAME}`, false],
+  [`\\bCREATE\\s+OR\\s+REPLACE\\s+(?:(?:TEMP|TEMPORARY)\\s+)?(?:TABLE|VIEW)\\s+${NAME}`, false],
+  [`\\b(?:DROP|ALTER)\\s+(?:TABLE|VIEW)\\s+(?:IF\\s+EXISTS\\s+)?${NAME}`, false]
+].map(([pattern, append]) => [new RegExp(pattern, 'gi'), append]);
+
+// table references, excluding table functions such as read_parquet(...),
+// the IS [NOT] DISTINCT FROM operator, and numeric literals
+const REFERENCES = new RegExp(
+  '\\b(?:(?<!\\bDISTINCT\\s+)FROM|JOIN|INTO|UPDATE|TABLE|VIEW|COPY)\\s+'
+    + `(?:IF\\s+(?:NOT\\s+)?EXISTS\\s+)?(?!\\d)${NAME}(?![\\w$."]|\\s*\\()`,
+  'gi'
+);
+
+// string literals, which may contain SQL keywords
+const STRINGS = /'(?:[^']|'')*'/g;
+
+// function calls whose arguments may include a FROM keyword
+const FROM_CALLS = /\b(?:EXTRACT|SUBSTRING|TRIM|OVERLAY)\s*\(/gi;
+
+// common table expression definitions
+const CTES = new RegExp(
+  `(?:\\bWITH(?:\\s+RECURSIVE)?|,)\\s*${NAME}\\s+AS\\s+`
+    + '(?:(?:NOT\\s+)?MATERIALIZED\\s+)?\\(',
+  'gi'
+);
+
+// statements that create a new table or view
+const CREATES = new RegExp(
+  '\\bCREATE\\s+(?:OR\\s+REPLACE\\s+)?(?:(?:TEMP|TEMPORARY)\\s+)?'
+    + `(?:TABLE|VIEW)\\s+(?:IF\\s+NOT\\s+EXISTS\\s+)?${NAME}`,
+  'gi'
+);
+
+/**
+ * Normalize a table name for comparison. Schema and database qualifiers
+ * and quotes are removed and the name is lower-cased. As a result, tables
+ * with the same name in different schemas are treated as the same table,
+ * which errs on the side of invalidating more cached results.
+ * @param {string} name The table name.
+ * @returns {string} The normalized table name.
+ */
+export function tableName(name) {
+  const parts = tableParts(name);
+  return parts[parts.length - 1];
+}
+
+/**
+ * Indicate if a table is one of Mosaic's own data cube index tables,
+ * including the table that tracks index table usage. User queries do not
+ * read these tables, so their modification does not affect cached results
+ * whose tables are unknown.
+ * @param {string} name The table name.
+ * @returns {boolean} True if the table is a data cube index table.
+ */
+export function isIndexTable(name) {
+  return tableName(name).startsWith('cube_index_');
+}
+
+/**
+ * Split a possibly qualified table name into normalized parts. Quotes are
+ * removed and the parts are lower-cased.
+ * @param {string} name The table name.
+ * @returns {string[]} The name parts, such as [database, schema, table].
+ */
+export function tableParts(name) {
+  const parts = `${name}`.match(/"(?:[^"]|"")+"|[^."\s]+/g) ?? [''];
+  return parts.map(part => part
+    .replace(/^"(.*)"$/, '$1')
+    .replaceAll('""', '"')
+    .toLowerCase()
+  );
+}
+
+/**
+ * Determine the normalized names of the tables modified by a SQL string,
+ * such as by insert, update, delete, replace, alter, or drop statements.
+ * The statements are matched by pattern rather than parsed, such that the
+ * result may include extra tables but should not miss a modified table.
+ * @param {string} sql The SQL string, possibly containing multiple
+ *  statements.
+ * @returns {string[]} The normalized names of the modified tables.
+ */
+export function mutatedTables(sql) {
+  return Array.from(tableMutations(sql).keys());
+}
+
+/**
+ * Determine the tables modified by a SQL string, along with whether each
+ * table is only appended to, as by insert or copy statements. Tables that
+ * are also modified in other ways are not append-only.
+ * @param {string} sql The SQL string, possibly containing multiple
+ *  statements.
+ * @returns {Map<string, boolean>} A map from normalized names of modified
+ *  tables to a flag indicating if the table is only appended to.
+ */
+export function tableMutations(sql) {
+  const tables = new Map;
+  for (const [re, append] of MUTATIONS) {
+    for (const [, name] of `${sql}`.matchAll(re)) {
+      const table = tableName(name);
+      tables.set(table, (tables.get(table) ?? true) && append);
+    }
+  }
+  return tables;
+}
+
+/**
+ * Determine the tables referenced by a SQL string, such as in from and join
+ * clauses or as the target of a create or modify statement. The statements
+ * are matched by pattern rather than parsed. References to common table
+ * expressions and table functions are not included.
+ * @param {string} sql The SQL string, possibly containing multiple
+ *  statements.
+ * @returns {string[][]} The normalized parts of each referenced table name.
+ */
+export function sqlTables(sql) {
+  const text = maskSQL(sql);
+  const names = new Set;
+  for (const [, name] of text.matchAll(REFERENCES)) {
+    names.add(tableParts(name).join('.'));
+  }
+  for (const [, name] of text.matchAll(CTES)) {
+    names.delete(tableParts(name).join('.'));
+  }
+  return Array.from(names, name => name.split('.'));
+}
+
+/**
+ * Blank out parts of a SQL string that may contain a FROM keyword that is
+ * not followed by a table reference: the contents of string literals and
+ * the arguments of function calls such as EXTRACT(year FROM date).
+ * @param {string} sql The SQL string.
+ * @returns {string} The masked SQL string.
+ */
+function maskSQL(sql) {
+  let text = `${sql}`.replace(STRINGS, s => `'${' '.repeat(s.length - 2)}'`);
+  for (const match of text.matchAll(FROM_CALLS)) {
+    const start = match.index + match[0].length;
+    let depth = 1;
+    let end = start;
+    for (; end < text.length && depth > 0; ++end) {
+      if (text[end] === '(') ++depth;
+      else if (text[end] === ')') --depth;
+    }
+    text = text.slice(0, start) + ' '.repeat(end - 1 - start) + text.slice(end - 1);
+  }
+  return text;
+}
+
+/**
+ * Determine the tables or views created by a SQL string.
+ * @param {string} sql The SQL string, possibly containing multiple
+ *  statements.
+ * @returns {string[][]} The normalized parts of each created table name.
+ */
+export function createdTables(sql) {
+  return Array.from(`${sql}`.matchAll(CREATES), ([, name]) => tableParts(name));
+}
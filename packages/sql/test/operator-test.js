@@ -1,10 +1,10 @@
 import assert from 'node:assert';
 import {
   column, and, or, not,
-  isNull, isNotNull,
+  isNull, isNotNull, isNotTrue,
   eq, neq, lt, gt, lte, gte,
   isDistinct, isNotDistinct,
-  isBetween, isNotBetween
+  isBetween, isNotBetween, isIn, isNotIn
 } from '../src/index.js';
 
 describe('Logical operators', () => {
@@ -47,6 +47,10 @@ describe('Unary operators', () => {
     assert.strictEqual(String(isNotNull(column('foo'))), '("foo" IS NOT NULL)');
     assert.strictEqual(String(isNotNull('foo')), '("foo" IS NOT NULL)');
   });
+  it('include IS NOT TRUE expressions', () => {
+    assert.strictEqual(String(isNotTrue(column('foo'))), '("foo" IS NOT TRUE)');
+    assert.strictEqual(String(isNotTrue('foo')), '("foo" IS NOT TRUE)');
+  });
 });
 
 describe('Binary operators', () => {
@@ -102,3 +106,18 @@ describe('Range operators', () => {
     assert.strictEqual(String(isNotBetween('a', [0, 1], true)), 'NOT (0 <= "a" AND "a" < 1)');
   });
 });
+
+describe('List operators', () => {
+  it('test list membership', () => {
+    assert.strictEqual(String(isIn('a', null)), '');
+    assert.strictEqual(String(isIn('a', [])), 'FALSE');
+    assert.strictEqual(String(isIn('a', [1])), '("a" IN (1))');
+    assert.strictEqual(String(isIn('a', [1, 'b'])), '("a" IN (1, \'b\'))');
+    assert.strictEqual(isIn('a', [1]).op, 'IN');
+  });
+  it('test list non-membership', () => {
+    assert.strictEqual(String(isNotIn('a', null)), '');
+    assert.strictEqual(String(isNotIn('a', [])), 'TRUE');
+    assert.strictEqual(String(isNotIn('a', [1, 2])), '("a" NOT IN (1, 2))');
+  });
+});
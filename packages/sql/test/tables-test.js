@@ -0,0 +1,24 @@
+import assert from 'node:assert';
+import { readFileSync } from 'node:fs';
+import { mutatedTables, sqlTables, tableMutations } from '../src/index.js';
+
+// cases shared with the Python data server tests (duckdb-server)
+const cases = JSON.parse(
+  readFileSync(new URL('./table-mutations.json', import.meta.url), 'utf8')
+);
+
+describe('Table matching', () => {
+  it('determines modified tables', () => {
+    for (const { sql, mutations } of cases) {
+      assert.deepStrictEqual(Object.fromEntries(tableMutations(sql)), mutations, sql);
+      assert.deepStrictEqual(mutatedTables(sql), Object.keys(mutations), sql);
+    }
+  });
+
+  it('determines referenced tables', () => {
+    for (const { sql, tables } of cases) {
+      const names = new Set(sqlTables(sql).map(parts => parts.at(-1)));
+      assert.deepStrictEqual(Array.from(names).sort(), tables, sql);
+    }
+  });
+});
@@ -1,7 +1,9 @@
 export {
+  History,
   Param,
   Selection,
-  coordinator
+  coordinator,
+  queryParam
 } from '@uwdata/mosaic-core';
 
 export {
@@ -66,6 +68,7 @@ export {
 } from '@uwdata/mosaic-sql';
 
 export {
+  kpi,
   menu,
   search,
   slider,
@@ -82,6 +85,10 @@ export {
   vspace
 } from './layout/space.js';
 
+export {
+  profiler
+} from './profiler.js';
+
 export {
   name,
   margins,
@@ -1,4 +1,4 @@
-import { Menu, Search, Slider, Table } from '@uwdata/mosaic-inputs';
+import { KPI, Menu, Search, Slider, Table } from '@uwdata/mosaic-inputs';
 import { connect } from './connect.js';
 
 function input(ctx, InputClass, options) {
@@ -7,6 +7,10 @@ function input(ctx, InputClass, options) {
   return input.element;
 }
 
+export function kpi(options) {
+  return input(this, KPI, options);
+}
+
 export function menu(options) {
   return input(this, Menu, options);
 }
@@ -0,0 +1,92 @@
+import { coordinator } from '@uwdata/mosaic-core';
+
+const COLUMNS = ['Client', 'Flags', 'Wait', 'Time', 'Bytes', 'Query'];
+
+/**
+ * Create a devtools panel listing recently completed queries along with
+ * their telemetry: the requesting client, whether the query was cached,
+ * consolidated, or served by a data cube index, and queue wait, execution
+ * time, and result size. Clicking a row requests and displays the query
+ * plan with runtime statistics (EXPLAIN ANALYZE).
+ * @param {object} [options] Profiler options.
+ * @param {number} [options.max=50] The maximum number of queries to list.
+ * @returns {HTMLElement} The profiler panel element. The `dispose` method
+ *  of the element value removes the coordinator event listener.
+ */
+export function profiler({ max = 50 } = {}) {
+  const mc = this?.context?.coordinator ?? coordinator();
+
+  const div = document.createElement('div');
+  div.style.font = '12px monospace';
+
+  const table = document.createElement('table');
+  const thead = table.createTHead().insertRow();
+  for (const name of COLUMNS) {
+    const th = document.createElement('th');
+    th.textContent = name;
+    th.style.textAlign = 'left';
+    thead.appendChild(th);
+  }
+  const tbody = table.createTBody();
+
+  const plan = document.createElement('pre');
+  plan.style.whiteSpace = 'pre-wrap';
+
+  const listener = trace => {
+    const row = tbody.insertRow(0);
+    const cells = [
+      clientName(trace.client),
+      flags(trace),
+      ms(trace.wait),
+      ms(trace.time),
+      trace.bytes ?? '',
+      trace.sql ?? ''
+    ];
+    for (const text of cells) {
+      row.insertCell().textContent = text;
+    }
+    if (trace.error) row.style.color = 'red';
+    if (trace.sql && trace.type !== 'exec') {
+      row.style.cursor = 'pointer';
+      row.addEventListener('click', async () => {
+        plan.textContent = 'Loading query plan...';
+        try {
+          plan.textContent = await mc.explain(trace.sql);
+        } catch (err) {
+          plan.textContent = `${err}`;
+        }
+      });
+    }
+    while (tbody.rows.length > max) {
+      tbody.deleteRow(-1);
+    }
+  };
+  mc.addEventListener('query', listener);
+
+  div.appendChild(table);
+  div.appendChild(plan);
+  return Object.assign(div, {
+    value: {
+      element: div,
+      dispose: () => mc.removeEventListener('query', listener)
+    }
+  });
+}
+
+function clientName(client) {
+  return client ? client.constructor?.name ?? 'client' : '';
+}
+
+function flags(trace) {
+  return [
+    trace.cached && 'cached',
+    trace.consolidated && 'consolidated',
+    trace.cube && 'cube',
+    trace.sample && 'sample',
+    trace.status !== 'done' && trace.status
+  ].filter(x => x).join(' ');
+}
+
+function ms(value) {
+  return value == null ? '' : `${value.toFixed(1)}ms`;
+}